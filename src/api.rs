@@ -0,0 +1,247 @@
+// The curated entry points of the crate. Each type runs one party over a `Channel`
+// and forwards to the drivers in `ot_async`.
+
+use rand::{CryptoRng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::bytes;
+use crate::channel::Channel;
+use crate::common::{bool_vec_to_byte_vec, MessagePairs, SecretBits, SecretRows, Zeroizing};
+use crate::error::OteError;
+use crate::ot_async;
+use crate::ot_primitive::{bool_vec_to_usize, usize_to_bool_vec_len, SafePrimeGroup, USIZE};
+use crate::params::SecurityParams;
+use crate::session::Protocol;
+
+/**
+ * Which extension to run and at which security level.
+ * Both parties must use the same configuration.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OtExtConfig {
+    protocol: Protocol,
+    params: SecurityParams,
+}
+
+impl OtExtConfig {
+    pub fn new(protocol: Protocol, params: SecurityParams) -> OtExtConfig {
+        OtExtConfig { protocol, params }
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn params(&self) -> SecurityParams {
+        self.params
+    }
+
+    // Number of base OTs.
+    pub fn k(&self) -> usize {
+        self.params.k()
+    }
+}
+
+impl Default for OtExtConfig {
+    fn default() -> Self {
+        OtExtConfig {
+            protocol: Protocol::Alsz,
+            params: SecurityParams::default(),
+        }
+    }
+}
+
+// Base OT of group elements, one party per call.
+#[derive(Clone)]
+pub struct BaseOt {
+    group: SafePrimeGroup,
+}
+
+impl BaseOt {
+    pub fn new(group: SafePrimeGroup) -> BaseOt {
+        BaseOt { group }
+    }
+
+    pub fn group(&self) -> &SafePrimeGroup {
+        &self.group
+    }
+
+    pub async fn send<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        messages: Vec<(USIZE, USIZE)>,
+        rng: &mut R,
+    ) -> Result<(), OteError> {
+        ot_async::base_ot_send(chan, &self.group, messages, rng).await
+    }
+
+    pub async fn receive<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        choices: Vec<bool>,
+        rng: &mut R,
+    ) -> Result<Vec<USIZE>, OteError> {
+        ot_async::base_ot_receive(chan, &self.group, choices, rng).await
+    }
+
+    /**
+     * `send` with byte strings as messages, all of the same length and at most
+     * `bytes::max_base_ot_bytes` long. See `bytes` for the format.
+     */
+    pub async fn send_bytes<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore, M: AsRef<[u8]>>(
+        &self,
+        chan: &mut Channel<S>,
+        messages: &[(M, M)],
+        rng: &mut R,
+    ) -> Result<(), OteError> {
+        bytes::check_base_ot_len(messages.first().map_or(0, |(x_0, _)| x_0.as_ref().len()), &self.group)?;
+        let bits = Zeroizing::new(bytes::message_bits(messages)?);
+        let messages = bits
+            .iter()
+            .map(|(x_0, x_1)| Ok((bool_vec_to_usize(x_0)?, bool_vec_to_usize(x_1)?)))
+            .collect::<Result<Vec<_>, OteError>>()?;
+        self.send(chan, messages, rng).await
+    }
+
+    /**
+     * `receive` for a sender that uses `send_bytes` with messages of `len` bytes: n OTs with
+     * their choice bits packed into a bitmap as in `bytes`, returning the chosen byte strings.
+     */
+    pub async fn receive_bytes<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        choice: &[u8],
+        n: usize,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, OteError> {
+        bytes::check_base_ot_len(len, &self.group)?;
+        let choice = bytes::unpack_choice(choice, n)?;
+        let outputs = Zeroizing::new(self.receive(chan, choice, rng).await?);
+        Ok(outputs
+            .iter()
+            .map(|x| bool_vec_to_byte_vec(&Zeroizing::new(usize_to_bool_vec_len(x, 8 * len))))
+            .collect())
+    }
+}
+
+// Sender of an OT extension: inputs one pair of equally long messages per OT.
+#[derive(Clone)]
+pub struct OtExtSender {
+    config: OtExtConfig,
+    group: SafePrimeGroup,
+}
+
+impl OtExtSender {
+    pub fn new(config: OtExtConfig, group: SafePrimeGroup) -> OtExtSender {
+        OtExtSender { config, group }
+    }
+
+    pub fn config(&self) -> OtExtConfig {
+        self.config
+    }
+
+    pub(crate) fn group(&self) -> &SafePrimeGroup {
+        &self.group
+    }
+
+    pub async fn send<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        messages: MessagePairs,
+        rng: &mut R,
+    ) -> Result<(), OteError> {
+        match self.config.protocol {
+            Protocol::Iknp => ot_async::iknp_send(chan, messages, self.config.k(), &self.group, rng).await,
+            Protocol::Alsz => ot_async::alsz_send(chan, messages, self.config.k(), &self.group, rng).await,
+        }
+    }
+
+    /**
+     * m OTs without messages for the protocols built on the correlated rows: returns the hash
+     * index of the first OT, the rows q_j and s, where the receiver has t_j = q_j xor (c_j * s).
+     */
+    pub(crate) async fn send_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        m: usize,
+        rng: &mut R,
+    ) -> Result<(usize, SecretRows, SecretBits), OteError> {
+        match self.config.protocol {
+            Protocol::Iknp => ot_async::iknp_send_rows(chan, m, self.config.k(), &self.group, rng).await,
+            Protocol::Alsz => ot_async::alsz_send_rows(chan, m, self.config.k(), &self.group, rng).await,
+        }
+    }
+
+    // `send` with byte strings as messages, all of the same length. See `bytes` for the format.
+    pub async fn send_bytes<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore, M: AsRef<[u8]>>(
+        &self,
+        chan: &mut Channel<S>,
+        messages: &[(M, M)],
+        rng: &mut R,
+    ) -> Result<(), OteError> {
+        self.send(chan, bytes::message_bits(messages)?, rng).await
+    }
+}
+
+// Receiver of an OT extension: inputs one choice bit per OT and learns the chosen messages.
+#[derive(Clone)]
+pub struct OtExtReceiver {
+    config: OtExtConfig,
+    group: SafePrimeGroup,
+}
+
+impl OtExtReceiver {
+    pub fn new(config: OtExtConfig, group: SafePrimeGroup) -> OtExtReceiver {
+        OtExtReceiver { config, group }
+    }
+
+    pub fn config(&self) -> OtExtConfig {
+        self.config
+    }
+
+    pub(crate) fn group(&self) -> &SafePrimeGroup {
+        &self.group
+    }
+
+    pub async fn receive<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        choice: Vec<bool>,
+        rng: &mut R,
+    ) -> Result<Vec<Vec<bool>>, OteError> {
+        match self.config.protocol {
+            Protocol::Iknp => ot_async::iknp_receive(chan, choice, self.config.k(), &self.group, rng).await,
+            Protocol::Alsz => ot_async::alsz_receive(chan, choice, self.config.k(), &self.group, rng).await,
+        }
+    }
+
+    // Receiver side of `OtExtSender::send_rows`: returns the hash index of the first OT and the rows t_j.
+    pub(crate) async fn receive_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        choice: Vec<bool>,
+        rng: &mut R,
+    ) -> Result<(usize, SecretRows), OteError> {
+        match self.config.protocol {
+            Protocol::Iknp => ot_async::iknp_receive_rows(chan, choice, self.config.k(), &self.group, rng).await,
+            Protocol::Alsz => ot_async::alsz_receive_rows(chan, choice, self.config.k(), &self.group, rng).await,
+        }
+    }
+
+    /**
+     * `receive` for a sender that uses `send_bytes`: n OTs with their choice bits packed into a
+     * bitmap as in `bytes`, returning the chosen byte strings.
+     */
+    pub async fn receive_bytes<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+        &self,
+        chan: &mut Channel<S>,
+        choice: &[u8],
+        n: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, OteError> {
+        let choice = bytes::unpack_choice(choice, n)?;
+        let outputs = Zeroizing::new(self.receive(chan, choice, rng).await?);
+        bytes::output_bytes(&outputs)
+    }
+}
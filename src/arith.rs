@@ -0,0 +1,288 @@
+// Oblivious multiplication with Gilboa's protocol on correlated OTs from an extension session.
+//
+// A correlated OT with correlation Delta gives the sender a random m_0 and the receiver
+// m_0 + c * Delta for its choice bit c. For x held by the sender and y = sum y_i 2^i held by the
+// receiver, one COT per bit of y with Delta_i = x * 2^i gives sum (m_0,i + y_i x 2^i) = sum m_0,i + x * y,
+// so the sender's share -sum m_0,i and the receiver's sum of outputs add up to x * y.
+//
+// The COTs come straight from the extended rows: m_0 = H(j, q_j), and the sender sends the
+// correction d_j = m_0 + Delta_j - H(j, q_j xor s), with which the receiver's H(j, t_j) + c_j d_j
+// is m_0 + c_j Delta_j. That is one element per OT on the wire instead of two messages.
+// `multiply` runs both parties on a session in one process, `multiply_sender` and
+// `multiply_receiver` run one party each over a `Channel`.
+
+use crypto_bigint::U64;
+use rand::{CryptoRng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtConfig, OtExtReceiver, OtExtSender};
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_async::blocking;
+use crate::ot_primitive::SafePrimeGroup;
+use crate::session::OtExtSession;
+
+// Extra hash bits for elements of a prime field, so reducing them is at most 2^-64 from uniform.
+const EXTRA_BITS: usize = 64;
+
+// The ring Z_{2^k} for 1 <= k <= 64 or a prime field F_p with p < 2^64. Elements are u64 in [0, modulus).
+// Only the constructors make one, so k and p are always checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Modulus(Kind);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    PowerOfTwo(u32),
+    Prime(u64),
+}
+
+impl Modulus {
+    pub fn power_of_two(k: u32) -> Result<Modulus, OteError> {
+        if !(1..=64).contains(&k) {
+            return Err(OteError::BadParameter(format!("Z_2^{} is not supported, k must be between 1 and 64", k)));
+        }
+        Ok(Modulus(Kind::PowerOfTwo(k)))
+    }
+
+    pub fn prime(p: u64) -> Result<Modulus, OteError> {
+        if !crypto_primes::is_prime(&U64::from_u64(p)) {
+            return Err(OteError::BadParameter(format!("{} is not a prime", p)));
+        }
+        Ok(Modulus(Kind::Prime(p)))
+    }
+
+    // The prime p of F_p, or None for Z_2^k.
+    pub fn prime_value(&self) -> Option<u64> {
+        match self.0 {
+            Kind::PowerOfTwo(_) => None,
+            Kind::Prime(p) => Some(p),
+        }
+    }
+
+    // Number of bits of an element, and so of COTs per multiplication.
+    pub fn bits(&self) -> usize {
+        match self.0 {
+            Kind::PowerOfTwo(k) => k as usize,
+            Kind::Prime(p) => 64 - p.leading_zeros() as usize,
+        }
+    }
+
+    fn reduce(&self, x: u128) -> u64 {
+        match self.0 {
+            Kind::PowerOfTwo(k) => (x & (u128::MAX >> (128 - k))) as u64,
+            Kind::Prime(p) => (x % p as u128) as u64,
+        }
+    }
+
+    pub fn add(&self, x: u64, y: u64) -> u64 {
+        self.reduce(x as u128 + y as u128)
+    }
+
+    pub fn neg(&self, x: u64) -> u64 {
+        match self.0 {
+            Kind::PowerOfTwo(_) => self.reduce(x.wrapping_neg() as u128),
+            Kind::Prime(p) => self.reduce((p - x) as u128),
+        }
+    }
+
+    pub fn sub(&self, x: u64, y: u64) -> u64 {
+        self.add(x, self.neg(y))
+    }
+
+    pub fn mul(&self, x: u64, y: u64) -> u64 {
+        self.reduce(x as u128 * y as u128)
+    }
+
+    // Checks that all values are elements, i.e. below the modulus.
+    pub(crate) fn check(&self, values: &[u64]) -> Result<(), OteError> {
+        match (*self, values.iter().find(|&&x| self.reduce(x as u128) != x)) {
+            (_, None) => Ok(()),
+            (modulus, Some(x)) => Err(OteError::BadParameter(format!("{} is not an element of {:?}", x, modulus))),
+        }
+    }
+
+    // Number of hash bits that are turned into one element.
+    fn hash_len(&self) -> usize {
+        match self.0 {
+            Kind::PowerOfTwo(k) => k as usize,
+            Kind::Prime(_) => self.bits() + EXTRA_BITS,
+        }
+    }
+
+    // The element H(j, row), read most significant bit first.
+    fn hash(&self, j: usize, row: &[bool]) -> u64 {
+        let bits = Zeroizing::new(hash_bits(&int_to_bool_vec(j), row, self.hash_len()));
+        self.reduce(bits.iter().fold(0u128, |acc, &b| acc << 1 | b as u128))
+    }
+}
+
+// x if the bit is set and 0 otherwise, without a branch on the bit.
+fn times(bit: bool, x: u64) -> u64 {
+    x & (bit as u64).wrapping_neg()
+}
+
+// Sender of the COTs on the rows q_j: m_0 = H(j, q_j) and the corrections d_j.
+fn sender_cots(modulus: Modulus, first_index: usize, q: &[Vec<bool>], s: &[bool], deltas: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    q.iter()
+        .zip(deltas)
+        .enumerate()
+        .map(|(i, (q_j, &delta))| {
+            let q_j_xor_s = Zeroizing::new(xor_boolvec(q_j, s));
+            let m_0 = modulus.hash(first_index + i, q_j);
+            let m_1 = modulus.hash(first_index + i, &q_j_xor_s);
+            (m_0, modulus.sub(modulus.add(m_0, delta), m_1))
+        })
+        .unzip()
+}
+
+// Receiver of the COTs on the rows t_j: H(j, t_j), plus the correction if the choice bit is 1.
+fn receiver_cots(modulus: Modulus, first_index: usize, t: &[Vec<bool>], choice: &[bool], corrections: &[u64]) -> Vec<u64> {
+    t.iter()
+        .zip(choice)
+        .zip(corrections)
+        .enumerate()
+        .map(|(i, ((t_j, &c), &d))| modulus.add(modulus.hash(first_index + i, t_j), times(c, d)))
+        .collect()
+}
+
+/**
+ * Runs one COT per choice bit on the session, with correlation deltas[i] for the i-th.
+ * Returns the sender's random m_0 and the receiver's m_0 + choice_i * deltas[i].
+ */
+pub fn correlated_ots(session: &mut OtExtSession, modulus: Modulus, deltas: &[u64], choice: &[bool]) -> Result<(Vec<u64>, Vec<u64>), OteError> {
+    if deltas.len() != choice.len() {
+        return Err(OteError::BadParameter(format!("{} correlations for {} choice bits", deltas.len(), choice.len())));
+    }
+    modulus.check(deltas)?;
+    let (first_index, q, t) = session.extend_rows(choice)?;
+    let (m_0, corrections) = sender_cots(modulus, first_index, &q, &session.sender_s(), deltas);
+    Ok((m_0, receiver_cots(modulus, first_index, &t, choice, &corrections)))
+}
+
+// The corrections from the wire, one 8-byte big-endian element per COT.
+fn parse_corrections(modulus: Modulus, bytes: &[u8], n: usize) -> Result<Vec<u64>, OteError> {
+    if bytes.len() != 8 * n {
+        return Err(OteError::MalformedMessage(format!("{} bytes of corrections for {} COTs", bytes.len(), n)));
+    }
+    let corrections = bytes.chunks_exact(8).map(be_u64).collect::<Vec<_>>();
+    modulus
+        .check(&corrections)
+        .map_err(|_| OteError::MalformedMessage(format!("a correction is not an element of {:?}", modulus)))?;
+    Ok(corrections)
+}
+
+/**
+ * Sender of `correlated_ots` over a channel: returns its m_0, while the receiver only learns
+ * m_0 + choice_i * deltas[i]. Both parties must run the same number of COTs.
+ * Messages: the extension without its y_j, then the "cot corrections".
+ */
+pub async fn correlated_ots_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    deltas: &[u64],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<u64>, OteError> {
+    modulus.check(deltas)?;
+    if deltas.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sender = OtExtSender::new(config, group.clone());
+    let (first_index, q, s) = sender.send_rows(chan, deltas.len(), rng).await?;
+    let deltas = Zeroizing::new(deltas.to_vec());
+    let (m_0, corrections) = blocking(move || Ok(sender_cots(modulus, first_index, &q, &s, &deltas))).await?;
+    let bytes = corrections.iter().flat_map(|d| d.to_be_bytes()).collect::<Vec<_>>();
+    chan.label("cot corrections").send_bytes(&bytes).await?;
+    Ok(m_0)
+}
+
+// Receiver of `correlated_ots_sender`: returns m_0 + choice_i * deltas[i] for every COT.
+pub async fn correlated_ots_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    choice: &[bool],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<u64>, OteError> {
+    if choice.is_empty() {
+        return Ok(Vec::new());
+    }
+    let receiver = OtExtReceiver::new(config, group.clone());
+    let (first_index, t) = receiver.receive_rows(chan, choice.to_vec(), rng).await?;
+    let bytes = chan.label("cot corrections").recv_bytes().await?;
+    let corrections = parse_corrections(modulus, &bytes, choice.len())?;
+    let choice = Zeroizing::new(choice.to_vec());
+    blocking(move || Ok(receiver_cots(modulus, first_index, &t, &choice, &corrections))).await
+}
+
+// The correlations x * 2^i of the COTs for every x, bits() of them per value.
+pub(crate) fn gilboa_deltas(modulus: Modulus, x: &[u64]) -> Zeroizing<Vec<u64>> {
+    Zeroizing::new(
+        x.iter()
+            .flat_map(|&x| (0..modulus.bits()).map(move |i| modulus.mul(x, modulus.reduce(1u128 << i))))
+            .collect(),
+    )
+}
+
+// The choice bits of the COTs, the bits of every y from the least significant.
+pub(crate) fn gilboa_choice(modulus: Modulus, y: &[u64]) -> SecretBits {
+    Zeroizing::new(y.iter().flat_map(|&y| (0..modulus.bits()).map(move |i| y >> i & 1 == 1)).collect())
+}
+
+// The sums of the COT outputs of every product.
+pub(crate) fn sums(modulus: Modulus, outputs: &[u64]) -> Vec<u64> {
+    outputs
+        .chunks(modulus.bits())
+        .map(|chunk| chunk.iter().fold(0, |acc, &x| modulus.add(acc, x)))
+        .collect()
+}
+
+/**
+ * Additive shares of x[i] * y[i] for all i, with x held by the sender and y by the receiver of
+ * the session. All multiplications share one extension of len * bits COTs.
+ * Returns the sender's and the receiver's shares, which add up to the products.
+ */
+pub fn multiply(session: &mut OtExtSession, modulus: Modulus, x: &[u64], y: &[u64]) -> Result<(Vec<u64>, Vec<u64>), OteError> {
+    if x.len() != y.len() {
+        return Err(OteError::BadParameter(format!("{} sender and {} receiver values", x.len(), y.len())));
+    }
+    modulus.check(x)?;
+    modulus.check(y)?;
+    let (m_0, outputs) = correlated_ots(session, modulus, &gilboa_deltas(modulus, x), &gilboa_choice(modulus, y))?;
+    let sender = sums(modulus, &m_0).into_iter().map(|sum| modulus.neg(sum)).collect();
+    Ok((sender, sums(modulus, &outputs)))
+}
+
+/**
+ * Sender of `multiply` over a channel, holding x: returns its shares of x[i] * y[i].
+ * The receiver must hold as many values. Messages: those of `correlated_ots_sender`.
+ */
+pub async fn multiply_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    x: &[u64],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<u64>, OteError> {
+    modulus.check(x)?;
+    let m_0 = correlated_ots_sender(chan, modulus, &gilboa_deltas(modulus, x), config, group, rng).await?;
+    Ok(sums(modulus, &m_0).into_iter().map(|sum| modulus.neg(sum)).collect())
+}
+
+// Receiver of `multiply_sender`, holding y: returns its shares of x[i] * y[i].
+pub async fn multiply_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    y: &[u64],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<u64>, OteError> {
+    modulus.check(y)?;
+    let outputs = correlated_ots_receiver(chan, modulus, &gilboa_choice(modulus, y), config, group, rng).await?;
+    Ok(sums(modulus, &outputs))
+}
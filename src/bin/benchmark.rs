@@ -1,24 +1,32 @@
-mod common;
-mod ot_better_network;
-mod ot_primitive;
-mod ote_IKNP;
+// Benchmarks of the base OT and the extensions, whose timings are written to tests/.
+// Experiments are toggled by (un)commenting calls in `main`, so most functions look unused.
+#![allow(dead_code)]
 
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::time::SystemTime;
 
-use ot_primitive::{make_group_from_scratch, SafePrimeGroup};
-use common::random_boolvec_len;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use ote::common::{self, random_boolvec_len, MessagePairs};
+use ote::ot_primitive::{self, SafePrimeGroup};
+use ote::{ot_better_network, ote_IKNP};
+use ote::OteError;
 
 const REPEAT: u128 = 5;
+// All randomness comes from a ChaCha20Rng with this seed, so every run does exactly the same work.
+const SEED: u64 = 0;
+
 
+type OteFn = dyn Fn(MessagePairs, Vec<bool>, usize, &SafePrimeGroup, &mut ChaCha20Rng) -> Result<Vec<Vec<bool>>, OteError>;
 
-fn random_messages(m: usize) -> Vec<(Vec<bool>, Vec<bool>)> {
+fn random_messages(m: usize, rng: &mut ChaCha20Rng) -> MessagePairs {
     (0..m)
         .map(|_| {
             (
-                random_boolvec_len(common::OUTPUT_SIZE),
-                random_boolvec_len(common::OUTPUT_SIZE),
+                random_boolvec_len(common::OUTPUT_SIZE, rng),
+                random_boolvec_len(common::OUTPUT_SIZE, rng),
             )
         })
         .collect::<Vec<_>>()
@@ -35,11 +43,12 @@ fn random_messages(m: usize) -> Vec<(Vec<bool>, Vec<bool>)> {
  * ...
  */
 fn run_experiment<T: Iterator<Item = usize> + Clone>(
-    ote: &dyn Fn(Vec<(Vec<bool>, Vec<bool>)>, Vec<bool>, usize, &SafePrimeGroup) -> Vec<Vec<bool>>,
+    ote: &OteFn,
     message_range: &T,
     k_range: &T,
     name: &str,
     group: &SafePrimeGroup,
+    rng: &mut ChaCha20Rng,
 ) {
     let mut path = "tests/".to_owned();
     path.push_str(name);
@@ -53,78 +62,78 @@ fn run_experiment<T: Iterator<Item = usize> + Clone>(
     let mut first = true;
     for m_num in message_range.clone() {
         if !first {
-            file.write(&" ".as_bytes());
+            file.write_all(" ".as_bytes()).unwrap();
         }
         first = false;
-        file.write(&m_num.to_string().as_bytes());
+        file.write_all(m_num.to_string().as_bytes()).unwrap();
     }
-    file.write(&"\n".as_bytes());
+    file.write_all("\n".as_bytes()).unwrap();
     first = true;
     for k in k_range.clone() {
         if !first {
-            file.write(&" ".as_bytes());
+            file.write_all(" ".as_bytes()).unwrap();
         }
         first = false;
-        file.write(&k.to_string().as_bytes());
+        file.write_all(k.to_string().as_bytes()).unwrap();
     }
     for m_num in message_range.clone() {
-        file.write(&"\n".as_bytes());
+        file.write_all("\n".as_bytes()).unwrap();
         first = true;
         for k in k_range.clone() {
             if !first {
-                file.write(&" ".as_bytes());
+                file.write_all(" ".as_bytes()).unwrap();
             }
             first = false;
 
             let mut x = 0;
             for round in 0..REPEAT {
                 println!("Round for {}: {}", m_num, round + 1);
-                let messages = random_messages(m_num);
-                let choice_bits = random_boolvec_len(m_num);
+                let messages = random_messages(m_num, rng);
+                let choice_bits = random_boolvec_len(m_num, rng);
                 let now = SystemTime::now();
-                ote(messages, choice_bits, k, group);
+                ote(messages, choice_bits, k, group, rng).unwrap();
                 x += now.elapsed().ok().unwrap().as_nanos()
             }
             x /= REPEAT;
-            file.write(x.to_string().as_bytes());
-            // file.write(" ".as_bytes());
+            file.write_all(x.to_string().as_bytes()).unwrap();
+            // file.write_all(" ".as_bytes()).unwrap();
         }
     }
 }
 
 fn run_experiments_for_primitive_vs_otes() {
-    let group = &ot_primitive::make_group();
+    let rng = &mut ChaCha20Rng::seed_from_u64(SEED);
+    let group = &ot_primitive::make_group(rng).unwrap();
     let security = vec![128].into_iter();
     // let messages = vec![1, 10, 100, 1_000, 10_000, 100_000].into_iter();
     let messages = (1..14).map(|x| 1 << x).collect::<Vec<_>>().into_iter();
-    run_experiment(&ote_IKNP::ote, &messages, &security, "IKNP", group);
-    run_experiment(&ot_better_network::ote, &messages, &security, "ALSZ", group);
+    run_experiment(&ote_IKNP::ote, &messages, &security, "IKNP", group, rng);
+    run_experiment(&ot_better_network::ote, &messages, &security, "ALSZ", group, rng);
     // messages.clone().rev().skip(1).rev();
-    run_experiment(&ot_primitive::ote, &messages, &security, "Prim", group);
+    run_experiment(&ot_primitive::ote, &messages, &security, "Prim", group, rng);
 }
 
 fn run_experiments_for_iknp_alsz_128_vs_256() {
-    let group = &ot_primitive::make_group();
+    let rng = &mut ChaCha20Rng::seed_from_u64(SEED);
+    let group = &ot_primitive::make_group(rng).unwrap();
     let security = vec![128, 256].into_iter();
     let messages1 = (7..22).map(|x| 1 << x).collect::<Vec<_>>().into_iter();
     let messages2 = (7..24).map(|x| 1 << x).collect::<Vec<_>>().into_iter();
-    run_experiment(&ote_IKNP::ote, &messages1, &security, "IKNP_tmp", group);
-    run_experiment(&ot_better_network::ote, &messages2, &security, "ALSZ_tmp", group);
+    run_experiment(&ote_IKNP::ote, &messages1, &security, "IKNP_tmp", group, rng);
+    run_experiment(&ot_better_network::ote, &messages2, &security, "ALSZ_tmp", group, rng);
 }
 
 fn run_experiments_for_iknp_alsz_single() {
-    let group = &ot_primitive::make_group();
+    let rng = &mut ChaCha20Rng::seed_from_u64(SEED);
+    let group = &ot_primitive::make_group(rng).unwrap();
     let security = vec![128].into_iter();
     let messages = vec![1 << 20].into_iter();
-    run_experiment(&ote_IKNP::ote, &messages, &security, "IKNP_single", group);
-    run_experiment(&ot_better_network::ote, &messages, &security, "ALSZ_single", group)
+    run_experiment(&ote_IKNP::ote, &messages, &security, "IKNP_single", group, rng);
+    run_experiment(&ot_better_network::ote, &messages, &security, "ALSZ_single", group, rng)
 }
 
 fn main() {
-    // make_group_from_scratch();
-    // ot_primitive::run_tests();
-    // ote_IKNP::run_tests();
-    // ot_better_network::run_tests();
+    // ot_primitive::make_group_from_scratch(&mut rand::rngs::OsRng).unwrap();
     // run_experiments_for_primitive_vs_otes();
     // run_experiments_for_iknp_alsz_128_vs_256();
     run_experiments_for_iknp_alsz_single();
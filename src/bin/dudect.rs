@@ -0,0 +1,24 @@
+// Timing leak check of the choice bit handling, see `ote::timing`.
+// Run with `cargo run --release --bin dudect [samples]`; exits with status 1 if a function leaks.
+
+use rand::rngs::OsRng;
+
+use ote::timing::{check_choice_handling, T_THRESHOLD};
+
+const DEFAULT_SAMPLES: usize = 100_000;
+
+fn main() {
+    let samples = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().expect("the number of samples must be an integer"),
+        None => DEFAULT_SAMPLES,
+    };
+    let reports = check_choice_handling(samples, &mut OsRng).unwrap();
+    println!("{:<16} {:>10} {:>8}  (leak if |t| > {})", "function", "samples", "t", T_THRESHOLD);
+    for report in &reports {
+        let verdict = if report.leaks() { "LEAK" } else { "ok" };
+        println!("{:<16} {:>10} {:>8.2}  {}", report.name, report.samples, report.t, verdict);
+    }
+    if reports.iter().any(|x| x.leaks()) {
+        std::process::exit(1);
+    }
+}
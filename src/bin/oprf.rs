@@ -0,0 +1,49 @@
+// OPRF server and client over TCP, both on the group of `safe_prime.txt`:
+//   cargo run --release --bin oprf -- server 127.0.0.1:7878
+//   cargo run --release --bin oprf -- client 127.0.0.1:7878 alice bob
+// The server keeps its key until it stops. The client prints F_k(x) in hex for every input.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use rand::rngs::OsRng;
+use tokio::net::TcpListener;
+
+use ote::oprf::{oprf_query, oprf_serve, OprfServer, ServeLimits};
+use ote::ot_primitive::make_group;
+use ote::OteError;
+
+async fn run(args: &[String]) -> Result<(), OteError> {
+    let group = make_group(&mut OsRng)?;
+    match args {
+        [mode, addr] if mode == "server" => {
+            let listener = TcpListener::bind(addr).await?;
+            println!("serving OPRF requests on {}", listener.local_addr()?);
+            let server = Arc::new(OprfServer::new(&group, &mut OsRng));
+            let report = |peer: Option<SocketAddr>, e: OteError| match peer {
+                Some(peer) => eprintln!("{}: {}", peer, e),
+                None => eprintln!("accept: {}", e),
+            };
+            oprf_serve(listener, server, ServeLimits::default(), report).await
+        }
+        [mode, addr, inputs @ ..] if mode == "client" => {
+            let inputs = inputs.iter().map(|x| x.as_bytes().to_vec()).collect::<Vec<_>>();
+            let values = oprf_query(addr.as_str(), &group, &inputs, &mut OsRng).await?;
+            for (x, v) in inputs.iter().zip(values) {
+                let hex = v.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+                println!("{} {}", String::from_utf8_lossy(x), hex);
+            }
+            Ok(())
+        }
+        _ => Err(OteError::BadParameter("usage: oprf server <addr> | oprf client <addr> <input>...".to_owned())),
+    }
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    if let Err(e) = runtime.block_on(run(&args)) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
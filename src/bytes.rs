@@ -0,0 +1,124 @@
+// Byte-oriented front end of the base OT and the two extensions, for callers that hold their
+// messages as bytes rather than bit vectors.
+//
+// Messages are byte strings. All messages of one call must have the same length in bytes, which
+// may be zero, and the receiver gets exactly the bytes of the chosen message back. Inside, every
+// byte becomes 8 bits, most significant first, so no padding is involved.
+//
+// Choice bits are a packed bitmap, least significant bit first: the choice of OT i is
+// (choice[i / 8] >> (i % 8)) & 1, so [0b0000_0101] picks x_1 in OTs 0 and 2 and x_0 elsewhere.
+// A bitmap for n OTs has exactly ceil(n / 8) bytes, and the 8 * ceil(n / 8) - n unused high bits
+// of its last byte must be zero. Any other bitmap is rejected rather than cut or padded.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::{self, SafePrimeGroup};
+use crate::{ot_better_network, ote_IKNP};
+
+// Bytes of a bitmap of n choice bits.
+pub fn choice_bytes(n: usize) -> usize {
+    n.div_ceil(8)
+}
+
+// Packs choice bits into a bitmap, least significant bit first.
+pub fn pack_choice(choice: &[bool]) -> Vec<u8> {
+    choice
+        .chunks(8)
+        .map(|bits| bits.iter().enumerate().fold(0, |acc, (i, &b)| acc | (b as u8) << i))
+        .collect()
+}
+
+/**
+ * The n choice bits of a bitmap. Fails unless the bitmap has ceil(n / 8) bytes and its unused
+ * bits are zero.
+ */
+pub fn unpack_choice(bitmap: &[u8], n: usize) -> Result<Vec<bool>, OteError> {
+    if bitmap.len() != choice_bytes(n) {
+        return Err(OteError::BadParameter(format!(
+            "a bitmap of {} choice bits has {} bytes, not {}",
+            n,
+            choice_bytes(n),
+            bitmap.len()
+        )));
+    }
+    if !n.is_multiple_of(8) && bitmap[n / 8] >> (n % 8) != 0 {
+        return Err(OteError::BadParameter(format!("bits beyond the {} choice bits are set", n)));
+    }
+    Ok((0..n).map(|i| bitmap[i / 8] >> (i % 8) & 1 == 1).collect())
+}
+
+// The messages as bits, checking first that all of them have the same length in bytes.
+pub(crate) fn message_bits<M: AsRef<[u8]>>(messages: &[(M, M)]) -> Result<MessagePairs, OteError> {
+    let len = messages.first().map_or(0, |(x_0, _)| x_0.as_ref().len());
+    if let Some(index) = messages.iter().position(|(x_0, x_1)| x_0.as_ref().len() != len || x_1.as_ref().len() != len) {
+        return Err(OteError::MessageLengthMismatch {
+            index,
+            expected: len,
+            len_0: messages[index].0.as_ref().len(),
+            len_1: messages[index].1.as_ref().len(),
+        });
+    }
+    Ok(messages
+        .iter()
+        .map(|(x_0, x_1)| (byte_vec_to_bool_vec(x_0.as_ref()), byte_vec_to_bool_vec(x_1.as_ref())))
+        .collect())
+}
+
+// The received messages as bytes. Each must be a whole number of bytes, as the sender's were.
+pub(crate) fn output_bytes(outputs: &[Vec<bool>]) -> Result<Vec<Vec<u8>>, OteError> {
+    match outputs.iter().find(|x| !x.len().is_multiple_of(8)) {
+        Some(x) => Err(OteError::MalformedMessage(format!("a message of {} bits is not a byte string", x.len()))),
+        None => Ok(outputs.iter().map(|x| bool_vec_to_byte_vec(x)).collect()),
+    }
+}
+
+type BitOte<R> = fn(MessagePairs, Vec<bool>, usize, &SafePrimeGroup, &mut R) -> Result<Vec<Vec<bool>>, OteError>;
+
+fn run<M: AsRef<[u8]>, R: CryptoRng + RngCore>(
+    ote: BitOte<R>,
+    messages: &[(M, M)],
+    choice: &[u8],
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let bits = message_bits(messages)?;
+    let choice = Zeroizing::new(unpack_choice(choice, messages.len())?);
+    let outputs = Zeroizing::new(ote(bits, choice.to_vec(), k, group, rng)?);
+    output_bytes(&outputs)
+}
+
+/**
+ * Base OTs of byte strings, one exponentiation-based OT per pair. A message is encoded as one
+ * group element, so it can have at most `max_base_ot_bytes(group)` bytes.
+ */
+pub fn base_ot<M: AsRef<[u8]>, R: CryptoRng + RngCore>(messages: &[(M, M)], choice: &[u8], group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<Vec<u8>>, OteError> {
+    check_base_ot_len(messages.first().map_or(0, |(x_0, _)| x_0.as_ref().len()), group)?;
+    run(ot_primitive::ote, messages, choice, 0, group, rng)
+}
+
+// Checks that base OT messages of `len` bytes fit in a group element.
+pub(crate) fn check_base_ot_len(len: usize, group: &SafePrimeGroup) -> Result<(), OteError> {
+    let max = max_base_ot_bytes(group);
+    if len > max {
+        return Err(OteError::BadParameter(format!("base OT messages have at most {} bytes, not {}", max, len)));
+    }
+    Ok(())
+}
+
+// Longest message of a base OT in bytes: every message must be smaller than the group order q.
+pub fn max_base_ot_bytes(group: &SafePrimeGroup) -> usize {
+    (group.q_bits() - 1) / 8
+}
+
+// OT extension of byte strings with IKNP at security parameter k.
+pub fn iknp<M: AsRef<[u8]>, R: CryptoRng + RngCore>(messages: &[(M, M)], choice: &[u8], k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<Vec<u8>>, OteError> {
+    run(ote_IKNP::ote, messages, choice, k, group, rng)
+}
+
+// OT extension of byte strings with ALSZ at security parameter k.
+pub fn alsz<M: AsRef<[u8]>, R: CryptoRng + RngCore>(messages: &[(M, M)], choice: &[u8], k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<Vec<u8>>, OteError> {
+    run(ot_better_network::ote, messages, choice, k, group, rng)
+}
@@ -0,0 +1,185 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::common::{be_u64, bool_vec_to_byte_vec, byte_vec_to_bool_vec_len, MessagePairs};
+use crate::error::OteError;
+use crate::ot_primitive::{elem_from_bytes, elem_to_bytes, PublicKey, SafePrimeGroup, ELEM_BYTES};
+use crate::transcript::{Direction, Transcript};
+
+// Default upper bound on a single frame. Larger batches need `set_max_frame_bytes` on the receiving side.
+pub const DEFAULT_MAX_FRAME_BYTES: u64 = 64 << 20;
+// A frame's buffer starts at most this large and grows as its bytes arrive, so a length prefix alone
+// cannot make us allocate much.
+const INITIAL_FRAME_CAPACITY: u64 = 64 << 10;
+
+/**
+ * Length-prefixed framing on top of any byte stream.
+ * Every message is a big-endian u64 length followed by that many bytes.
+ * With `record` on, every message is also logged to a `Transcript` under the label set by `label`.
+ */
+pub struct Channel<S> {
+    stream: S,
+    transcript: Option<Transcript>,
+    // Label of the next message, reset once it is sent or received.
+    label: String,
+    max_frame_bytes: u64,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Channel<S> {
+    pub fn new(stream: S) -> Channel<S> {
+        Channel {
+            stream,
+            transcript: None,
+            label: String::new(),
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+        }
+    }
+
+    pub fn max_frame_bytes(&self) -> u64 {
+        self.max_frame_bytes
+    }
+
+    // Frames announced as longer than this are rejected before any of their bytes are read.
+    pub fn set_max_frame_bytes(&mut self, max: u64) {
+        self.max_frame_bytes = max;
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    // Start logging all messages, dropping whatever was recorded before.
+    pub fn record(&mut self) {
+        self.transcript = Some(Transcript::new());
+    }
+
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
+
+    // Stop recording and return what was recorded.
+    pub fn take_transcript(&mut self) -> Option<Transcript> {
+        self.transcript.take()
+    }
+
+    // Label the next message in the transcript, e.g. `chan.label("u_i").send_bit_rows(&u)`.
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        label.clone_into(&mut self.label);
+        self
+    }
+
+    fn log(&mut self, direction: Direction, bytes: &[u8]) {
+        let label = std::mem::take(&mut self.label);
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push(direction, &label, bytes);
+        }
+    }
+
+    pub async fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), OteError> {
+        self.stream.write_u64(bytes.len() as u64).await?;
+        self.stream.write_all(bytes).await?;
+        self.stream.flush().await?;
+        self.log(Direction::Sent, bytes);
+        Ok(())
+    }
+
+    pub async fn recv_bytes(&mut self) -> Result<Vec<u8>, OteError> {
+        let len = self.stream.read_u64().await?;
+        if len > self.max_frame_bytes {
+            return Err(OteError::MalformedMessage(format!(
+                "frame of {} bytes is larger than the limit of {}",
+                len, self.max_frame_bytes
+            )));
+        }
+        let mut bytes = Vec::with_capacity(len.min(INITIAL_FRAME_CAPACITY) as usize);
+        (&mut self.stream).take(len).read_to_end(&mut bytes).await?;
+        if bytes.len() as u64 != len {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        self.log(Direction::Received, &bytes);
+        Ok(bytes)
+    }
+
+    /**
+     * Sends rows of bits which must all have the same length.
+     * Format: number of rows, row length in bits, then every row packed with `bool_vec_to_byte_vec`.
+     */
+    pub async fn send_bit_rows(&mut self, rows: &[Vec<bool>]) -> Result<(), OteError> {
+        let row_len = rows.first().map_or(0, |r| r.len());
+        if rows.iter().any(|r| r.len() != row_len) {
+            return Err(OteError::BadParameter("rows must have equal length".to_owned()));
+        }
+        let mut bytes = Vec::with_capacity(16 + rows.len() * usize::div_ceil(row_len, 8));
+        bytes.extend_from_slice(&(rows.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&(row_len as u64).to_be_bytes());
+        rows.iter().for_each(|r| bytes.extend(bool_vec_to_byte_vec(r)));
+        self.send_bytes(&bytes).await
+    }
+
+    /**
+     * Receives rows sent by `send_bit_rows` and checks that there are `rows` of them.
+     * If `row_len` is None, any row length is accepted.
+     */
+    pub async fn recv_bit_rows(&mut self, rows: usize, row_len: Option<usize>) -> Result<Vec<Vec<bool>>, OteError> {
+        let bytes = self.recv_bytes().await?;
+        if bytes.len() < 16 {
+            return Err(OteError::MalformedMessage("missing bit matrix header".to_owned()));
+        }
+        let got_rows = be_u64(&bytes[0..8]);
+        let got_len = be_u64(&bytes[8..16]);
+        let row_len = row_len.unwrap_or(got_len as usize);
+        let row_bytes = usize::div_ceil(row_len, 8);
+        if got_rows != rows as u64 || got_len != row_len as u64 || got_rows.checked_mul(row_bytes as u64) != Some((bytes.len() - 16) as u64) {
+            return Err(OteError::MalformedMessage(format!(
+                "expected {} rows of {} bits but got {} rows of {} bits",
+                rows, row_len, got_rows, got_len
+            )));
+        }
+        Ok((0..rows)
+            .map(|i| {
+                let start = 16 + i * row_bytes;
+                byte_vec_to_bool_vec_len(&bytes[start..start + row_bytes], row_len)
+            })
+            .collect())
+    }
+
+    pub async fn send_bit_pairs(&mut self, pairs: &[(Vec<bool>, Vec<bool>)]) -> Result<(), OteError> {
+        let rows = pairs
+            .iter()
+            .flat_map(|(a, b)| [a.clone(), b.clone()])
+            .collect::<Vec<_>>();
+        self.send_bit_rows(&rows).await
+    }
+
+    pub async fn recv_bit_pairs(&mut self, pairs: usize, len: Option<usize>) -> Result<MessagePairs, OteError> {
+        let mut rows = self.recv_bit_rows(2 * pairs, len).await?.into_iter();
+        Ok((0..pairs)
+            .map_while(|_| Some((rows.next()?, rows.next()?)))
+            .collect())
+    }
+
+    pub async fn send_group_elems(&mut self, elems: &[PublicKey]) -> Result<(), OteError> {
+        let bytes = elems.iter().flat_map(elem_to_bytes).collect::<Vec<_>>();
+        self.send_bytes(&bytes).await
+    }
+
+    /**
+     * Receives exactly `num` group elements and checks that each of them is reduced modulo p.
+     */
+    pub async fn recv_group_elems(&mut self, group: &SafePrimeGroup, num: usize) -> Result<Vec<PublicKey>, OteError> {
+        let bytes = self.recv_bytes().await?;
+        if bytes.len() != num * ELEM_BYTES {
+            return Err(OteError::MalformedMessage(format!(
+                "expected {} group elements but got {} bytes",
+                num,
+                bytes.len()
+            )));
+        }
+        bytes
+            .chunks(ELEM_BYTES)
+            .map(|x| {
+                elem_from_bytes(group, x)
+                    .ok_or_else(|| OteError::MalformedMessage("group element is not reduced".to_owned()))
+            })
+            .collect()
+    }
+}
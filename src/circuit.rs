@@ -0,0 +1,262 @@
+// Boolean circuits over numbered wires, as evaluated by `gmw`, and a parser for Bristol Fashion files.
+//
+// The layout follows Bristol Fashion: the input wires of party 0 come first, then those of
+// party 1 and so on, the output wires are the last ones, and every gate comes after the gates
+// that write its input wires. Within an input or output the bits are in wire order.
+// Circuits such as AES-128, SHA-256 and the adders in this format are published at
+// https://nigelsmart.github.io/MPC-Circuits/ and load with `Circuit::load`.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::OteError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gate {
+    // Input wires a and b, then the output wire.
+    Xor(usize, usize, usize),
+    And(usize, usize, usize),
+    // Input wire, then the output wire.
+    Inv(usize, usize),
+    // Copies the input wire to the output wire.
+    Eqw(usize, usize),
+    // Sets the output wire to a constant.
+    Eq(bool, usize),
+}
+
+impl Gate {
+    pub fn inputs(&self) -> Vec<usize> {
+        match *self {
+            Gate::Xor(a, b, _) | Gate::And(a, b, _) => vec![a, b],
+            Gate::Inv(a, _) | Gate::Eqw(a, _) => vec![a],
+            Gate::Eq(..) => vec![],
+        }
+    }
+
+    pub fn output(&self) -> usize {
+        match *self {
+            Gate::Xor(_, _, out) | Gate::And(_, _, out) | Gate::Inv(_, out) | Gate::Eqw(_, out) | Gate::Eq(_, out) => out,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Circuit {
+    pub wires: usize,
+    // Number of input wires of each party.
+    pub inputs: Vec<usize>,
+    // Number of wires of each output.
+    pub outputs: Vec<usize>,
+    pub gates: Vec<Gate>,
+}
+
+fn invalid(msg: String) -> OteError {
+    OteError::InvalidFile(msg)
+}
+
+// The next whitespace-separated number of the file, or an error naming what was expected.
+fn number<'a>(tokens: &mut impl Iterator<Item = &'a str>, what: &str) -> Result<usize, OteError> {
+    let token = tokens.next().ok_or_else(|| invalid(format!("file ends before the {}", what)))?;
+    token.parse().map_err(|_| invalid(format!("{} \"{}\" is not a number", what, token)))
+}
+
+// The count of a header line, followed by that many numbers.
+fn counts(line: Option<&str>, what: &str) -> Result<Vec<usize>, OteError> {
+    let mut tokens = line.ok_or_else(|| invalid(format!("missing line with the {}", what)))?.split_whitespace();
+    let n = number(&mut tokens, what)?;
+    let res = (0..n).map(|_| number(&mut tokens, what)).collect::<Result<Vec<_>, _>>()?;
+    if tokens.next().is_some() {
+        return Err(invalid(format!("more than {} {}", n, what)));
+    }
+    Ok(res)
+}
+
+// One gate line "nin nout inputs.. outputs.. OP", where MAND is split into its AND gates.
+fn parse_gate(line: &str, gates: &mut Vec<Gate>) -> Result<(), OteError> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let (op, numbers) = tokens.split_last().ok_or_else(|| invalid("empty gate".to_owned()))?;
+    let numbers = numbers
+        .iter()
+        .map(|x| x.parse::<usize>().map_err(|_| invalid(format!("wire \"{}\" in \"{}\" is not a number", x, line))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (inputs, outputs) = match numbers.as_slice() {
+        [n_in, n_out, wires @ ..] if n_in.checked_add(*n_out) == Some(wires.len()) => wires.split_at(*n_in),
+        _ => return Err(invalid(format!("wrong number of wires in \"{}\"", line))),
+    };
+    match (*op, inputs, outputs) {
+        ("XOR", &[a, b], &[out]) => gates.push(Gate::Xor(a, b, out)),
+        ("AND", &[a, b], &[out]) => gates.push(Gate::And(a, b, out)),
+        ("INV", &[a], &[out]) => gates.push(Gate::Inv(a, out)),
+        ("EQW", &[a], &[out]) => gates.push(Gate::Eqw(a, out)),
+        ("EQ", &[c], &[out]) if c <= 1 => gates.push(Gate::Eq(c == 1, out)),
+        ("MAND", _, _) if inputs.len() == 2 * outputs.len() => {
+            let (a, b) = inputs.split_at(outputs.len());
+            gates.extend(a.iter().zip(b).zip(outputs).map(|((&a, &b), &out)| Gate::And(a, b, out)));
+        }
+        _ => return Err(invalid(format!("unknown or malformed gate \"{}\"", line))),
+    }
+    Ok(())
+}
+
+impl Circuit {
+    /**
+     * Parses a circuit in Bristol Fashion:
+     *
+     * ngates nwires
+     * niv ni_1 .. ni_niv
+     * nov no_1 .. no_nov
+     * one gate per line: nin nout input wires.. output wires.. XOR|AND|INV|EQ|EQW|MAND
+     *
+     * The input of EQ is the constant 0 or 1. A MAND gate with 2k inputs and k outputs becomes
+     * k AND gates of the i-th and (k + i)-th input. The circuit is checked with `check`.
+     */
+    pub fn parse(text: &str) -> Result<Circuit, OteError> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut header = lines.next().ok_or_else(|| invalid("empty circuit file".to_owned()))?.split_whitespace();
+        let ngates = number(&mut header, "number of gates")?;
+        let wires = number(&mut header, "number of wires")?;
+        let inputs = counts(lines.next(), "input sizes")?;
+        let outputs = counts(lines.next(), "output sizes")?;
+        // Grown as the gates are parsed, since the header's count is not to be trusted.
+        let mut gates = Vec::new();
+        let mut parsed = 0;
+        for line in lines {
+            parse_gate(line, &mut gates)?;
+            parsed += 1;
+        }
+        if parsed != ngates {
+            return Err(invalid(format!("header announces {} gates but the file has {}", ngates, parsed)));
+        }
+        // In a file every wire is an input or the output of one gate.
+        if inputs.iter().try_fold(gates.len(), |acc, &n| acc.checked_add(n)).is_none_or(|most| wires > most) {
+            return Err(invalid(format!("{} wires for {} gates and inputs of {:?} wires", wires, gates.len(), inputs)));
+        }
+        let circuit = Circuit { wires, inputs, outputs, gates };
+        circuit.check().map_err(|e| match e {
+            OteError::BadParameter(msg) => invalid(msg),
+            e => e,
+        })?;
+        Ok(circuit)
+    }
+
+    pub fn load(path: &Path) -> Result<Circuit, OteError> {
+        Circuit::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn input_wires(&self, party: usize) -> Range<usize> {
+        let start = self.inputs[..party].iter().sum::<usize>();
+        start..start + self.inputs[party]
+    }
+
+    pub fn output_wires(&self) -> Range<usize> {
+        self.wires - self.outputs.iter().sum::<usize>()..self.wires
+    }
+
+    pub fn and_gates(&self) -> usize {
+        self.gates.iter().filter(|gate| matches!(gate, Gate::And(..))).count()
+    }
+
+    /**
+     * Checks that all wires exist, that no wire is written twice, that every gate only reads
+     * wires that are inputs or were written before, and that all outputs get written.
+     * Nothing here is allocated by the number of wires, which must not exceed the input wires
+     * and the largest wire a gate uses.
+     */
+    pub fn check(&self) -> Result<(), OteError> {
+        let bad = |msg: String| Err(OteError::BadParameter(msg));
+        let sum = |sizes: &[usize]| sizes.iter().try_fold(0usize, |acc, &n| acc.checked_add(n));
+        let (Some(input_wires), Some(output_wires)) = (sum(&self.inputs), sum(&self.outputs)) else {
+            return bad("the input or output sizes overflow".to_owned());
+        };
+        if input_wires.max(output_wires) > self.wires {
+            return bad(format!("{} input and {} output wires in a circuit of {} wires", input_wires, output_wires, self.wires));
+        }
+        let used = self.gates.iter().flat_map(|gate| gate.inputs().into_iter().chain([gate.output()])).max().map_or(0, |wire| wire.saturating_add(1));
+        if self.wires > used.max(input_wires) {
+            return bad(format!("{} wires but the gates use only {} and there are {} input wires", self.wires, used, input_wires));
+        }
+        // The wires written so far, at most one per gate. Input wires count as set.
+        let mut written = HashSet::with_capacity(self.gates.len());
+        let is_set = |written: &HashSet<usize>, wire: usize| wire < input_wires || written.contains(&wire);
+        for (i, gate) in self.gates.iter().enumerate() {
+            if let Some(&wire) = gate.inputs().iter().find(|&&wire| wire >= self.wires || !is_set(&written, wire)) {
+                return bad(format!("gate {} reads wire {}, which is not set before", i, wire));
+            }
+            let out = gate.output();
+            if out >= self.wires || is_set(&written, out) {
+                return bad(format!("gate {} writes wire {}, which is out of range or already set", i, out));
+            }
+            written.insert(out);
+        }
+        // Output wires that are also inputs are set, and at most one wire per gate is written.
+        let first_output = (self.wires - output_wires).max(input_wires);
+        if self.wires - first_output > written.len() {
+            return bad(format!("{} output wires are written by {} gates", self.wires - first_output, written.len()));
+        }
+        if let Some(wire) = (first_output..self.wires).find(|&wire| !is_set(&written, wire)) {
+            return bad(format!("output wire {} is never set", wire));
+        }
+        Ok(())
+    }
+
+    // Splits the output wires into the outputs.
+    pub fn split_outputs(&self, bits: &[bool]) -> Vec<Vec<bool>> {
+        let mut rest = bits;
+        self.outputs
+            .iter()
+            .map(|&n| {
+                let (output, tail) = rest.split_at(n);
+                rest = tail;
+                output.to_vec()
+            })
+            .collect()
+    }
+
+    /**
+     * Evaluates the circuit in the clear, for checking circuits and the results of the
+     * protocols. inputs[i] holds the bits of input i and the result the bits of every output.
+     */
+    pub fn evaluate(&self, inputs: &[Vec<bool>]) -> Result<Vec<Vec<bool>>, OteError> {
+        self.check()?;
+        let sizes = inputs.iter().map(Vec::len).collect::<Vec<_>>();
+        if sizes != self.inputs {
+            return Err(OteError::BadParameter(format!("inputs of {:?} bits for a circuit that takes {:?}", sizes, self.inputs)));
+        }
+        let mut wires = vec![false; self.wires];
+        wires[..sizes.iter().sum::<usize>()].copy_from_slice(&inputs.concat());
+        for gate in &self.gates {
+            wires[gate.output()] = match *gate {
+                Gate::Xor(a, b, _) => wires[a] ^ wires[b],
+                Gate::And(a, b, _) => wires[a] & wires[b],
+                Gate::Inv(a, _) => !wires[a],
+                Gate::Eqw(a, _) => wires[a],
+                Gate::Eq(c, _) => c,
+            };
+        }
+        Ok(self.split_outputs(&wires[self.output_wires()]))
+    }
+
+    /**
+     * Groups the gates by the number of AND gates on their longest path from the inputs.
+     * Layer l holds the AND gates of depth l, whose inputs are all of a lower depth, and then the
+     * other gates of depth l in circuit order. Layer 0 has no AND gates.
+     */
+    pub fn layers(&self) -> Vec<(Vec<usize>, Vec<usize>)> {
+        let mut depth = vec![0; self.wires];
+        let mut layers: Vec<(Vec<usize>, Vec<usize>)> = vec![(Vec::new(), Vec::new())];
+        for (i, gate) in self.gates.iter().enumerate() {
+            let d = gate.inputs().iter().map(|&wire| depth[wire]).max().unwrap_or(0);
+            let d = if let Gate::And(..) = gate { d + 1 } else { d };
+            depth[gate.output()] = d;
+            if layers.len() <= d {
+                layers.resize(d + 1, (Vec::new(), Vec::new()));
+            }
+            match gate {
+                Gate::And(..) => layers[d].0.push(i),
+                _ => layers[d].1.push(i),
+            }
+        }
+        layers
+    }
+}
@@ -1,19 +1,31 @@
-use rand::{random, RngCore, SeedableRng};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use rand_aes::{seeds::{Aes128Ctr128Seed, Aes256Ctr128Seed}, Aes128Ctr128, Aes256Ctr128};
-use sha3::{Digest, Sha3_256};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::{Shake256, Shake256Reader};
+pub use subtle::Choice;
+use subtle::ConditionallySelectable;
+pub use zeroize::Zeroizing;
+
+use crate::error::OteError;
 
 pub const OUTPUT_SIZE: usize = 256;
 pub static USE_AES: bool = true;
 
+pub type MessagePairs = Vec<(Vec<bool>, Vec<bool>)>;
+
+/**
+ * Secret bits, like seeds, s and PRG output, and secret rows, like t_j and q_j.
+ * They are overwritten with zeros when dropped, so they do not stay behind in freed memory.
+ */
+pub type SecretBits = Zeroizing<Vec<bool>>;
+pub type SecretRows = Zeroizing<Vec<Vec<bool>>>;
+
 pub fn int_to_boolvec_len(input: usize, len: usize) -> Vec<bool> {
     (0..len)
         .rev()
         .map(|i| {
-            (match input.checked_shr(i as u32) {
-                Some(s) => s,
-                None => 0,
-            } & 1)
-                != 0
+            (input.checked_shr(i as u32).unwrap_or_default() & 1) != 0
         })
         .collect::<Vec<_>>()
 }
@@ -25,49 +37,94 @@ pub fn byte_to_boolvec(input: u8) -> Vec<bool> {
         .collect::<Vec<_>>()
 }
 
-pub fn boolvec_to_u8(input: &Vec<bool>) -> u8 {
-    input.iter().fold(0, |acc, &b| ((acc << 1) + (b as u8)))
+pub fn boolvec_to_u8(input: &[bool]) -> u8 {
+    input.iter().fold(0, |acc, &b| (acc << 1) + (b as u8))
 }
 
-pub fn xor_boolvec(l: &Vec<bool>, r: &Vec<bool>) -> Vec<bool> {
+pub fn xor_boolvec(l: &[bool], r: &[bool]) -> Vec<bool> {
     l.iter().zip(r).map(|(l, r)| l ^ r).collect::<Vec<_>>()
 }
 
-pub fn transpose(matrix: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+// A secret bit as a `Choice`, for selecting without a branch on it.
+pub fn ct_choice(b: bool) -> Choice {
+    Choice::from(b as u8)
+}
+
+// l if choice is 0 and r if it is 1, selected bit by bit in constant time.
+pub fn select_boolvec(l: &[bool], r: &[bool], choice: Choice) -> Vec<bool> {
+    l.iter()
+        .zip(r)
+        .map(|(&l, &r)| u8::conditional_select(&(l as u8), &(r as u8), choice) != 0)
+        .collect::<Vec<_>>()
+}
+
+pub fn transpose(matrix: &[Vec<bool>]) -> Vec<Vec<bool>> {
     let outer_axis = matrix.len();
-    let inner_axis = matrix[0].len();
+    let inner_axis = matrix.first().map_or(0, |row| row.len());
     let mut m_transp = Vec::new();
     let mut inner = Vec::new();
     inner.resize(outer_axis, false);
     m_transp.resize(inner_axis, inner);
-    (0..inner_axis).into_iter().for_each(|row| {
-        (0..outer_axis)
-            .into_iter()
-            .for_each(|col| m_transp[row][col] = matrix[col][row]);
+    (0..inner_axis).for_each(|row| {
+        (0..outer_axis).for_each(|col| m_transp[row][col] = matrix[col][row]);
     });
     m_transp
 }
 
-pub fn hash_bits(v: &Vec<bool>, j: &Vec<bool>) -> Vec<bool> {
-    let mut hasher = Sha3_256::new();
-    bool_vec_to_byte_vec(v)
+/**
+ * Hash of (v, j) stretched to len bits with SHAKE256, so pads can have any length.
+ */
+pub fn hash_bits(v: &[bool], j: &[bool], len: usize) -> Vec<bool> {
+    let mut hasher = Shake256::default();
+    Zeroizing::new(bool_vec_to_byte_vec(v))
         .rchunks(32)
         .for_each(|x| hasher.update(x));
-    bool_vec_to_byte_vec(j)
+    Zeroizing::new(bool_vec_to_byte_vec(j))
         .rchunks(32)
         .for_each(|x| hasher.update(x));
-    let output: [u8; 32] = *hasher.finalize().as_ref();
-    output
+    let mut output = Zeroizing::new(vec![0; usize::div_ceil(len, 8)]);
+    hasher.finalize_xof().read(&mut output);
+    let mut bits = byte_vec_to_bool_vec(&output);
+    bits.truncate(len);
+    bits
+}
+
+/**
+ * Checks that x_0 and x_1 of every pair have the same length and returns that length,
+ * which is the output length of the OTs.
+ */
+pub fn check_message_lengths(messages: &[(Vec<bool>, Vec<bool>)]) -> Result<usize, OteError> {
+    let len = messages.first().map_or(0, |(x_0, _)| x_0.len());
+    match messages
         .iter()
-        .flat_map(|&x| byte_to_boolvec(x))
-        .take(OUTPUT_SIZE)
-        .collect::<Vec<_>>()
+        .position(|(x_0, x_1)| x_0.len() != len || x_1.len() != len)
+    {
+        Some(index) => Err(OteError::MessageLengthMismatch {
+            index,
+            expected: len,
+            len_0: messages[index].0.len(),
+            len_1: messages[index].1.len(),
+        }),
+        None => Ok(len),
+    }
 }
 
-pub fn bool_vec_to_byte_vec(v: &Vec<bool>) -> Vec<u8> {
+// Checks the inputs of an in-process OT: one choice bit per message pair, and equal message lengths.
+pub fn check_inputs(messages: &[(Vec<bool>, Vec<bool>)], choice: &[bool]) -> Result<usize, OteError> {
+    if messages.len() != choice.len() {
+        return Err(OteError::BadParameter(format!(
+            "{} choice bits for {} message pairs",
+            choice.len(),
+            messages.len()
+        )));
+    }
+    check_message_lengths(messages)
+}
+
+pub fn bool_vec_to_byte_vec(v: &[bool]) -> Vec<u8> {
     v.rchunks(8)
         .rev()
-        .map(|x| boolvec_to_u8(&x.to_vec()))
+        .map(boolvec_to_u8)
         .collect::<Vec<u8>>()
 }
 
@@ -75,94 +132,206 @@ pub fn get_bit(byte: u8, pos: u8) -> bool {
     (byte >> pos) & 1 != 0
 }
 
-pub fn byte_vec_to_bool_vec(v: &Vec<u8>) -> Vec<bool> {
+pub fn byte_vec_to_bool_vec(v: &[u8]) -> Vec<bool> {
+    let mut bits = Vec::with_capacity(8 * v.len());
     v.iter()
-        .flat_map(|&x| {
-            [
-                get_bit(x, 7),
-                get_bit(x, 6),
-                get_bit(x, 5),
-                get_bit(x, 4),
-                get_bit(x, 3),
-                get_bit(x, 2),
-                get_bit(x, 1),
-                get_bit(x, 0),
-            ]
-            .to_vec()
-        })
-        .collect::<Vec<_>>()
+        .for_each(|&x| bits.extend((0..8).rev().map(|pos| get_bit(x, pos))));
+    bits
 }
 
-pub fn int_vec_to_bool_vec(v: &Vec<u64>) -> Vec<bool> {
-    byte_vec_to_bool_vec(&v.iter().flat_map(|&x| x.to_be_bytes()).collect::<Vec<_>>())
+// Inverse of `bool_vec_to_byte_vec` for a vector of len bits, dropping the zero padding in front.
+// If v holds fewer than len bits, zeros are added in front instead.
+pub fn byte_vec_to_bool_vec_len(v: &[u8], len: usize) -> Vec<bool> {
+    let bits = Zeroizing::new(byte_vec_to_bool_vec(v));
+    if bits.len() < len {
+        let mut padded = vec![false; len - bits.len()];
+        padded.extend_from_slice(&bits);
+        return padded;
+    }
+    bits[bits.len() - len..].to_vec()
+}
+
+// Big-endian u64 from the first 8 bytes of a slice that is known to be long enough.
+pub(crate) fn be_u64(bytes: &[u8]) -> u64 {
+    let mut x = [0; 8];
+    x.copy_from_slice(&bytes[..8]);
+    u64::from_be_bytes(x)
+}
+
+pub fn int_vec_to_bool_vec(v: &[u64]) -> Vec<bool> {
+    byte_vec_to_bool_vec(&Zeroizing::new(v.iter().flat_map(|&x| x.to_be_bytes()).collect::<Vec<_>>()))
 }
 
 pub fn int_to_bool_vec(i: usize) -> Vec<bool> {
-    byte_vec_to_bool_vec(&i.to_be_bytes().to_vec())
+    byte_vec_to_bool_vec(&i.to_be_bytes())
 }
 
-pub fn random_boolvec_len(m: usize) -> Vec<bool> {
+pub fn random_boolvec_len<R: CryptoRng + RngCore>(m: usize, rng: &mut R) -> Vec<bool> {
     let needed_bytes = usize::div_ceil(m, 8);
-    let res: &mut Vec<u8> = &mut Vec::with_capacity(needed_bytes);
-    res.resize(needed_bytes, 0);
-    rand::RngCore::try_fill_bytes(&mut rand::thread_rng(),res).ok();
-    byte_vec_to_bool_vec(&res).iter().take(m).map(|x| *x).collect::<Vec<bool>>()
+    let mut res = Zeroizing::new(vec![0; needed_bytes]);
+    rng.fill_bytes(&mut res);
+    let mut bits = byte_vec_to_bool_vec(&res);
+    bits.truncate(m);
+    bits
+}
+
+/**
+ * A new RNG seeded from `rng`, for randomness that is needed later or on another thread.
+ * With a seeded `rng` it is just as reproducible.
+ */
+pub fn fork_rng<R: CryptoRng + RngCore>(rng: &mut R) -> ChaCha20Rng {
+    ChaCha20Rng::from_seed(rng.gen())
 }
 
 
 // Stolen from: https://stackoverflow.com/questions/29570607/is-there-a-good-way-to-convert-a-vect-to-an-array
 use std::convert::TryInto;
-pub fn to_array<T, const N: usize>(v: Vec<T>) -> [T; N] {
-    v.try_into()
-        .unwrap_or_else(|v: Vec<T>| panic!("Expected a Vec of length {} but it was {}", N, v.len()))
+pub fn to_array<T, const N: usize>(v: Vec<T>) -> Result<[T; N], OteError> {
+    v.try_into().map_err(|v: Vec<T>| {
+        OteError::BadParameter(format!("Expected a Vec of length {} but it was {}", N, v.len()))
+    })
 }
 
-pub fn pseudo_random_gen(seed: &Vec<bool>, num: usize) -> Vec<bool> {
-    if USE_AES {
-        pseudo_random_gen_aes(seed, num)
+// The PRGs are keyed with 128, 192 or 256 bit seeds, so these are the supported values of k.
+pub fn check_seed_length(len: usize) -> Result<(), OteError> {
+    if len == 128 || len == 192 || len == 256 {
+        Ok(())
     } else {
-        pseudo_random_gen_cha_cha(seed, num)
+        Err(OteError::BadParameter(format!("seeds must have 128, 192 or 256 bits, not {}", len)))
     }
 }
 
-
-pub fn pseudo_random_gen_cha_cha(seed: &Vec<bool>, num: usize) -> Vec<bool> {
-    // Padding strategy as described by the orignal Salsa20 paper. Cannot change the nonce as required though...
-    let mut s = vec![false; 256];
-    if seed.len() == 256 {
-        s.copy_from_slice(seed);
-    } else if seed.len() == 128 {
-        s[0..128].copy_from_slice(seed);
-        s[128..256].copy_from_slice(seed);
+// AES and ChaCha20 only take 128 and 256 bit keys.
+fn check_cipher_seed_length(len: usize) -> Result<(), OteError> {
+    if len == 128 || len == 256 {
+        Ok(())
     } else {
-        panic!("Wrong seed length")
+        Err(OteError::BadParameter(format!("cipher seeds must have 128 or 256 bits, not {}", len)))
     }
-    let bytes = bool_vec_to_byte_vec(&s);
-    let mut x = rand_chacha::ChaCha20Rng::from_seed(to_array(bytes));
-    let needed_bytes = usize::div_ceil(num, 8);
-    let res: &mut Vec<u8> = &mut Vec::with_capacity(needed_bytes);
-    res.resize(needed_bytes, 0);
-    x.fill_bytes(&mut res[..]);
-    byte_vec_to_bool_vec(res).into_iter().take(num).collect::<Vec<_>>()
-}
-
-
-pub fn pseudo_random_gen_aes(seed: &Vec<bool>, num: usize) -> Vec<bool> {
-    if seed.len() == 128 {
-        let mut x = Aes128Ctr128::from_seed(Aes128Ctr128Seed::new(to_array(bool_vec_to_byte_vec(&seed)), 0));
-        let needed_bytes = usize::div_ceil(num, 8);
-        let res: &mut Vec<u8> = &mut Vec::with_capacity(needed_bytes);
-        res.resize(needed_bytes, 0);
-        x.fill_bytes(&mut res[..]);
-        byte_vec_to_bool_vec(res).into_iter().take(num).collect::<Vec<_>>()    
-    } else if seed.len() == 256 {
-        let mut x = Aes256Ctr128::from_seed(Aes256Ctr128Seed::new(to_array(bool_vec_to_byte_vec(&seed)), 0));
-        let needed_bytes = usize::div_ceil(num, 8);
-        let res: &mut Vec<u8> = &mut Vec::with_capacity(needed_bytes);
-        res.resize(needed_bytes, 0);
-        x.fill_bytes(&mut res[..]);
-        byte_vec_to_bool_vec(res).into_iter().take(num).collect::<Vec<_>>()
-    } else {
-        panic!("Wrong seed length")
+}
+
+// The bytes of a cipher key, wiped when dropped like the seed they come from.
+fn key_bytes<const N: usize>(seed: &[bool]) -> Result<Zeroizing<[u8; N]>, OteError> {
+    let bytes = Zeroizing::new(bool_vec_to_byte_vec(seed));
+    if bytes.len() != N {
+        return Err(OteError::BadParameter(format!("Expected a key of {} bytes but it was {}", N, bytes.len())));
+    }
+    let mut key = Zeroizing::new([0; N]);
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+pub fn pseudo_random_gen(seed: &[bool], num: usize) -> Result<Vec<bool>, OteError> {
+    Ok(Prg::new(seed)?.next_bits(num))
+}
+
+
+pub fn pseudo_random_gen_cha_cha(seed: &[bool], num: usize) -> Result<Vec<bool>, OteError> {
+    Ok(Prg::new_cha_cha(seed)?.next_bits(num))
+}
+
+pub fn pseudo_random_gen_aes(seed: &[bool], num: usize) -> Result<Vec<bool>, OteError> {
+    Ok(Prg::new_aes(seed)?.next_bits(num))
+}
+
+enum PrgCore {
+    Aes128(Aes128Ctr128),
+    Aes256(Aes256Ctr128),
+    ChaCha(Box<ChaCha20Rng>),
+    Shake(Box<Shake256Reader>),
+}
+
+/**
+ * Stateful version of `pseudo_random_gen`.
+ * Consecutive calls to `next_bits` continue the same stream, so expanding a seed
+ * chunk by chunk gives exactly the bits of one big `pseudo_random_gen` call.
+ * The AES round keys and the SHAKE256 state are wiped on drop by their crates, the ChaCha20
+ * state is not, which is one more reason AES is the default.
+ */
+pub struct Prg {
+    core: PrgCore,
+    // Generated bits that have not been handed out yet, less than one block.
+    leftover: SecretBits,
+}
+
+impl Prg {
+    // 192 bit seeds have no matching AES or ChaCha key size, so they always use SHAKE256.
+    pub fn new(seed: &[bool]) -> Result<Prg, OteError> {
+        if seed.len() == 192 {
+            Prg::new_shake(seed)
+        } else if USE_AES {
+            Prg::new_aes(seed)
+        } else {
+            Prg::new_cha_cha(seed)
+        }
+    }
+
+    pub fn new_cha_cha(seed: &[bool]) -> Result<Prg, OteError> {
+        check_cipher_seed_length(seed.len())?;
+        // Padding strategy as described by the orignal Salsa20 paper. Cannot change the nonce as required though...
+        let mut s = Zeroizing::new(vec![false; 256]);
+        if seed.len() == 256 {
+            s.copy_from_slice(seed);
+        } else {
+            s[0..128].copy_from_slice(seed);
+            s[128..256].copy_from_slice(seed);
+        }
+        let core = PrgCore::ChaCha(Box::new(ChaCha20Rng::from_seed(*key_bytes(&s)?)));
+        Ok(Prg { core, leftover: Zeroizing::default() })
+    }
+
+    pub fn new_aes(seed: &[bool]) -> Result<Prg, OteError> {
+        check_cipher_seed_length(seed.len())?;
+        let core = if seed.len() == 128 {
+            PrgCore::Aes128(Aes128Ctr128::from_seed(Aes128Ctr128Seed::new(*key_bytes(seed)?, 0)))
+        } else {
+            PrgCore::Aes256(Aes256Ctr128::from_seed(Aes256Ctr128Seed::new(*key_bytes(seed)?, 0)))
+        };
+        Ok(Prg { core, leftover: Zeroizing::default() })
+    }
+
+    // Hash-based PRG: the output stream of SHAKE256 on a domain tag and the seed.
+    pub fn new_shake(seed: &[bool]) -> Result<Prg, OteError> {
+        check_seed_length(seed.len())?;
+        let mut hasher = Shake256::default();
+        hasher.update(b"OTE PRG");
+        hasher.update(&Zeroizing::new(bool_vec_to_byte_vec(seed)));
+        let core = PrgCore::Shake(Box::new(hasher.finalize_xof()));
+        Ok(Prg { core, leftover: Zeroizing::default() })
+    }
+
+    pub fn next_bits(&mut self, num: usize) -> Vec<bool> {
+        let from_leftover = num.min(self.leftover.len());
+        // Sized up front, so no partial copy of the output is left behind by a reallocation.
+        let mut res = Vec::with_capacity(num);
+        res.extend(self.leftover.drain(..from_leftover));
+        if res.len() < num {
+            let missing = num - res.len();
+            // Whole blocks, the bits that are not needed yet are kept for the next call.
+            let mut bytes = Zeroizing::new(vec![0; 16 * usize::div_ceil(missing, 128)]);
+            match &mut self.core {
+                PrgCore::Aes128(x) => fill_blocks(x, &mut bytes),
+                PrgCore::Aes256(x) => fill_blocks(x, &mut bytes),
+                PrgCore::ChaCha(x) => x.fill_bytes(&mut bytes),
+                PrgCore::Shake(x) => x.read(&mut bytes),
+            }
+            let mut bits = Zeroizing::new(byte_vec_to_bool_vec(&bytes));
+            self.leftover = Zeroizing::new(bits.split_off(missing));
+            res.extend_from_slice(&bits);
+        }
+        res
     }
 }
+
+/**
+ * Fill bytes with whole AES blocks. The `fill_bytes` of rand_aes always draws one block more
+ * than it writes, so consecutive calls would not continue the same stream.
+ */
+fn fill_blocks<R: rand_aes::Random>(rng: &R, bytes: &mut [u8]) {
+    bytes
+        .chunks_mut(16)
+        .for_each(|chunk| chunk.copy_from_slice(&rng.u128().to_le_bytes()[..chunk.len()]));
+}
+
+// Recommended number of OTs per chunk for the streaming extensions.
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 16;
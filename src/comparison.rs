@@ -0,0 +1,314 @@
+// Millionaires' protocol of CrypTFlow2 (Rathee et al. 2020): XOR shares of [x < y] for l-bit
+// x held by the sender and y held by the receiver of a session.
+//
+// x and y are cut into digits of m bits. For every digit the sender fills a table with one row
+// per possible digit v of y, holding its random shares lt_0, eq_0 xor'ed with [x_j < v] and
+// [x_j = v], and the receiver picks row y_j with a 1-out-of-2^m OT. The digits are then merged
+// pairwise, lowest first, in a tree of depth log(l / m):
+//   lt = lt_hi xor (eq_hi and lt_lo), eq = eq_hi and eq_lo,
+// where the two terms of lt never both hold. Each AND of shared bits u and v needs the cross
+// terms u_0 v_1 and v_0 u_1, which are two 1-bit OTs from the sender to the receiver. All ANDs
+// of a tree level are extended together.
+// `less_than` runs both parties on a session in one process, `less_than_sender` and
+// `less_than_receiver` run one party each over a `Channel`.
+
+use rand::{CryptoRng, Rng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtConfig, OtExtReceiver, OtExtSender};
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_n::{choice_of, mask_tables, ot_n, unmask};
+use crate::ot_primitive::SafePrimeGroup;
+use crate::session::OtExtSession;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComparisonConfig {
+    bits: usize,
+    digit_bits: usize,
+    batch: usize,
+}
+
+impl ComparisonConfig {
+    /**
+     * Comparisons of `bits`-bit integers, bits <= 64, with digits of `digit_bits` bits, so
+     * 1-out-of-2^digit_bits OTs, and at most `batch` comparisons per round of extensions.
+     */
+    pub fn new(bits: usize, digit_bits: usize, batch: usize) -> Result<ComparisonConfig, OteError> {
+        if !(1..=64).contains(&bits) || !(1..=16).contains(&digit_bits) || batch == 0 {
+            return Err(OteError::BadParameter(format!(
+                "comparison of {} bits with {}-bit digits in batches of {}",
+                bits, digit_bits, batch
+            )));
+        }
+        Ok(ComparisonConfig { bits, digit_bits, batch })
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn digit_bits(&self) -> usize {
+        self.digit_bits
+    }
+
+    pub fn batch(&self) -> usize {
+        self.batch
+    }
+
+    // The bit offset and width of every digit, lowest first. The last one may be narrower.
+    fn digits(&self) -> Vec<(usize, usize)> {
+        (0..self.bits).step_by(self.digit_bits).map(|start| (start, self.digit_bits.min(self.bits - start))).collect()
+    }
+}
+
+// 64-bit integers compared digit by digit with 4-bit digits and 1024 comparisons per batch.
+impl Default for ComparisonConfig {
+    fn default() -> Self {
+        ComparisonConfig {
+            bits: 64,
+            digit_bits: 4,
+            batch: 1024,
+        }
+    }
+}
+
+// One party's shares of lt and eq of every merged digit, for all comparisons of a batch.
+type Levels = Vec<(Vec<bool>, Vec<bool>)>;
+
+// One party's shares of lt and eq of every digit of a batch, digit-major.
+type LeafShares = Zeroizing<Vec<(bool, bool)>>;
+
+/**
+ * Sender of the ANDs of XOR-shared u and v: it offers (r, r xor u_0) and (r', r' xor v_0) for
+ * every i, and its share of u[i] and v[i] is u_0 v_0 xor r xor r'. Returns the messages and the masks.
+ */
+fn and_messages<R: CryptoRng + RngCore>(u_0: &[bool], v_0: &[bool], rng: &mut R) -> (MessagePairs, SecretBits) {
+    let masks = Zeroizing::new((0..2 * u_0.len()).map(|_| rng.gen::<bool>()).collect::<Vec<_>>());
+    let messages = u_0
+        .iter()
+        .zip(v_0)
+        .zip(masks.chunks_exact(2))
+        .flat_map(|((&u, &v), r)| [(r[0], u), (r[1], v)])
+        .map(|(r, x)| (vec![r], vec![r ^ x]))
+        .collect();
+    (messages, masks)
+}
+
+fn and_sender(u_0: &[bool], v_0: &[bool], masks: &[bool]) -> Vec<bool> {
+    u_0.iter().zip(v_0).zip(masks.chunks_exact(2)).map(|((&u, &v), r)| (u & v) ^ r[0] ^ r[1]).collect()
+}
+
+// Receiver of the ANDs: it picks with v_1 and u_1, and the received bits are the cross terms.
+fn and_choice(u_1: &[bool], v_1: &[bool]) -> SecretBits {
+    Zeroizing::new(u_1.iter().zip(v_1).flat_map(|(&u, &v)| [v, u]).collect())
+}
+
+fn and_receiver(u_1: &[bool], v_1: &[bool], received: &[Vec<bool>]) -> Vec<bool> {
+    u_1.iter().zip(v_1).zip(received.chunks_exact(2)).map(|((&u, &v), r)| (u & v) ^ r[0][0] ^ r[1][0]).collect()
+}
+
+// Shares of u[i] and v[i] for XOR-shared u and v, as (sender, receiver) operands and results.
+fn and<R: CryptoRng + RngCore>(
+    session: &mut OtExtSession,
+    (u_0, v_0): &(Vec<bool>, Vec<bool>),
+    (u_1, v_1): &(Vec<bool>, Vec<bool>),
+    rng: &mut R,
+) -> Result<(Vec<bool>, Vec<bool>), OteError> {
+    let (messages, masks) = and_messages(u_0, v_0, rng);
+    let received = Zeroizing::new(session.extend(&messages, &and_choice(u_1, v_1))?);
+    Ok((and_sender(u_0, v_0, &masks), and_receiver(u_1, v_1, &received)))
+}
+
+// The operands of all ANDs of a level, in one extension: eq_hi and lt_lo, then eq_hi and eq_lo.
+fn operands(levels: &Levels) -> (Vec<bool>, Vec<bool>) {
+    let pairs = levels.chunks_exact(2).collect::<Vec<_>>();
+    let eq_hi = || pairs.iter().flat_map(|pair| pair[1].1.iter().copied());
+    let u = eq_hi().chain(eq_hi()).collect();
+    let v = pairs
+        .iter()
+        .flat_map(|pair| pair[0].0.iter().copied())
+        .chain(pairs.iter().flat_map(|pair| pair[0].1.iter().copied()))
+        .collect();
+    (u, v)
+}
+
+// The next level from the products of `operands`: lt = lt_hi xor (eq_hi and lt_lo), eq = eq_hi and eq_lo.
+fn merge(mut levels: Levels, products: &[bool], n: usize) -> Levels {
+    let half = levels.len() / 2 * n;
+    let mut next = levels
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(p, pair)| {
+            let lt = xor_boolvec(&pair[1].0, &products[p * n..(p + 1) * n]);
+            (lt, products[half + p * n..half + (p + 1) * n].to_vec())
+        })
+        .collect::<Vec<_>>();
+    // An odd digit on top moves up unchanged.
+    if levels.len() % 2 == 1 {
+        next.push(levels.pop().expect("odd number of digits"));
+    }
+    next
+}
+
+// The digit of x at the given offset and width.
+fn digit(x: u64, (start, width): (usize, usize)) -> usize {
+    (x >> start & ((1u64 << width) - 1)) as usize
+}
+
+/**
+ * Sender of the leaves: its random shares lt, eq of every digit, digit-major, and the tables
+ * with one row (lt xor [x_j < v], eq xor [x_j = v]) per digit v of y.
+ */
+fn leaf_tables<R: CryptoRng + RngCore>(config: &ComparisonConfig, x: &[u64], rng: &mut R) -> (LeafShares, Vec<Vec<Vec<bool>>>) {
+    let mut own = Zeroizing::new(Vec::with_capacity(x.len() * config.digits().len()));
+    let mut tables = Vec::with_capacity(own.capacity());
+    for d in config.digits() {
+        for &x in x {
+            let (lt, eq) = (rng.gen::<bool>(), rng.gen::<bool>());
+            let x_j = digit(x, d);
+            tables.push((0..1usize << d.1).map(|v| vec![lt ^ (x_j < v), eq ^ (x_j == v)]).collect());
+            own.push((lt, eq));
+        }
+    }
+    (own, tables)
+}
+
+// Receiver of the leaves: the digit of y to pick from every table, in the order of `leaf_tables`.
+fn leaf_choices(config: &ComparisonConfig, y: &[u64]) -> Zeroizing<Vec<usize>> {
+    Zeroizing::new(config.digits().into_iter().flat_map(|d| y.iter().map(move |&y| digit(y, d))).collect())
+}
+
+// One party's shares of the leaves as the first level.
+fn leaves(shares: &[(bool, bool)], n: usize) -> Levels {
+    shares.chunks(n).map(|digit| digit.iter().copied().unzip()).collect()
+}
+
+// The received rows of the leaf tables as shares of lt and eq.
+fn received_leaves(received: &[Vec<bool>]) -> LeafShares {
+    Zeroizing::new(received.iter().map(|row| (row[0], row[1])).collect())
+}
+
+// Shares of [x < y] for one batch.
+fn compare_batch<R: CryptoRng + RngCore>(session: &mut OtExtSession, config: &ComparisonConfig, x: &[u64], y: &[u64], rng: &mut R) -> Result<(Vec<bool>, Vec<bool>), OteError> {
+    let n = x.len();
+    let (own, tables) = leaf_tables(config, x, rng);
+    let received = Zeroizing::new(ot_n(session, &tables, &leaf_choices(config, y), rng)?);
+    let mut sender = leaves(&own, n);
+    let mut receiver = leaves(&received_leaves(&received), n);
+    while sender.len() > 1 {
+        let products = and(session, &operands(&sender), &operands(&receiver), rng)?;
+        sender = merge(sender, &products.0, n);
+        receiver = merge(receiver, &products.1, n);
+    }
+    Ok((sender.pop().map(|(lt, _)| lt).unwrap_or_default(), receiver.pop().map(|(lt, _)| lt).unwrap_or_default()))
+}
+
+fn check_values(config: &ComparisonConfig, values: &[u64]) -> Result<(), OteError> {
+    match values.iter().find(|&&v| config.bits < 64 && v >> config.bits != 0) {
+        Some(v) => Err(OteError::BadParameter(format!("{} has more than {} bits", v, config.bits))),
+        None => Ok(()),
+    }
+}
+
+/**
+ * XOR shares of [x[i] < y[i]] for all i, with x held by the sender and y by the receiver of the
+ * session. Returns the sender's and the receiver's shares.
+ */
+pub fn less_than<R: CryptoRng + RngCore>(
+    session: &mut OtExtSession,
+    config: &ComparisonConfig,
+    x: &[u64],
+    y: &[u64],
+    rng: &mut R,
+) -> Result<(Vec<bool>, Vec<bool>), OteError> {
+    if x.len() != y.len() {
+        return Err(OteError::BadParameter(format!("{} sender and {} receiver values", x.len(), y.len())));
+    }
+    check_values(config, x)?;
+    check_values(config, y)?;
+    let mut res = (Vec::with_capacity(x.len()), Vec::with_capacity(x.len()));
+    for (x, y) in x.chunks(config.batch).zip(y.chunks(config.batch)) {
+        let (sender, receiver) = compare_batch(session, config, x, y, rng)?;
+        res.0.extend(sender);
+        res.1.extend(receiver);
+    }
+    Ok(res)
+}
+
+/**
+ * Sender of `less_than` over a channel, holding x: returns its shares of [x[i] < y[i]].
+ * The receiver must hold as many values and use the same configurations.
+ * Messages per batch: the OT extension for the keys of the leaf tables, "comparison tables" with
+ * the masked tables, then one OT extension per level of the tree for its ANDs.
+ */
+pub async fn less_than_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    config: &ComparisonConfig,
+    x: &[u64],
+    ot_config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    check_values(config, x)?;
+    let sender = OtExtSender::new(ot_config, group.clone());
+    let mut res = Vec::with_capacity(x.len());
+    for x in x.chunks(config.batch) {
+        let n = x.len();
+        let (own, tables) = leaf_tables(config, x, rng);
+        let tables = tables.iter().map(|table| &table[..]).collect::<Vec<_>>();
+        let (keys, masked) = mask_tables(&tables, &vec![2; tables.len()], ot_config.k(), rng);
+        sender.send(chan, keys.to_vec(), rng).await?;
+        chan.label("comparison tables").send_bit_rows(&masked.concat()).await?;
+        let mut levels = leaves(&own, n);
+        while levels.len() > 1 {
+            let (u, v) = operands(&levels);
+            let (messages, masks) = and_messages(&u, &v, rng);
+            sender.send(chan, messages, rng).await?;
+            levels = merge(levels, &and_sender(&u, &v, &masks), n);
+        }
+        res.extend(levels.pop().map(|(lt, _)| lt).unwrap_or_default());
+    }
+    Ok(res)
+}
+
+// Receiver of `less_than_sender`, holding y: returns its shares of [x[i] < y[i]].
+pub async fn less_than_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    config: &ComparisonConfig,
+    y: &[u64],
+    ot_config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    check_values(config, y)?;
+    let receiver = OtExtReceiver::new(ot_config, group.clone());
+    let sizes = config.digits().iter().map(|&(_, width)| 1usize << width).collect::<Vec<_>>();
+    let mut res = Vec::with_capacity(y.len());
+    for y in y.chunks(config.batch) {
+        let n = y.len();
+        let choices = leaf_choices(config, y);
+        let sizes = sizes.iter().flat_map(|&size| std::iter::repeat_n(size, n)).collect::<Vec<_>>();
+        let keys = Zeroizing::new(receiver.receive(chan, choice_of(&sizes, &choices).to_vec(), rng).await?);
+        let masked = chan.label("comparison tables").recv_bit_rows(sizes.iter().sum(), Some(2)).await?;
+        let mut rest = &masked[..];
+        let tables = sizes
+            .iter()
+            .map(|&size| {
+                let (table, tail) = rest.split_at(size);
+                rest = tail;
+                table
+            })
+            .collect::<Vec<_>>();
+        let received = Zeroizing::new(unmask(&tables, &choices, &keys));
+        let mut levels = leaves(&received_leaves(&received), n);
+        while levels.len() > 1 {
+            let (u, v) = operands(&levels);
+            let cross = Zeroizing::new(receiver.receive(chan, and_choice(&u, &v).to_vec(), rng).await?);
+            levels = merge(levels, &and_receiver(&u, &v, &cross), n);
+        }
+        res.extend(levels.pop().map(|(lt, _)| lt).unwrap_or_default());
+    }
+    Ok(res)
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use crate::pool_file::Role;
+
+#[derive(Debug)]
+pub enum OteError {
+    Io(std::io::Error),
+    // The peer sent something that does not fit the protocol, e.g. a vector of the wrong length.
+    MalformedMessage(String),
+    BadParameter(String),
+    // x_0 and x_1 of the pair at `index` do not both have the length of the first message.
+    MessageLengthMismatch { index: usize, expected: usize, len_0: usize, len_1: usize },
+    // More precomputed OTs were requested than are left in the pool.
+    PoolExhausted { requested: usize, available: usize },
+    // A pool, prime, transcript or circuit file that is truncated, has an unknown format or does not parse.
+    InvalidFile(String),
+    // A value that is computed twice in different ways did not come out the same.
+    ConsistencyCheckFailed(String),
+    // The MAC of a pool file did not verify, so it was tampered with or the key is wrong.
+    IntegrityCheckFailed,
+    WrongRole { expected: Role, found: Role },
+}
+
+impl fmt::Display for OteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OteError::Io(e) => write!(f, "I/O error: {}", e),
+            OteError::MalformedMessage(msg) => write!(f, "Malformed message from peer: {}", msg),
+            OteError::BadParameter(msg) => write!(f, "Bad parameter: {}", msg),
+            OteError::MessageLengthMismatch { index, expected, len_0, len_1 } => write!(
+                f,
+                "Messages of pair {} have lengths {} and {} but {} was expected",
+                index, len_0, len_1, expected
+            ),
+            OteError::PoolExhausted { requested, available } => write!(
+                f,
+                "Requested {} precomputed OTs but only {} are left",
+                requested, available
+            ),
+            OteError::InvalidFile(msg) => write!(f, "Invalid file: {}", msg),
+            OteError::ConsistencyCheckFailed(msg) => write!(f, "Consistency check failed: {}", msg),
+            OteError::IntegrityCheckFailed => write!(f, "Integrity check of pool file failed"),
+            OteError::WrongRole { expected, found } => write!(
+                f,
+                "Expected a pool file of the {:?} but it belongs to the {:?}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OteError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for OteError {
+    fn from(e: std::io::Error) -> Self {
+        OteError::Io(e)
+    }
+}
@@ -0,0 +1,308 @@
+// Two-party GMW on boolean circuits (Goldreich, Micali, Wigderson 1987).
+//
+// Every wire value x is XOR-shared as x = x_0 xor x_1 between the parties. XOR and INV gates are
+// computed locally, like copies and constants. An AND gate z = x and y uses a Beaver triple c = a and b: the parties open
+// d = x xor a and e = y xor b, and party i sets z_i = c_i xor (d and b_i) xor (e and a_i), with
+// party 0 also adding d and e. All AND gates of one layer are opened together in one message.
+//
+// The triples come from two batches of random OTs with 1-bit messages, one in each direction.
+// In a batch where party i sends (u, v) and party j chooses a_j and learns w_j, we have
+// a_j and (u xor v) = w_j xor u. With b_i = u xor v, party i's share of the cross term
+// a_j and b_i is u and party j's is w_j, and the local term a_i and b_i completes c_i.
+
+use rand::{CryptoRng, Rng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtConfig, OtExtReceiver, OtExtSender};
+use crate::channel::Channel;
+use crate::circuit::{Circuit, Gate};
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::SafePrimeGroup;
+use crate::precompute::Precomputed;
+use crate::session::Protocol;
+
+// One party's shares of a batch of multiplication triples, with c_0 xor c_1 = (a_0 xor a_1) and (b_0 xor b_1).
+pub struct Triples {
+    pub a: SecretBits,
+    pub b: SecretBits,
+    pub c: SecretBits,
+}
+
+impl Triples {
+    /**
+     * One party's triples from its two ROT batches: the random messages (u_i, v_i) it sent and
+     * its random choice bits a_i with the messages w_i it received.
+     */
+    pub fn from_rots(sent: &[(bool, bool)], choice: &[bool], received: &[bool]) -> Result<Triples, OteError> {
+        if sent.len() != choice.len() || choice.len() != received.len() {
+            return Err(OteError::BadParameter(format!(
+                "{} sent and {} received random OTs for {} choice bits",
+                sent.len(),
+                received.len(),
+                choice.len()
+            )));
+        }
+        let b = Zeroizing::new(sent.iter().map(|&(u, v)| u ^ v).collect::<Vec<_>>());
+        let c = Zeroizing::new(
+            sent.iter()
+                .zip(choice)
+                .zip(received)
+                .zip(b.iter())
+                .map(|((((u, _), &a), &w), &b)| (a & b) ^ w ^ u)
+                .collect(),
+        );
+        Ok(Triples {
+            a: Zeroizing::new(choice.to_vec()),
+            b,
+            c,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.a.is_empty()
+    }
+}
+
+/**
+ * Both parties' shares of n triples, made in one process from two pools of random OTs:
+ * party 0 is the sender of the first and party 1 of the second.
+ */
+pub fn triples<R: CryptoRng + RngCore>(n: usize, protocol: Protocol, k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<(Triples, Triples), OteError> {
+    if n == 0 {
+        return Ok((Triples::from_rots(&[], &[], &[])?, Triples::from_rots(&[], &[], &[])?));
+    }
+    let mut from_0 = Precomputed::new(protocol, n, k, group, rng)?;
+    let mut from_1 = Precomputed::new(protocol, n, k, group, rng)?;
+    let sent_0 = first_bits(&from_0.sender.take_random(n, 1)?);
+    let sent_1 = first_bits(&from_1.sender.take_random(n, 1)?);
+    let (choice_1, received_1) = from_0.receiver.take_random(n, 1)?;
+    let (choice_0, received_0) = from_1.receiver.take_random(n, 1)?;
+    let received = |x: &[SecretBits]| Zeroizing::new(x.iter().map(|w| w[0]).collect::<Vec<_>>());
+    Ok((
+        Triples::from_rots(&sent_0, &choice_0, &received(&received_0))?,
+        Triples::from_rots(&sent_1, &choice_1, &received(&received_1))?,
+    ))
+}
+
+fn first_bits(messages: &[(SecretBits, SecretBits)]) -> Zeroizing<Vec<(bool, bool)>> {
+    Zeroizing::new(messages.iter().map(|(r_0, r_1)| (r_0[0], r_1[0])).collect())
+}
+
+// One party's view of an evaluation: its shares of all wires set so far and its triples.
+struct Party<'a> {
+    circuit: &'a Circuit,
+    index: usize,
+    wires: SecretBits,
+    triples: Triples,
+    // Triples before this one have been used.
+    used: usize,
+}
+
+impl<'a> Party<'a> {
+    fn new(circuit: &'a Circuit, index: usize, triples: Triples) -> Result<Party<'a>, OteError> {
+        if triples.len() < circuit.and_gates() {
+            return Err(OteError::BadParameter(format!(
+                "{} triples for {} AND gates",
+                triples.len(),
+                circuit.and_gates()
+            )));
+        }
+        Ok(Party {
+            circuit,
+            index,
+            wires: Zeroizing::new(vec![false; circuit.wires]),
+            triples,
+            used: 0,
+        })
+    }
+
+    // Sets the shares of one party's input wires.
+    fn set_inputs(&mut self, party: usize, shares: &[bool]) {
+        let range = self.circuit.input_wires(party);
+        self.wires[range].copy_from_slice(shares);
+    }
+
+    fn eval_local(&mut self, gates: &[usize]) {
+        for &i in gates {
+            match self.circuit.gates[i] {
+                Gate::Xor(a, b, out) => self.wires[out] = self.wires[a] ^ self.wires[b],
+                // Only one share is flipped, so the value is flipped once.
+                Gate::Inv(a, out) => self.wires[out] = self.wires[a] ^ (self.index == 0),
+                Gate::Eqw(a, out) => self.wires[out] = self.wires[a],
+                // Party 0 holds the constant and party 1 a zero share.
+                Gate::Eq(c, out) => self.wires[out] = c & (self.index == 0),
+                Gate::And(..) => unreachable!("AND gates are evaluated with triples"),
+            }
+        }
+    }
+
+    // This party's shares of d and e for every AND gate of a layer, interleaved.
+    fn openings(&self, gates: &[usize]) -> SecretBits {
+        let mut res = Zeroizing::new(Vec::with_capacity(2 * gates.len()));
+        for (t, &i) in (self.used..).zip(gates) {
+            if let Gate::And(x, y, _) = self.circuit.gates[i] {
+                res.push(self.wires[x] ^ self.triples.a[t]);
+                res.push(self.wires[y] ^ self.triples.b[t]);
+            }
+        }
+        res
+    }
+
+    // Sets the outputs of the AND gates of a layer from both parties' openings and uses up their triples.
+    fn finish_and(&mut self, gates: &[usize], own: &[bool], other: &[bool]) {
+        for (j, &i) in gates.iter().enumerate() {
+            let t = self.used + j;
+            let d = own[2 * j] ^ other[2 * j];
+            let e = own[2 * j + 1] ^ other[2 * j + 1];
+            let z = self.triples.c[t] ^ (d & self.triples.b[t]) ^ (e & self.triples.a[t]) ^ (d & e & (self.index == 0));
+            self.wires[self.circuit.gates[i].output()] = z;
+        }
+        self.used += gates.len();
+    }
+
+    fn output_shares(&self) -> Vec<bool> {
+        self.wires[self.circuit.output_wires()].to_vec()
+    }
+}
+
+fn check_inputs(circuit: &Circuit, party: usize, inputs: &[bool]) -> Result<(), OteError> {
+    circuit.check()?;
+    if circuit.inputs.len() != 2 {
+        return Err(OteError::BadParameter(format!("GMW needs a circuit of 2 parties, not {}", circuit.inputs.len())));
+    }
+    if inputs.len() != circuit.inputs[party] {
+        return Err(OteError::BadParameter(format!(
+            "party {} has {} input bits but the circuit takes {}",
+            party,
+            inputs.len(),
+            circuit.inputs[party]
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Evaluates a two-party circuit with GMW in one process and returns the values of the output
+ * wires. inputs[i] are the input bits of party i.
+ */
+pub fn gmw<R: CryptoRng + RngCore>(
+    circuit: &Circuit,
+    inputs: [&[bool]; 2],
+    protocol: Protocol,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    for (i, input) in inputs.iter().enumerate() {
+        check_inputs(circuit, i, input)?;
+    }
+    let (triples_0, triples_1) = triples(circuit.and_gates(), protocol, k, group, rng)?;
+    let mut parties = [Party::new(circuit, 0, triples_0)?, Party::new(circuit, 1, triples_1)?];
+    for (i, input) in inputs.iter().enumerate() {
+        let mask = Zeroizing::new(random_boolvec_len(input.len(), rng));
+        parties[i].set_inputs(i, &xor_boolvec(input, &mask));
+        parties[1 - i].set_inputs(i, &mask);
+    }
+    for (and_gates, local_gates) in circuit.layers() {
+        let (d_0, d_1) = (parties[0].openings(&and_gates), parties[1].openings(&and_gates));
+        parties[0].finish_and(&and_gates, &d_0, &d_1);
+        parties[1].finish_and(&and_gates, &d_1, &d_0);
+        parties.iter_mut().for_each(|party| party.eval_local(&local_gates));
+    }
+    Ok(xor_boolvec(&parties[0].output_shares(), &parties[1].output_shares()))
+}
+
+// Party 0 sends first and party 1 receives first, so the two never both wait on a full stream.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    label: &str,
+    party: usize,
+    bits: &[bool],
+    len: usize,
+) -> Result<Vec<bool>, OteError> {
+    let rows = [bits.to_vec()];
+    if party == 0 {
+        chan.label(label).send_bit_rows(&rows).await?;
+    }
+    let received = chan.label(label).recv_bit_rows(1, Some(len)).await?.remove(0);
+    if party == 1 {
+        chan.label(label).send_bit_rows(&rows).await?;
+    }
+    Ok(received)
+}
+
+// This party's triples from two OT extensions with random 1-bit messages and random choice bits.
+async fn triples_over<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    party: usize,
+    n: usize,
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Triples, OteError> {
+    if n == 0 {
+        return Triples::from_rots(&[], &[], &[]);
+    }
+    let sender = OtExtSender::new(config, group.clone());
+    let receiver = OtExtReceiver::new(config, group.clone());
+    let sent = Zeroizing::new((0..n).map(|_| (rng.gen(), rng.gen())).collect::<Vec<_>>());
+    let messages = sent.iter().map(|&(u, v)| (vec![u], vec![v])).collect::<Vec<_>>();
+    let choice = Zeroizing::new(random_boolvec_len(n, rng));
+    // Party 0 is the sender of the first batch and party 1 of the second.
+    let received = if party == 0 {
+        sender.send(chan, messages, rng).await?;
+        receiver.receive(chan, choice.to_vec(), rng).await?
+    } else {
+        let received = receiver.receive(chan, choice.to_vec(), rng).await?;
+        sender.send(chan, messages, rng).await?;
+        received
+    };
+    let received = Zeroizing::new(received.iter().map(|w| w[0]).collect::<Vec<_>>());
+    Triples::from_rots(&sent, &choice, &received)
+}
+
+/**
+ * One party of a GMW evaluation over a channel: `party` is 0 or 1 and `inputs` are its input
+ * bits. Both parties learn the values of all output wires.
+ * Messages: the ROTs for the triples, "gmw input masks" from each party, one "gmw d,e" per
+ * layer with AND gates and "gmw outputs".
+ */
+pub async fn gmw_party<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    circuit: &Circuit,
+    party: usize,
+    inputs: &[bool],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    if party > 1 {
+        return Err(OteError::BadParameter(format!("party {} of 2", party)));
+    }
+    check_inputs(circuit, party, inputs)?;
+    let triples = triples_over(chan, party, circuit.and_gates(), config, group, rng).await?;
+    let mut state = Party::new(circuit, party, triples)?;
+    let other = 1 - party;
+
+    let mask = Zeroizing::new(random_boolvec_len(inputs.len(), rng));
+    state.set_inputs(party, &xor_boolvec(inputs, &mask));
+    let other_mask = Zeroizing::new(exchange(chan, "gmw input masks", party, &mask, circuit.inputs[other]).await?);
+    state.set_inputs(other, &other_mask);
+
+    for (and_gates, local_gates) in circuit.layers() {
+        if !and_gates.is_empty() {
+            let own = state.openings(&and_gates);
+            let received = exchange(chan, "gmw d,e", party, &own, 2 * and_gates.len()).await?;
+            state.finish_and(&and_gates, &own, &received);
+        }
+        state.eval_local(&local_gates);
+    }
+    let own = state.output_shares();
+    let received = exchange(chan, "gmw outputs", party, &own, own.len()).await?;
+    Ok(xor_boolvec(&own, &received))
+}
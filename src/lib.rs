@@ -0,0 +1,65 @@
+/*!
+ * Oblivious transfer: a 2048-bit ElGamal base OT and the IKNP and ALSZ extensions on top of it,
+ * private set intersection from the KKRT OPRF in `psi`, and two-party evaluation of boolean
+ * circuits with GMW in `gmw`, on multiplication triples from random OTs. The circuits are read
+ * from Bristol Fashion files by `circuit`, and can also be evaluated with Yao's garbled circuits
+ * in `yao`, where the evaluator gets its input labels from the OT extension. `arith` multiplies
+ * into additive shares over Z_2^k or a prime field with Gilboa's protocol on correlated OTs, and
+ * `comparison` compares secret integers with 1-out-of-N OTs from `ot_n`. `vole` turns the
+ * correlated OTs into subfield VOLE over GF(2^128) or a prime field, and `oprf` is a standalone
+ * OPRF (2HashDH) with a long-lived key, also served over TCP. `pir` fetches rows of a table
+ * with 1-out-of-N OTs, locally or over a channel.
+ *
+ * The items re-exported here are the stable API. The protocols run over a `Channel`, which
+ * wraps any tokio stream, with `BaseOt` for the base OT and `OtExtSender`/`OtExtReceiver`
+ * for the extensions, configured by an `OtExtConfig` with its `SecurityParams`.
+ * Messages and choices are bit vectors; `bytes` and the `_bytes` methods take byte strings and a
+ * packed choice bitmap instead, with the bit order spelled out there.
+ * All randomness is drawn from a caller-supplied `CryptoRng + RngCore`, so a run with a seeded
+ * `ChaCha20Rng` can be repeated exactly, down to the bytes on the channel.
+ * Secret keys, seeds, s and the rows t_j and q_j are kept in `Zeroizing` buffers, which are
+ * overwritten with zeros when dropped.
+ * The modules themselves are public as well, for the in-process variants (`ote`, sessions,
+ * precomputed pools) and the benchmarks, but their contents may still change.
+ */
+
+pub mod api;
+pub mod arith;
+pub mod bytes;
+pub mod channel;
+pub mod circuit;
+pub mod common;
+pub mod comparison;
+pub mod error;
+pub mod gmw;
+pub mod ot_async;
+pub mod ot_better_network;
+pub mod ot_n;
+pub mod oprf;
+pub mod ot_primitive;
+pub mod pool_file;
+pub mod precompute;
+pub mod psi;
+#[allow(non_snake_case)]
+pub mod ote_IKNP;
+pub mod params;
+pub mod pir;
+pub mod session;
+pub mod timing;
+pub mod trace;
+pub mod transcript;
+pub mod vole;
+pub mod yao;
+
+pub use api::{BaseOt, OtExtConfig, OtExtReceiver, OtExtSender};
+pub use channel::Channel;
+pub use common::MessagePairs;
+pub use error::OteError;
+pub use gmw::gmw_party;
+pub use ot_primitive::{make_group, SafePrimeGroup};
+pub use params::SecurityParams;
+pub use pir::{pir_client, pir_server};
+pub use precompute::Precomputed;
+pub use psi::{psi_receiver, psi_sender};
+pub use session::{OtExtSession, Protocol};
+pub use yao::{yao_evaluator, yao_garbler};
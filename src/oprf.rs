@@ -0,0 +1,247 @@
+// Oblivious PRF 2HashDH of Jarecki, Kiayias and Krawczyk (2014) in the group of `ot_primitive`.
+//
+// F_k(x) = H_2(x, H_1(x)^k), where H_1 hashes onto the group. The client sends a = H_1(x)^r for
+// a random exponent r, the server answers a^k, and the client unblinds (a^k)^(1/r) = H_1(x)^k.
+// a is a uniformly random element whatever x is, so the server learns nothing about x, and the
+// client gets H_1(x)^k only for the elements it sent. Unlike the KKRT OPRF in `psi` the key k
+// lives as long as the server, so one server answers any number of requests, as for password lookups.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::{CryptoRng, RngCore};
+use rayon::prelude::*;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, timeout};
+
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_async::blocking;
+use crate::ot_primitive::{self, elem_from_bytes, elem_to_bytes, PublicKey, SafePrimeGroup, SecretKeys, ELEM_BYTES};
+use crate::psi::shake;
+
+// Bytes of an OPRF value.
+pub const OUTPUT_BYTES: usize = 32;
+
+fn h_1(group: &SafePrimeGroup, input: &[u8]) -> PublicKey {
+    let mut hash = [0; 2 * ELEM_BYTES];
+    shake(&[b"2HashDH H_1", input], &mut hash);
+    ot_primitive::hash_to_group(group, &hash)
+}
+
+fn h_2(input: &[u8], y: &PublicKey) -> Vec<u8> {
+    let mut out = vec![0; OUTPUT_BYTES];
+    let y = Zeroizing::new(elem_to_bytes(y));
+    shake(&[b"2HashDH H_2", input, &y[..]], &mut out);
+    out
+}
+
+// The group elements of a request or response, each of which must be in the group.
+fn parse(group: &SafePrimeGroup, bytes: &[u8], what: &str) -> Result<Vec<PublicKey>, OteError> {
+    if !bytes.len().is_multiple_of(ELEM_BYTES) {
+        return Err(OteError::MalformedMessage(format!("{} of {} bytes is not a list of group elements", what, bytes.len())));
+    }
+    bytes
+        .par_chunks(ELEM_BYTES)
+        .map(|x| match elem_from_bytes(group, x) {
+            Some(e) if ot_primitive::in_group(group, &e) => Ok(e),
+            _ => Err(OteError::MalformedMessage(format!("{} holds a value outside the group", what))),
+        })
+        .collect()
+}
+
+pub struct OprfServer {
+    group: SafePrimeGroup,
+    key: SecretKeys,
+}
+
+impl OprfServer {
+    // A server with a fresh random key k.
+    pub fn new<R: CryptoRng + RngCore>(group: &SafePrimeGroup, rng: &mut R) -> OprfServer {
+        OprfServer {
+            group: group.clone(),
+            key: ot_primitive::create_secret_keys(group, 1, rng),
+        }
+    }
+
+    // F_k(x), computed directly with the key.
+    pub fn evaluate(&self, input: &[u8]) -> Vec<u8> {
+        h_2(input, &ot_primitive::pow(&self.group, &h_1(&self.group, input), &self.key[0]))
+    }
+
+    /**
+     * The response to a request of blinded elements a_i: the elements a_i^k, in the same order.
+     * Fails if any a_i is not in the group, so the key is never applied to anything else.
+     */
+    pub fn respond(&self, request: &[u8]) -> Result<Vec<u8>, OteError> {
+        let blinded = parse(&self.group, request, "OPRF request")?;
+        Ok(blinded.par_iter().flat_map_iter(|a| elem_to_bytes(&ot_primitive::pow(&self.group, a, &self.key[0]))).collect())
+    }
+}
+
+pub struct OprfClient {
+    group: SafePrimeGroup,
+    inputs: Zeroizing<Vec<Vec<u8>>>,
+    blinds: SecretKeys,
+}
+
+impl OprfClient {
+    /**
+     * Blinds the inputs with one random exponent each.
+     * Returns the client's state for `finish` and the request for the server.
+     */
+    pub fn request<R: CryptoRng + RngCore>(group: &SafePrimeGroup, inputs: &[Vec<u8>], rng: &mut R) -> (OprfClient, Vec<u8>) {
+        let blinds = ot_primitive::create_secret_keys(group, inputs.len(), rng);
+        let request = inputs
+            .par_iter()
+            .zip(blinds.par_iter())
+            .flat_map_iter(|(x, r)| elem_to_bytes(&ot_primitive::pow(group, &h_1(group, x), r)))
+            .collect();
+        let client = OprfClient {
+            group: group.clone(),
+            inputs: Zeroizing::new(inputs.to_vec()),
+            blinds,
+        };
+        (client, request)
+    }
+
+    // F_k(x) for every input, in the order of the request, from the server's response.
+    pub fn finish(self, response: &[u8]) -> Result<Vec<Vec<u8>>, OteError> {
+        if response.len() != self.inputs.len() * ELEM_BYTES {
+            return Err(OteError::MalformedMessage(format!(
+                "expected {} group elements but got {} bytes",
+                self.inputs.len(),
+                response.len()
+            )));
+        }
+        let answers = parse(&self.group, response, "OPRF response")?;
+        self.inputs
+            .par_iter()
+            .zip(self.blinds.par_iter())
+            .zip(answers.par_iter())
+            .map(|((x, r), b)| {
+                let unblind = Zeroizing::new(
+                    ot_primitive::invert_exponent(&self.group, r).ok_or_else(|| OteError::BadParameter("blinding exponent is zero".to_owned()))?,
+                );
+                Ok(h_2(x, &ot_primitive::pow(&self.group, b, &unblind)))
+            })
+            .collect()
+    }
+}
+
+/**
+ * Server side of one request on the channel. Returns the number of inputs it answered.
+ */
+pub async fn oprf_server<S: AsyncRead + AsyncWrite + Unpin>(chan: &mut Channel<S>, server: &Arc<OprfServer>) -> Result<usize, OteError> {
+    let request = chan.label("oprf request").recv_bytes().await?;
+    let n = request.len() / ELEM_BYTES;
+    let server = server.clone();
+    let response = blocking(move || server.respond(&request)).await?;
+    chan.label("oprf response").send_bytes(&response).await?;
+    Ok(n)
+}
+
+/**
+ * Client side of one request on the channel: returns F_k(x) for every input, in order.
+ */
+pub async fn oprf_client<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    group: &SafePrimeGroup,
+    inputs: &[Vec<u8>],
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let (g, inputs) = (group.clone(), inputs.to_vec());
+    let mut rng = fork_rng(rng);
+    let (client, request) = blocking(move || Ok(OprfClient::request(&g, &inputs, &mut rng))).await?;
+    chan.label("oprf request").send_bytes(&request).await?;
+    let response = chan.label("oprf response").recv_bytes().await?;
+    blocking(move || client.finish(&response)).await
+}
+
+// Limits of `oprf_serve` on what one client can make the server hold on to.
+#[derive(Clone, Copy, Debug)]
+pub struct ServeLimits {
+    // Connections served at once. Further connections wait in the listener's backlog.
+    pub max_connections: usize,
+    // Time for a connection from accepting it until its response is sent.
+    pub timeout: Duration,
+    // Group elements in one request, checked against the frame header before reading.
+    pub max_elements: usize,
+}
+
+impl Default for ServeLimits {
+    fn default() -> Self {
+        ServeLimits {
+            max_connections: 64,
+            timeout: Duration::from_secs(30),
+            max_elements: 1 << 16,
+        }
+    }
+}
+
+// Pause after a failed accept before trying again.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/**
+ * Answers requests from every connection to `listener`, one request per connection, each in its
+ * own task and within `limits`. A failed request only ends its connection, and its error is
+ * passed to `report` with the peer's address. Failed accepts are reported without an address
+ * and the server goes on, unless the listener itself is unusable, which ends it.
+ */
+pub async fn oprf_serve<F: Fn(Option<SocketAddr>, OteError) + Send + Sync + 'static>(
+    listener: TcpListener,
+    server: Arc<OprfServer>,
+    limits: ServeLimits,
+    report: F,
+) -> Result<(), OteError> {
+    if limits.max_connections == 0 {
+        return Err(OteError::BadParameter("the server must serve at least one connection at a time".to_owned()));
+    }
+    let max_request = u64::try_from(limits.max_elements.saturating_mul(ELEM_BYTES)).unwrap_or(u64::MAX);
+    let slots = Arc::new(Semaphore::new(limits.max_connections));
+    let report = Arc::new(report);
+    loop {
+        // Nothing is accepted until a slot is free.
+        let slot = slots.clone().acquire_owned().await.expect("the semaphore is never closed");
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => return Err(e.into()),
+            // E.g. EMFILE or ECONNABORTED, which go away again. Waiting a little keeps the loop
+            // from spinning while no file descriptors are left.
+            Err(e) => {
+                report(None, e.into());
+                sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let (server, report) = (server.clone(), report.clone());
+        tokio::spawn(async move {
+            let mut chan = Channel::new(stream);
+            chan.set_max_frame_bytes(max_request);
+            let result = match timeout(limits.timeout, oprf_server(&mut chan, &server)).await {
+                Ok(result) => result,
+                Err(_) => Err(OteError::Io(io::Error::new(io::ErrorKind::TimedOut, "OPRF request timed out"))),
+            };
+            if let Err(e) = result {
+                report(Some(peer), e);
+            }
+            drop(slot);
+        });
+    }
+}
+
+// Connects to an OPRF server at `addr` and evaluates F_k on the inputs.
+pub async fn oprf_query<A: ToSocketAddrs, R: CryptoRng + RngCore>(
+    addr: A,
+    group: &SafePrimeGroup,
+    inputs: &[Vec<u8>],
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let mut chan = Channel::new(TcpStream::connect(addr).await?);
+    oprf_client(&mut chan, group, inputs, rng).await
+}
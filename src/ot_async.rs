@@ -0,0 +1,332 @@
+// Async drivers for the base OT and the two extensions.
+// Each function runs one party of the protocol over a `Channel`, while the
+// exponentiations, PRG expansions and transposes are moved to tokio's blocking pool.
+// The randomness of those blocking steps comes from an RNG forked off the caller's `rng`.
+
+use std::sync::Arc;
+
+use rand::{CryptoRng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::{self, PublicKey, SafePrimeGroup, USIZE};
+use crate::{ot_better_network, ote_IKNP};
+
+pub(crate) async fn blocking<F, T>(f: F) -> Result<T, OteError>
+where
+    F: FnOnce() -> Result<T, OteError> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(x) => x,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(OteError::Io(std::io::Error::other(e))),
+    }
+}
+
+pub(crate) async fn send_keys<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    keys: &[(PublicKey, PublicKey)],
+) -> Result<(), OteError> {
+    let elems = keys.iter().flat_map(|&(k_0, k_1)| [k_0, k_1]).collect::<Vec<_>>();
+    chan.label("base-ot keys").send_group_elems(&elems).await
+}
+
+pub(crate) async fn recv_keys<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    group: &SafePrimeGroup,
+    num: usize,
+) -> Result<Vec<(PublicKey, PublicKey)>, OteError> {
+    let elems = chan.label("base-ot keys").recv_group_elems(group, 2 * num).await?;
+    Ok(elems.chunks_exact(2).map(|x| (x[0], x[1])).collect())
+}
+
+pub(crate) async fn send_ot_params<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    params: &ot_primitive::OTParams,
+) -> Result<(), OteError> {
+    let elems = params
+        .iter()
+        .flat_map(|&((c_0, d_0), (c_1, d_1))| [c_0, d_0, c_1, d_1])
+        .collect::<Vec<_>>();
+    chan.label("base-ot ciphertexts").send_group_elems(&elems).await
+}
+
+pub(crate) async fn recv_ot_params<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    group: &SafePrimeGroup,
+    num: usize,
+) -> Result<ot_primitive::OTParams, OteError> {
+    let elems = chan.label("base-ot ciphertexts").recv_group_elems(group, 4 * num).await?;
+    Ok(elems.chunks_exact(4).map(|x| ((x[0], x[1]), (x[2], x[3]))).collect())
+}
+
+// Sender side of the base OT: one pair of messages per OT.
+pub async fn base_ot_send<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    group: &SafePrimeGroup,
+    messages: Vec<(USIZE, USIZE)>,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    let keys = recv_keys(chan, group, messages.len()).await?;
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let encrypted = blocking(move || ot_primitive::send(&g, &keys, &messages, &mut rng)).await?;
+    send_ot_params(chan, &encrypted).await
+}
+
+// Receiver side of the base OT: returns the chosen message of every OT.
+pub async fn base_ot_receive<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    group: &SafePrimeGroup,
+    choices: Vec<bool>,
+    rng: &mut R,
+) -> Result<Vec<USIZE>, OteError> {
+    let n = choices.len();
+    let choices = Arc::new(choices);
+    let (g, c) = (group.clone(), choices.clone());
+    let mut rng = fork_rng(rng);
+    let (sk, keys) = blocking(move || {
+        let sk = ot_primitive::create_secret_keys(&g, n, &mut rng);
+        let keys = ot_primitive::commit_choice(&g, &sk, &c, &mut rng)?;
+        Ok((sk, keys))
+    })
+    .await?;
+    send_keys(chan, &keys).await?;
+    let encrypted = recv_ot_params(chan, group, n).await?;
+    let g = group.clone();
+    blocking(move || ot_primitive::receive_(&g, &encrypted, &sk, &choices)).await
+}
+
+// The extension sender after its base OTs, in which it plays the receiver with choice bits s.
+async fn iknp_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<ote_IKNP::Sender, OteError> {
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (mut sender, sk, keys) = blocking(move || {
+        let sender = ote_IKNP::Sender::initialize(k, &mut rng)?;
+        let (sk, keys) = sender.commit_ot_primitive(&g, &mut rng)?;
+        Ok((sender, sk, keys))
+    })
+    .await?;
+    send_keys(chan, &keys).await?;
+    let seeds_ot = recv_ot_params(chan, group, k).await?;
+    let g = group.clone();
+    blocking(move || {
+        sender.receive_ot_primitive(&g, &sk, &seeds_ot)?;
+        Ok(sender)
+    })
+    .await
+}
+
+// The extension receiver after the base OTs, in which it sends its seeds.
+async fn iknp_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<ote_IKNP::Receiver, OteError> {
+    let keys = recv_keys(chan, group, k).await?;
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (receiver, seeds_ot) = blocking(move || {
+        let receiver = ote_IKNP::Receiver::initialize(k, &mut rng)?;
+        let seeds_ot = receiver.send_ot_primitive(&g, &keys, &mut rng)?;
+        Ok((receiver, seeds_ot))
+    })
+    .await?;
+    send_ot_params(chan, &seeds_ot).await?;
+    Ok(receiver)
+}
+
+pub async fn iknp_send<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    messages: MessagePairs,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    check_message_lengths(&messages)?;
+    let m = messages.len();
+    let mut sender = iknp_sender(chan, k, group, rng).await?;
+    let otp = chan.label("u_i").recv_bit_pairs(k, Some(m)).await?;
+    let y = blocking(move || Ok(sender.receive_columns(otp, &messages))).await?;
+    chan.label("y_j").send_bit_pairs(&y).await
+}
+
+pub async fn iknp_receive<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    choice: Vec<bool>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<bool>>, OteError> {
+    let m = choice.len();
+    let mut receiver = iknp_receiver(chan, k, group, rng).await?;
+    let (mut receiver, t, otp, choice) = blocking(move || {
+        let (t, otp) = receiver.mask_columns(&choice);
+        Ok((receiver, t, otp, choice))
+    })
+    .await?;
+    chan.label("u_i").send_bit_pairs(&otp).await?;
+    let y = chan.label("y_j").recv_bit_pairs(m, None).await?;
+    blocking(move || Ok(receiver.decode(&y, t, &choice))).await
+}
+
+/**
+ * Sender side of m OTs without messages, for protocols built on the correlated rows: returns
+ * the hash index of the first OT, the rows q_j and s, with t_j = q_j xor (c_j * s) on the other side.
+ */
+pub(crate) async fn iknp_send_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    m: usize,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(usize, SecretRows, SecretBits), OteError> {
+    let mut sender = iknp_sender(chan, k, group, rng).await?;
+    let otp = chan.label("u_i").recv_bit_pairs(k, Some(m)).await?;
+    blocking(move || {
+        let (offset, q) = sender.next_rows(otp, m);
+        Ok((offset, q, Zeroizing::new(sender.s().to_vec())))
+    })
+    .await
+}
+
+// Receiver side of `iknp_send_rows`: returns the hash index of the first OT and the rows t_j.
+pub(crate) async fn iknp_receive_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    choice: Vec<bool>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(usize, SecretRows), OteError> {
+    let m = choice.len();
+    let mut receiver = iknp_receiver(chan, k, group, rng).await?;
+    let (rows, otp) = blocking(move || {
+        let (t, otp) = receiver.mask_columns(&choice);
+        Ok((receiver.next_rows(t, m), otp))
+    })
+    .await?;
+    chan.label("u_i").send_bit_pairs(&otp).await?;
+    Ok(rows)
+}
+
+async fn alsz_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<ot_better_network::Sender, OteError> {
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (mut sender, sk, keys) = blocking(move || {
+        let sender = ot_better_network::Sender::initialize(k, &mut rng)?;
+        let (sk, keys) = sender.commit_ot_primitive(&g, &mut rng)?;
+        Ok((sender, sk, keys))
+    })
+    .await?;
+    send_keys(chan, &keys).await?;
+    let res = recv_ot_params(chan, group, k).await?;
+    let g = group.clone();
+    blocking(move || {
+        sender.receive_ot_primitive(&g, &sk, &res)?;
+        Ok(sender)
+    })
+    .await
+}
+
+async fn alsz_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<ot_better_network::Receiver, OteError> {
+    let keys = recv_keys(chan, group, k).await?;
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (receiver, res) = blocking(move || {
+        let receiver = ot_better_network::Receiver::initialize(k, &mut rng)?;
+        let res = receiver.send_ot_primitive(&g, &keys, &mut rng)?;
+        Ok((receiver, res))
+    })
+    .await?;
+    send_ot_params(chan, &res).await?;
+    Ok(receiver)
+}
+
+pub async fn alsz_send<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    messages: MessagePairs,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    check_message_lengths(&messages)?;
+    let m = messages.len();
+    let mut sender = alsz_sender(chan, k, group, rng).await?;
+    let u = chan.label("u_i").recv_bit_rows(k, Some(m)).await?;
+    let y = blocking(move || Ok(sender.receive_vectors(u, &messages))).await?;
+    chan.label("y_j").send_bit_pairs(&y).await
+}
+
+pub async fn alsz_receive<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    choice: Vec<bool>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<bool>>, OteError> {
+    let m = choice.len();
+    let mut receiver = alsz_receiver(chan, k, group, rng).await?;
+    let (mut receiver, t, u, choice) = blocking(move || {
+        let (t, u) = receiver.compute_t_and_u(&choice);
+        Ok((receiver, t, u, choice))
+    })
+    .await?;
+    chan.label("u_i").send_bit_rows(&u).await?;
+    let y = chan.label("y_j").recv_bit_pairs(m, None).await?;
+    blocking(move || Ok(receiver.decode(&y, t, &choice))).await
+}
+
+// `iknp_send_rows` for ALSZ.
+pub(crate) async fn alsz_send_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    m: usize,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(usize, SecretRows, SecretBits), OteError> {
+    let mut sender = alsz_sender(chan, k, group, rng).await?;
+    let u = chan.label("u_i").recv_bit_rows(k, Some(m)).await?;
+    blocking(move || {
+        let (offset, q) = sender.next_rows(u, m);
+        Ok((offset, q, Zeroizing::new(sender.s().to_vec())))
+    })
+    .await
+}
+
+// `iknp_receive_rows` for ALSZ.
+pub(crate) async fn alsz_receive_rows<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    choice: Vec<bool>,
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(usize, SecretRows), OteError> {
+    let m = choice.len();
+    let mut receiver = alsz_receiver(chan, k, group, rng).await?;
+    let (rows, u) = blocking(move || {
+        let (t, u) = receiver.compute_t_and_u(&choice);
+        Ok((receiver.next_rows(t, m), u))
+    })
+    .await?;
+    chan.label("u_i").send_bit_rows(&u).await?;
+    Ok(rows)
+}
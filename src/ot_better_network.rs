@@ -1,173 +1,218 @@
+use rand::{CryptoRng, RngCore};
+
 use crate::common::*;
+use crate::error::OteError;
 use crate::ot_primitive;
 use crate::ot_primitive::bool_vec_to_usize;
 use crate::ot_primitive::usize_to_bool_vec_len;
 use ot_primitive::PublicKey;
 use ot_primitive::SafePrimeGroup;
-use rand::random;
+use ot_primitive::USIZE;
 
-struct Receiver {
-    k: Vec<(Vec<bool>, Vec<bool>)>,
-    choice_bits: Vec<bool>,
+pub(crate) struct Receiver {
+    k: Zeroizing<Vec<(Vec<bool>, Vec<bool>)>>,
+    prgs: Vec<(Prg, Prg)>,
+    // Index of the next OT, used as counter in the hash so it never repeats across chunks.
+    offset: usize,
 }
-struct Sender {
-    s: Vec<bool>,
-    messages: Vec<(Vec<bool>, Vec<bool>)>,
-    k_s: Vec<Vec<bool>>,
+pub(crate) struct Sender {
+    s: SecretBits,
+    k_s: SecretRows,
+    prgs: Vec<Prg>,
+    offset: usize,
 }
 
 impl Receiver {
-    fn initialize(k: usize, choice_bits: Vec<bool>) -> Receiver {
-        let k = (0..k)
-            .map(|_| {
-                (
-                    random_boolvec_len(k),
-                    random_boolvec_len(k),
-                )
-            })
-            .collect::<Vec<(Vec<bool>, Vec<bool>)>>();
-        return Receiver { k, choice_bits };
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(k: usize, rng: &mut R) -> Result<Receiver, OteError> {
+        check_seed_length(k)?;
+        let k = Zeroizing::new(
+            (0..k)
+                .map(|_| {
+                    (
+                        random_boolvec_len(k, rng),
+                        random_boolvec_len(k, rng),
+                    )
+                })
+                .collect::<Vec<(Vec<bool>, Vec<bool>)>>(),
+        );
+        let prgs = k
+            .iter()
+            .map(|(k_0, k_1)| Ok((Prg::new(k_0)?, Prg::new(k_1)?)))
+            .collect::<Result<Vec<_>, OteError>>()?;
+        Ok(Receiver { k, prgs, offset: 0 })
     }
 
-    fn do_protocol(&self, sender: &mut Sender, group: &SafePrimeGroup) -> Vec<Vec<bool>> {
-        sender.receive_ot_primitive(self, group);
-        let m = self.choice_bits.len();
-        let t = self
-            .k
-            .iter()
-            .map(|(k_0, _)| pseudo_random_gen(k_0, m))
-            .collect::<Vec<Vec<bool>>>();
-        let u = self
-            .k
-            .iter()
-            .zip(t.clone())
-            .map(|((_, k_1), t_i)| {
-                xor_boolvec(
-                    &self.choice_bits,
-                    &xor_boolvec(&t_i, &pseudo_random_gen(&k_1, m)),
-                )
+    // Base OTs, in which the receiver sends its seeds and the sender chooses with s.
+    pub(crate) fn setup<R: CryptoRng + RngCore>(&self, sender: &mut Sender, group: &SafePrimeGroup, rng: &mut R) -> Result<(), OteError> {
+        let (sk, keys) = sender.commit_ot_primitive(group, rng)?;
+        let res = self.send_ot_primitive(group, &keys, rng)?;
+        sender.receive_ot_primitive(group, &sk, &res)
+    }
+
+    pub(crate) fn extend(&mut self, sender: &mut Sender, messages: &[(Vec<bool>, Vec<bool>)], choice_bits: &[bool]) -> Vec<Vec<bool>> {
+        let (t, u) = self.compute_t_and_u(choice_bits);
+        let y = sender.receive_vectors(u, messages);
+        self.decode(&y, t, choice_bits)
+    }
+
+    // Expand the next chunk of the seeds into the columns t^i and the corrections u^i sent to the sender.
+    pub(crate) fn compute_t_and_u(&mut self, choice_bits: &[bool]) -> (SecretRows, Vec<Vec<bool>>) {
+        let m = choice_bits.len();
+        let (t, u) = self
+            .prgs
+            .iter_mut()
+            .map(|(g_0, g_1)| {
+                let t_i = g_0.next_bits(m);
+                let t_i_xor = Zeroizing::new(xor_boolvec(&t_i, &Zeroizing::new(g_1.next_bits(m))));
+                let u_i = xor_boolvec(choice_bits, &t_i_xor);
+                (t_i, u_i)
             })
-            .collect::<Vec<Vec<bool>>>();
+            .unzip();
+        (Zeroizing::new(t), u)
+    }
 
-        let y = sender.receive_vectors(u);
-        let t_transpose = transpose(&t);
+    // Unmask the chosen message of every pair in y, given the columns t^i of this chunk.
+    pub(crate) fn decode(&mut self, y: &[(Vec<bool>, Vec<bool>)], t: SecretRows, choice_bits: &[bool]) -> Vec<Vec<bool>> {
+        let (offset, t_transpose) = self.next_rows(t, choice_bits.len());
         let z = y
             .iter()
-            .zip(t_transpose)
+            .zip(t_transpose.iter())
             .enumerate()
             .map(|(j, ((yj_0, yj_1), t_j))| {
-                let yj = if self.choice_bits[j] { yj_1 } else { yj_0 };
-                xor_boolvec(yj, &hash_bits(&int_to_bool_vec(j), &t_j))
+                let yj = Zeroizing::new(select_boolvec(yj_0, yj_1, ct_choice(choice_bits[j])));
+                xor_boolvec(&yj, &Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), t_j, yj.len())))
             })
             .collect::<Vec<_>>();
         z
     }
 
-    fn send_ot_primitive(
+    // The rows t_j of a chunk of m OTs, together with the hash index of the first one.
+    pub(crate) fn next_rows(&mut self, t: SecretRows, m: usize) -> (usize, SecretRows) {
+        let offset = self.offset;
+        self.offset += m;
+        (offset, Zeroizing::new(transpose(&t)))
+    }
+
+    pub(crate) fn send_ot_primitive<R: CryptoRng + RngCore>(
         &self,
         group: &SafePrimeGroup,
         keys: &Vec<(PublicKey, PublicKey)>,
-    ) -> ot_primitive::OTParams {
-        let r_input = self
-            .k
-            .iter()
-            .map(|(k_0, k_1)| (bool_vec_to_usize(k_0), bool_vec_to_usize(k_1)))
-            .collect::<Vec<_>>();
-        return ot_primitive::send(group, keys, &r_input);
+        rng: &mut R,
+    ) -> Result<ot_primitive::OTParams, OteError> {
+        let r_input = Zeroizing::new(
+            self.k
+                .iter()
+                .map(|(k_0, k_1)| Ok((bool_vec_to_usize(k_0)?, bool_vec_to_usize(k_1)?)))
+                .collect::<Result<Vec<_>, OteError>>()?,
+        );
+        ot_primitive::send(group, keys, &r_input, rng)
     }
 }
 
 impl Sender {
-    fn initialize(k: usize, messages: Vec<(Vec<bool>, Vec<bool>)>) -> Sender {
-        let s = random_boolvec_len(k);
-        return Sender {
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(k: usize, rng: &mut R) -> Result<Sender, OteError> {
+        check_seed_length(k)?;
+        let s = Zeroizing::new(random_boolvec_len(k, rng));
+        Ok(Sender {
             s,
-            messages,
-            k_s: Vec::new(),
-        };
+            k_s: Zeroizing::default(),
+            prgs: Vec::new(),
+            offset: 0,
+        })
     }
 
-    fn receive_vectors(&self, u: Vec<Vec<bool>>) -> Vec<(Vec<bool>, Vec<bool>)> {
-        let m = self.messages.len();
-        let q = self
-            .k_s
-            .iter()
-            .zip(u)
-            .enumerate()
-            .map(|(i, (k_i, u_i))| {
-                let g = pseudo_random_gen(k_i, m);
-                if self.s[i] {
-                    xor_boolvec(&u_i, &g)
-                } else {
-                    g
-                }
-            })
-            .collect::<Vec<_>>();
-        let q_transp = transpose(&q);
-        self.messages
+    pub(crate) fn s(&self) -> &[bool] {
+        &self.s
+    }
+
+    pub(crate) fn receive_vectors(&mut self, u: Vec<Vec<bool>>, messages: &[(Vec<bool>, Vec<bool>)]) -> MessagePairs {
+        let (offset, q_transp) = self.next_rows(u, messages.len());
+        self.mask_messages(offset, &q_transp, messages)
+    }
+
+    // Mask both messages of every pair with the hashes of q_j and q_j xor s, given the rows q_j of a chunk.
+    pub(crate) fn mask_messages(&self, offset: usize, q_transp: &[Vec<bool>], messages: &[(Vec<bool>, Vec<bool>)]) -> MessagePairs {
+        messages
             .iter()
             .zip(q_transp)
             .enumerate()
             .map(|(j, ((xj_0, xj_1), q_j))| {
-                let yj_0 = xor_boolvec(xj_0, &hash_bits(&int_to_bool_vec(j), &q_j));
-                let yj_1 = xor_boolvec(
-                    xj_1,
-                    &hash_bits(&int_to_bool_vec(j), &xor_boolvec(&q_j, &self.s)),
-                );
-                (yj_0, yj_1)
+                let pad_0 = Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), q_j, xj_0.len()));
+                let q_j_xor_s = Zeroizing::new(xor_boolvec(q_j, &self.s));
+                let pad_1 = Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), &q_j_xor_s, xj_1.len()));
+                (xor_boolvec(xj_0, &pad_0), xor_boolvec(xj_1, &pad_1))
             })
             .collect::<Vec<_>>()
     }
 
-    fn receive_ot_primitive(&mut self, receiver: &Receiver, group: &SafePrimeGroup) {
+    // The rows q_j = t_j xor (r_j * s) of a chunk of m OTs, together with the hash index of the first one.
+    pub(crate) fn next_rows(&mut self, u: Vec<Vec<bool>>, m: usize) -> (usize, SecretRows) {
+        let offset = self.offset;
+        self.offset += m;
+        let q = Zeroizing::new(
+            self.prgs
+                .iter_mut()
+                .zip(u)
+                .enumerate()
+                .map(|(i, (prg, u_i))| {
+                    let g = Zeroizing::new(prg.next_bits(m));
+                    let g_xor_u = Zeroizing::new(xor_boolvec(&u_i, &g));
+                    select_boolvec(&g, &g_xor_u, ct_choice(self.s[i]))
+                })
+                .collect::<Vec<_>>(),
+        );
+        (offset, Zeroizing::new(transpose(&q)))
+    }
+
+    // The sender plays the receiver in the base OTs, with s as its choice bits.
+    pub(crate) fn commit_ot_primitive<R: CryptoRng + RngCore>(&self, group: &SafePrimeGroup, rng: &mut R) -> Result<ot_primitive::Commitment, OteError> {
+        let sk = ot_primitive::create_secret_keys(group, self.s.len(), rng);
+        let keys = ot_primitive::commit_choice(group, &sk, &self.s, rng)?;
+        Ok((sk, keys))
+    }
+
+    pub(crate) fn receive_ot_primitive(&mut self, group: &SafePrimeGroup, sk: &[USIZE], res: &ot_primitive::OTParams) -> Result<(), OteError> {
         let k = self.s.len();
-        let sk = ot_primitive::create_secret_keys(group, k);
-        let keys = ot_primitive::commit_choice(group, &sk, &self.s);
-        let res = receiver.send_ot_primitive(group, &keys);
-        let values = ot_primitive::receive_(group, &res, &sk, &self.s);
-        self.k_s = values
-            .iter()
-            .map(|x| usize_to_bool_vec_len(x, k))
-            .collect::<Vec<Vec<bool>>>()
+        let values = Zeroizing::new(ot_primitive::receive_(group, res, sk, &self.s)?);
+        self.k_s = Zeroizing::new(
+            values
+                .iter()
+                .map(|x| usize_to_bool_vec_len(x, k))
+                .collect::<Vec<Vec<bool>>>(),
+        );
+        self.prgs = self.k_s.iter().map(|k_i| Prg::new(k_i)).collect::<Result<_, _>>()?;
+        Ok(())
     }
 }
 
-pub fn ote(messages: Vec<(Vec<bool>, Vec<bool>)>, choice: Vec<bool>, k: usize, group: &SafePrimeGroup) -> Vec<Vec<bool>> {
-    let mut sender = Sender::initialize(k, messages);
-    let receiver = Receiver::initialize(k, choice);
+pub fn ote<R: CryptoRng + RngCore>(messages: Vec<(Vec<bool>, Vec<bool>)>, choice: Vec<bool>, k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<Vec<bool>>, OteError> {
+    check_inputs(&messages, &choice)?;
+    let mut sender = Sender::initialize(k, rng)?;
+    let mut receiver = Receiver::initialize(k, rng)?;
 
-    receiver.do_protocol(&mut sender, group)
+    receiver.setup(&mut sender, group, rng)?;
+    Ok(receiver.extend(&mut sender, &messages, &choice))
 }
 
-pub fn run_tests() {
-    let group = &ot_primitive::make_group();
-    println!("Testing network friendly OTE... ");
-    for m in [1, 10000] {
-        for k in [128, 256] {
-            println!("Running protocol with m={} and k={} .", m, k);
-            for _ in 0..1 {
-                let messages = (0..m)
-                    .into_iter()
-                    .map(|x| {
-                        (
-                            int_to_boolvec_len(x, OUTPUT_SIZE),
-                            int_to_boolvec_len(x + 1, OUTPUT_SIZE),
-                        )
-                    })
-                    .collect::<Vec<_>>();
-                let choice_bits = (0..m).into_iter().map(|_| random()).collect::<Vec<_>>();
-                let prediction = ote(messages.clone(), choice_bits.clone(), k, group);
-                let correct = messages
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, m)| if choice_bits[i] { m.1 } else { m.0 })
-                    .collect::<Vec<_>>();
-                prediction
-                    .into_iter()
-                    .zip(correct)
-                    .for_each(|(p, c)| assert_eq!(p, c))
-            }
-        }
+/**
+ * Runs the extension chunk by chunk, so memory stays constant no matter the total number of OTs.
+ * `chunks` yields the messages and choice bits of one chunk at a time (e.g. DEFAULT_CHUNK_SIZE OTs)
+ * and `on_output` is handed the receiver's output for each chunk in order.
+ * The base OTs run once and the PRG streams and hash counter continue across chunks.
+ */
+pub fn ote_streaming<I, F, R>(chunks: I, k: usize, group: &SafePrimeGroup, rng: &mut R, mut on_output: F) -> Result<(), OteError>
+where
+    I: IntoIterator<Item = (MessagePairs, Vec<bool>)>,
+    F: FnMut(Vec<Vec<bool>>),
+    R: CryptoRng + RngCore,
+{
+    let mut sender = Sender::initialize(k, rng)?;
+    let mut receiver = Receiver::initialize(k, rng)?;
+
+    receiver.setup(&mut sender, group, rng)?;
+    for (messages, choice) in chunks {
+        check_inputs(&messages, &choice)?;
+        on_output(receiver.extend(&mut sender, &messages, &choice));
     }
-    println!("Everything worked")
+    Ok(())
 }
@@ -0,0 +1,123 @@
+// 1-out-of-N OT from log N 1-out-of-2 OTs (Naor, Pinkas 1999).
+//
+// For every OT the sender picks l = ceil(log2 N) pairs of k-bit keys (K_i^0, K_i^1) and sends
+// row v of its table masked with H(v, K_1^{v_1} || .. || K_l^{v_l}), where v_i is bit i of v.
+// The receiver gets K_i^{c_i} for the bits of its choice c with l 1-out-of-2 OTs on the session,
+// so it can unmask row c and no other.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::session::OtExtSession;
+
+// Number of 1-out-of-2 OTs for a table of n rows.
+pub fn choice_bits(n: usize) -> usize {
+    n.next_power_of_two().trailing_zeros() as usize
+}
+
+// The pad of row v from one key per bit of v.
+fn pad(v: usize, keys: &[&[bool]], len: usize) -> Vec<bool> {
+    let keys = Zeroizing::new(keys.concat());
+    hash_bits(&int_to_bool_vec(v), &keys, len)
+}
+
+// Checks that every table has rows of one length and every choice is a row, and returns the row lengths.
+fn check_tables(tables: &[&[Vec<bool>]], choices: &[usize]) -> Result<Vec<usize>, OteError> {
+    if tables.len() != choices.len() {
+        return Err(OteError::BadParameter(format!("{} tables for {} choices", tables.len(), choices.len())));
+    }
+    tables
+        .iter()
+        .zip(choices)
+        .enumerate()
+        .map(|(i, (table, &c))| {
+            if c >= table.len() {
+                return Err(OteError::BadParameter(format!("choice {} of OT {} is not a row of its {} rows", c, i, table.len())));
+            }
+            let len = table[0].len();
+            if table.iter().any(|row| row.len() != len) {
+                return Err(OteError::BadParameter(format!("the rows of table {} differ in length", i)));
+            }
+            Ok(len)
+        })
+        .collect()
+}
+
+// Sender: the key pairs of all OTs, one after the other, and every table masked with its keys.
+pub(crate) fn mask_tables<R: CryptoRng + RngCore>(
+    tables: &[&[Vec<bool>]],
+    lens: &[usize],
+    k: usize,
+    rng: &mut R,
+) -> (Zeroizing<MessagePairs>, Vec<Vec<Vec<bool>>>) {
+    let total = tables.iter().map(|table| choice_bits(table.len())).sum::<usize>();
+    let keys = Zeroizing::new((0..total).map(|_| (random_boolvec_len(k, rng), random_boolvec_len(k, rng))).collect::<Vec<_>>());
+    let mut start = 0;
+    let masked = tables
+        .iter()
+        .zip(lens)
+        .map(|(table, &len)| {
+            let l = choice_bits(table.len());
+            let own = &keys[start..start + l];
+            start += l;
+            table
+                .iter()
+                .enumerate()
+                .map(|(v, row)| {
+                    let keys = own.iter().enumerate().map(|(i, (k_0, k_1))| if v >> i & 1 == 1 { &k_1[..] } else { &k_0[..] }).collect::<Vec<_>>();
+                    xor_boolvec(row, &pad(v, &keys, len))
+                })
+                .collect()
+        })
+        .collect();
+    (keys, masked)
+}
+
+// Receiver: the bits of its choices in tables of the given sizes, one 1-out-of-2 OT per bit.
+pub(crate) fn choice_of(sizes: &[usize], choices: &[usize]) -> SecretBits {
+    Zeroizing::new(sizes.iter().zip(choices).flat_map(|(&n, &c)| (0..choice_bits(n)).map(move |i| c >> i & 1 == 1)).collect())
+}
+
+// Receiver: the chosen rows of the masked tables with the keys it received.
+pub(crate) fn unmask<T: AsRef<[Vec<bool>]>>(masked: &[T], choices: &[usize], received: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    let mut start = 0;
+    masked
+        .iter()
+        .zip(choices)
+        .map(|(rows, &c)| {
+            let rows = rows.as_ref();
+            let l = choice_bits(rows.len());
+            let keys = received[start..start + l].iter().map(|x| &x[..]).collect::<Vec<_>>();
+            start += l;
+            xor_boolvec(&rows[c], &pad(c, &keys, rows[c].len()))
+        })
+        .collect()
+}
+
+fn run<R: CryptoRng + RngCore>(session: &mut OtExtSession, tables: &[&[Vec<bool>]], choices: &[usize], rng: &mut R) -> Result<Vec<Vec<bool>>, OteError> {
+    let lens = check_tables(tables, choices)?;
+    let (keys, masked) = mask_tables(tables, &lens, session.k(), rng);
+    let sizes = tables.iter().map(|table| table.len()).collect::<Vec<_>>();
+    let received = Zeroizing::new(session.extend(&keys, &choice_of(&sizes, choices))?);
+    Ok(unmask(&masked, choices, &received))
+}
+
+/**
+ * One 1-out-of-N OT per table on the session: the receiver learns row choices[i] of tables[i].
+ * The tables may have different sizes and row lengths, but all rows of one table must have the
+ * same length. All 1-out-of-2 OTs of the batch are extended together.
+ */
+pub fn ot_n<R: CryptoRng + RngCore>(
+    session: &mut OtExtSession,
+    tables: &[Vec<Vec<bool>>],
+    choices: &[usize],
+    rng: &mut R,
+) -> Result<Vec<Vec<bool>>, OteError> {
+    run(session, &tables.iter().map(|table| &table[..]).collect::<Vec<_>>(), choices, rng)
+}
+
+// `ot_n` with the same table for every choice, which is only borrowed once.
+pub(crate) fn ot_n_shared<R: CryptoRng + RngCore>(session: &mut OtExtSession, table: &[Vec<bool>], choices: &[usize], rng: &mut R) -> Result<Vec<Vec<bool>>, OteError> {
+    run(session, &vec![table; choices.len()], choices, rng)
+}
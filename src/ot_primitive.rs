@@ -1,91 +1,160 @@
-use crypto_bigint::{modular, rand_core::OsRng, NonZero, RandomMod, Uint, U2048};
-use crypto_primes;
+use crypto_bigint::{modular, Encoding, Integer, NonZero, RandomMod, Uint, U2048};
 use modular::runtime_mod::{DynResidue, DynResidueParams};
-use rand::random;
+use rand::{CryptoRng, RngCore};
 use rayon::prelude::*;
+use subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
 
-use crate::common::int_to_bool_vec;
+use crate::common::{be_u64, check_inputs, ct_choice, Zeroizing};
+use crate::error::OteError;
 
+#[allow(clippy::upper_case_acronyms)]
 pub type USIZE = U2048;
 pub const PRIME_FILE_NAME: &str = "safe_prime.txt";
 const SECURITY: usize = 2048; // Larger security is extremely slow
 const DYN_RES: usize = SECURITY / 64; // 8*WORD_SIZE(64) = 512
+pub const ELEM_BYTES: usize = SECURITY / 8;
+// 384 bit safe prime for `make_test_group`. Far too small to be secure, but it makes the base OTs fast.
+const TEST_PRIME: &str = "f1e9308d2462767fd2811a6d21c3d09edfb79f2f902086781bd9e0c1e76279aebafc3229d92b426425cbb641f24421fb";
 type GroupElem = DynResidue<DYN_RES>;
 pub type PublicKey = GroupElem;
 pub type OTParams = Vec<((GroupElem, GroupElem), (GroupElem, GroupElem))>;
+// Secret keys of the base OT receiver, wiped when dropped.
+pub type SecretKeys = Zeroizing<Vec<USIZE>>;
+// Secret keys of the base OT receiver and the public keys it sends.
+pub type Commitment = (SecretKeys, Vec<(PublicKey, PublicKey)>);
 
 #[derive(Clone)]
 pub struct SafePrimeGroup {
     g: USIZE,
     p: USIZE,
     q: USIZE,
+    // p and q as moduli for sampling, checked to be non-zero once when the group is made.
+    p_modulus: NonZero<USIZE>,
+    q_modulus: NonZero<USIZE>,
+    // All exponents are reduced modulo q, so exponentiations only need this many bits.
+    q_bits: usize,
 }
 
-pub fn make_group_from_scratch() {
-    let p = crypto_primes::generate_safe_prime(Some(SECURITY));
+impl SafePrimeGroup {
+    // Group of quadratic residues modulo the safe prime p = 2q + 1, with a random generator.
+    pub fn from_prime<R: CryptoRng + RngCore>(p: USIZE, rng: &mut R) -> Result<SafePrimeGroup, OteError> {
+        if p <= USIZE::from(5u32) || !bool::from(p.is_odd()) {
+            return Err(OteError::BadParameter("p must be an odd prime larger than 5".to_owned()));
+        }
+        let q = p.shr_vartime(1);
+        let p_modulus = Option::from(NonZero::new(p)).ok_or_else(|| OteError::BadParameter("p is zero".to_owned()))?;
+        let q_modulus = Option::from(NonZero::new(q)).ok_or_else(|| OteError::BadParameter("q is zero".to_owned()))?;
+        let g = get_generator(&p, &p_modulus, rng);
+        Ok(SafePrimeGroup { g, p, q, p_modulus, q_modulus, q_bits: q.bits() })
+    }
+
+    // Bits of the group order q.
+    pub(crate) fn q_bits(&self) -> usize {
+        self.q_bits
+    }
+}
+
+pub fn make_group_from_scratch<R: CryptoRng + RngCore>(rng: &mut R) -> Result<(), OteError> {
+    let p = crypto_primes::generate_safe_prime_with_rng(rng, Some(SECURITY));
     let x = USIZE::to_words(p).iter().map(|x| x.to_be_bytes()).rev().flatten().collect::<Vec<_>>();
     let mut file = std::fs::OpenOptions::new()
     .write(true)
     .truncate(true)
     .create(true)
-    .open(PRIME_FILE_NAME)
-    .ok()
-    .unwrap();
-    std::io::Write::write(&mut file, &x).ok();
-    assert_eq!(p, make_group().p, "Saved p was not equal to created p");
+    .open(PRIME_FILE_NAME)?;
+    std::io::Write::write_all(&mut file, &x)?;
+    if p != make_group(rng)?.p {
+        return Err(OteError::ConsistencyCheckFailed("saved p was not equal to created p".to_owned()));
+    }
+    Ok(())
 }
 
-pub fn make_group() -> SafePrimeGroup {
+pub fn make_group<R: CryptoRng + RngCore>(rng: &mut R) -> Result<SafePrimeGroup, OteError> {
     let mut p_as_bytes = Vec::new();
-    let byte_num = std::io::Read::read_to_end(&mut std::fs::OpenOptions::new().read(true).write(false).open(PRIME_FILE_NAME).ok().unwrap(), &mut p_as_bytes);
-    assert_eq!(byte_num.ok().unwrap(), SECURITY / 8, "Asserts correct number of bytes in file");
-    let p = USIZE::from_words(crate::common::to_array(p_as_bytes.rchunks(8).map(|x| u64::from_be_bytes([x[0], x[1], x[2], x[3], x[4], x[5], x[6], x[7]])).collect::<Vec<_>>()));
+    let byte_num = std::io::Read::read_to_end(&mut std::fs::OpenOptions::new().read(true).write(false).open(PRIME_FILE_NAME)?, &mut p_as_bytes)?;
+    if byte_num != SECURITY / 8 {
+        return Err(OteError::InvalidFile(format!("{} should hold {} bytes but has {}", PRIME_FILE_NAME, SECURITY / 8, byte_num)));
+    }
+    let p = USIZE::from_words(crate::common::to_array(p_as_bytes.rchunks(8).map(be_u64).collect::<Vec<_>>())?);
     // let p = crypto_primes::generate_safe_prime(Some(SECURITY));
-    let q = (p.wrapping_sub(&USIZE::from(1u32))).wrapping_div(&USIZE::from(2u32)); // Should never wrap, but Rust doesn't know that.
-    let g = get_generator(&p);
-    return SafePrimeGroup { g, p, q };
+    SafePrimeGroup::from_prime(p, rng)
 }
 
-pub fn create_secret_keys(group: &SafePrimeGroup, num: usize) -> Vec<USIZE> {
-    (0..num)
-        .map(|_| USIZE::random_mod(&mut OsRng, &NonZero::new(group.q).unwrap()))
-        .collect()
+/**
+ * Group over a small built-in safe prime, so tests need neither `safe_prime.txt` nor 2048 bit exponentiations.
+ * Only for testing: it offers no real security.
+ */
+pub fn make_test_group<R: CryptoRng + RngCore>(rng: &mut R) -> Result<SafePrimeGroup, OteError> {
+    let bytes = (0..TEST_PRIME.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&TEST_PRIME[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| OteError::BadParameter("test prime is not hex".to_owned()))?;
+    let mut padded = vec![0; ELEM_BYTES - bytes.len()];
+    padded.extend(bytes);
+    SafePrimeGroup::from_prime(USIZE::from_be_slice(&padded), rng)
+}
+
+pub fn create_secret_keys<R: CryptoRng + RngCore>(group: &SafePrimeGroup, num: usize, rng: &mut R) -> SecretKeys {
+    Zeroizing::new(
+        (0..num)
+            .map(|_| USIZE::random_mod(rng, &group.q_modulus))
+            .collect(),
+    )
+}
+
+// Both lists of a base OT step must describe the same number of OTs.
+fn check_counts(what: &str, expected: usize, got: usize) -> Result<(), OteError> {
+    if expected != got {
+        return Err(OteError::BadParameter(format!("expected {} {} but got {}", expected, what, got)));
+    }
+    Ok(())
 }
 
 
-// Choose the real and oblivious keys to send to Bob.
-pub fn commit_choice(
+/**
+ * Choose the real and oblivious keys to send to Bob.
+ * The randomness is drawn before the parallel part, so a seeded rng always gives the same keys.
+ */
+pub fn commit_choice<R: CryptoRng + RngCore>(
     group: &SafePrimeGroup,
-    sk: &Vec<USIZE>,
+    sk: &[USIZE],
     choice: &Vec<bool>,
-) -> Vec<(PublicKey, PublicKey)> {
-    let modulus = NonZero::new(group.p).unwrap();
+    rng: &mut R,
+) -> Result<Vec<(PublicKey, PublicKey)>, OteError> {
+    check_counts("secret keys", choice.len(), sk.len())?;
+    let modulus = group.p_modulus;
     let res_params = DynResidueParams::new(&group.p);
     let g = GroupElem::new(&group.g, res_params);
+    let xs = Zeroizing::new(choice.iter().map(|_| USIZE::random_mod(rng, &modulus)).collect::<Vec<_>>());
     let keys = choice
         .par_iter()
+        .zip(xs.par_iter())
         .enumerate()
-        .map(|(i, &b)| {
-            let x = USIZE::random_mod(&mut OsRng, &modulus);
-            let fake_gamal = GroupElem::new(&x, res_params).square();
-            let real_gamal = g.pow(&sk[i]);
-            if b {
-                (fake_gamal, real_gamal)
-            } else {
-                (real_gamal, fake_gamal)
-            }
+        .map(|(i, (&b, x))| {
+            let fake_gamal = GroupElem::new(x, res_params).square();
+            let real_gamal = g.pow_bounded_exp(&sk[i], group.q_bits);
+            let (mut k_0, mut k_1) = (real_gamal, fake_gamal);
+            GroupElem::conditional_swap(&mut k_0, &mut k_1, ct_choice(b));
+            (k_0, k_1)
         })
         .collect::<Vec<_>>();
-    keys
+    Ok(keys)
 }
 
-pub fn send(
+pub fn send<R: CryptoRng + RngCore>(
     group: &SafePrimeGroup,
     keys: &Vec<(PublicKey, PublicKey)>,
     messages: &Vec<(USIZE, USIZE)>,
-) -> OTParams {
+    rng: &mut R,
+) -> Result<OTParams, OteError> {
+    check_counts("public keys", messages.len(), keys.len())?;
+    // The encoding maps messages below q into the subgroup, larger ones would decode wrongly.
+    if messages.iter().any(|(m_0, m_1)| *m_0 >= group.q || *m_1 >= group.q) {
+        return Err(OteError::BadParameter("messages must be smaller than the group order q".to_owned()));
+    }
     let res_params = DynResidueParams::new(&group.p);
-    let modulus = NonZero::new(group.q).unwrap();
+    let modulus = group.q_modulus;
     let messages_as_elems = messages.par_iter().map(|(m_0, m_1)| {
         (
             GroupElem::new(m_0, res_params),
@@ -93,21 +162,31 @@ pub fn send(
         )
     });
     let encode_p_q = |m: &GroupElem| to_encoding(m, &group.p, &group.q);
-    let encoded_messages = messages_as_elems
-        .into_par_iter()
-        .map(|(m_0, m_1)| (encode_p_q(&m_0), encode_p_q(&m_1)))
-        .collect::<Vec<_>>();
-    keys.into_par_iter()
-        .zip(encoded_messages)
-        .map(|((k_0, k_1), (m_0, m_1))| {
-            let r_0 = USIZE::random_mod(&mut OsRng, &modulus);
-            let r_1 = USIZE::random_mod(&mut OsRng, &modulus);
-            let s_0 = k_0.pow(&r_0);
-            let s_1 = k_1.pow(&r_1);
+    let encoded_messages = Zeroizing::new(
+        messages_as_elems
+            .into_par_iter()
+            .map(|(m_0, m_1)| (encode_p_q(&m_0), encode_p_q(&m_1)))
+            .collect::<Vec<_>>(),
+    );
+    // Drawn up front like in `commit_choice`, in the same order whatever the scheduling.
+    let rs = Zeroizing::new(
+        keys.iter()
+            .map(|_| (USIZE::random_mod(rng, &modulus), USIZE::random_mod(rng, &modulus)))
+            .collect::<Vec<_>>(),
+    );
+    Ok(keys.into_par_iter()
+        .zip(encoded_messages.par_iter())
+        .zip(rs.par_iter())
+        .map(|(((k_0, k_1), (m_0, m_1)), (r_0, r_1))| {
+            let s_0 = k_0.pow_bounded_exp(r_0, group.q_bits);
+            let s_1 = k_1.pow_bounded_exp(r_1, group.q_bits);
             let g = GroupElem::new(&group.g, res_params);
-            ((g.pow(&r_0), s_0.mul(&m_0)), (g.pow(&r_1), s_1.mul(&m_1)))
+            (
+                (g.pow_bounded_exp(r_0, group.q_bits), s_0.mul(m_0)),
+                (g.pow_bounded_exp(r_1, group.q_bits), s_1.mul(m_1)),
+            )
         })
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>())
 }
 
 
@@ -115,23 +194,26 @@ pub fn send(
 pub fn receive_(
     group: &SafePrimeGroup,
     m: &OTParams,
-    sk: &Vec<USIZE>,
+    sk: &[USIZE],
     choices: &Vec<bool>,
-) -> Vec<USIZE> {
+) -> Result<Vec<USIZE>, OteError> {
+    check_counts("ciphertexts", choices.len(), m.len())?;
+    check_counts("secret keys", choices.len(), sk.len())?;
     let messages = m
         .par_iter()
         .zip(choices)
         .zip(sk)
         .map(|(((c_d_0, c_d_1), &b), sk)| {
-            let (c, d) = if b { c_d_1 } else { c_d_0 };
+            let c = GroupElem::conditional_select(&c_d_0.0, &c_d_1.0, ct_choice(b));
+            let d = GroupElem::conditional_select(&c_d_0.1, &c_d_1.1, ct_choice(b));
             let (inverted, _) = c.invert(); // Happening modulo prime, so ignore possible error.
-            let m = inverted.pow(&(sk)).mul(&d);
+            let m = inverted.pow_bounded_exp(sk, group.q_bits).mul(&d);
             let x = from_encoding(&m, &group.p, &group.q);
             let k = x.retrieve();
             USIZE::from(k)
         })
         .collect();
-    messages
+    Ok(messages)
 }
 
 /**
@@ -141,12 +223,10 @@ pub fn receive_(
  * q is the order of the subgroup we work in
  */
 fn to_encoding(m: &GroupElem, p: &USIZE, q: &USIZE) -> GroupElem {
-    let one = GroupElem::one(DynResidueParams::new(&p));
-    if (m + one).pow(&q) == one {
-        return m + one;
-    } else {
-        return -(m + one);
-    }
+    let one = GroupElem::one(DynResidueParams::new(p));
+    let x = m + one;
+    let in_subgroup = x.pow_bounded_exp(q, q.bits()).ct_eq(&one);
+    GroupElem::conditional_select(&-x, &x, in_subgroup)
 }
 
 /**
@@ -156,39 +236,79 @@ fn to_encoding(m: &GroupElem, p: &USIZE, q: &USIZE) -> GroupElem {
  * q is the order of the subgroup we work in
  */
 fn from_encoding(m: &GroupElem, p: &USIZE, q: &USIZE) -> GroupElem {
-    let one = GroupElem::one(DynResidueParams::new(&p));
-    if m.retrieve() <= *q {
-        m - one
-    } else {
-        -m - one
+    let one = GroupElem::one(DynResidueParams::new(p));
+    let above_q = m.retrieve().ct_gt(q);
+    GroupElem::conditional_select(&(m - one), &(-m - one), above_q)
+}
+
+pub fn elem_to_bytes(e: &PublicKey) -> [u8; ELEM_BYTES] {
+    e.retrieve().to_be_bytes()
+}
+
+// Returns None if the bytes do not describe an element reduced modulo p.
+pub fn elem_from_bytes(group: &SafePrimeGroup, bytes: &[u8]) -> Option<PublicKey> {
+    if bytes.len() != ELEM_BYTES {
+        return None;
     }
+    let x = USIZE::from_be_slice(bytes);
+    if x >= group.p {
+        return None;
+    }
+    Some(GroupElem::new(&x, DynResidueParams::new(&group.p)))
+}
+
+/**
+ * The group element of a hash of 2 * ELEM_BYTES bytes: the hash modulo p, squared.
+ * With twice the bits of p the hash modulo p is close to uniform, and its discrete logarithm is unknown.
+ */
+pub(crate) fn hash_to_group(group: &SafePrimeGroup, hash: &[u8; 2 * ELEM_BYTES]) -> PublicKey {
+    let params = DynResidueParams::new(&group.p);
+    let (hi, lo) = hash.split_at(ELEM_BYTES);
+    // 2^SECURITY modulo p, as 2^(SECURITY - 1) doubled.
+    let half = GroupElem::new(&USIZE::ONE.shl_vartime(SECURITY - 1), params);
+    let x = GroupElem::new(&USIZE::from_be_slice(hi), params) * (half + half) + GroupElem::new(&USIZE::from_be_slice(lo), params);
+    x.square()
 }
 
-pub fn bool_vec_to_usize(v: &Vec<bool>) -> USIZE {
-    let mut clone = v.clone();
-    clone.reverse();
-    clone.resize(SECURITY, false);
-    clone.reverse();
-    USIZE::from_be_slice(&crate::common::bool_vec_to_byte_vec(&clone)[..])
+// e^x for an exponent x below q.
+pub(crate) fn pow(group: &SafePrimeGroup, e: &PublicKey, x: &USIZE) -> PublicKey {
+    e.pow_bounded_exp(x, group.q_bits)
+}
+
+// 1/x modulo q, so (e^x)^(1/x) = e for every e in the group. None for x = 0.
+pub(crate) fn invert_exponent(group: &SafePrimeGroup, x: &USIZE) -> Option<USIZE> {
+    let (inverse, exists) = x.inv_odd_mod(&group.q);
+    bool::from(exists).then_some(inverse)
+}
+
+// Whether e is in the group of quadratic residues, i.e. e^q = 1. Zero is not.
+pub(crate) fn in_group(group: &SafePrimeGroup, e: &PublicKey) -> bool {
+    let one = GroupElem::one(DynResidueParams::new(&group.p));
+    bool::from(e.pow_bounded_exp(&group.q, group.q_bits).ct_eq(&one))
+}
+
+pub fn bool_vec_to_usize(v: &[bool]) -> Result<USIZE, OteError> {
+    if v.len() > SECURITY {
+        return Err(OteError::BadParameter(format!("{} bits do not fit in a group element", v.len())));
+    }
+    let mut padded = Zeroizing::new(vec![false; SECURITY - v.len()]);
+    padded.extend_from_slice(v);
+    Ok(USIZE::from_be_slice(&Zeroizing::new(crate::common::bool_vec_to_byte_vec(&padded))))
 }
 
 // ot_primitive
 pub fn usize_to_bool_vec_len(n: &USIZE, output_bits: usize) -> Vec<bool> {
-    let x = n
-        .to_words()
-        .to_vec()
-        .iter()
-        .take(usize::div_ceil(output_bits, 64))
-        .rev()
-        .map(|&x| x)
-        .collect::<Vec<_>>();
-    crate::common::int_vec_to_bool_vec(&x)
-        .iter()
-        .rev()
-        .take(output_bits)
-        .rev()
-        .map(|&x| x)
-        .collect::<Vec<_>>()
+    let words = Zeroizing::new(n.to_words());
+    let x = Zeroizing::new(
+        words
+            .iter()
+            .take(usize::div_ceil(output_bits, 64))
+            .rev()
+            .copied()
+            .collect::<Vec<_>>(),
+    );
+    let bits = Zeroizing::new(crate::common::int_vec_to_bool_vec(&x));
+    bits[bits.len().saturating_sub(output_bits)..].to_vec()
 }
 
 // Returns 1 and -1 mod p.
@@ -197,53 +317,31 @@ fn get_one_and_minus_one_mod(p: &Uint<DYN_RES>) -> (Uint<DYN_RES>, Uint<DYN_RES>
     let one = GroupElem::one(res_params);
     let zero = GroupElem::zero(res_params);
     let minus_one = zero.sub(&one);
-    return (one.retrieve(), minus_one.retrieve());
+    (one.retrieve(), minus_one.retrieve())
 }
 
 // Create generator for safe prime p.
-fn get_generator(p: &Uint<DYN_RES>) -> Uint<DYN_RES> {
+fn get_generator<R: CryptoRng + RngCore>(p: &Uint<DYN_RES>, modulus: &NonZero<Uint<DYN_RES>>, rng: &mut R) -> Uint<DYN_RES> {
     let res_params = DynResidueParams::new(p);
     let (one, minus_one) = get_one_and_minus_one_mod(p);
-    let modulus = NonZero::new(*p).unwrap();
-    let mut g = USIZE::random_mod(&mut OsRng, &modulus);
+    let mut g = USIZE::random_mod(rng, modulus);
     loop {
         if g != one && g != minus_one {
             return GroupElem::new(&g, res_params).square().retrieve();
         }
-        g = USIZE::random_mod(&mut OsRng, &modulus);
+        g = USIZE::random_mod(rng, modulus);
     }
 }
 
 
-pub fn ote(messages: Vec<(Vec<bool>, Vec<bool>)>, choice: Vec<bool>, _: usize, group: &SafePrimeGroup) -> Vec<Vec<bool>> {
+pub fn ote<R: CryptoRng + RngCore>(messages: Vec<(Vec<bool>, Vec<bool>)>, choice: Vec<bool>, _: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<Vec<bool>>, OteError> {
     let m = messages.len();
-    let len = messages[0].0.len();
-    let sk = create_secret_keys(&group, m);
-    let keys = commit_choice(&group, &sk, &choice);
-    let messages_as_usize = messages.iter().map(|(m_0, m_1)| (bool_vec_to_usize(m_0), bool_vec_to_usize(m_1))).collect::<Vec<_>>();
-    let encrypted_messages = send(&group, &keys, &messages_as_usize);
-    let res = receive_(&group, &encrypted_messages, &sk, &choice);
-    return res.iter().map(|x| usize_to_bool_vec_len(x, len)).collect::<Vec<_>>();
-}
-
-
-pub fn run_tests() {
-    let group = &make_group();
-    println!("Testing primitive... ");
-    for m in [10] {
-        println!("Running protocol with m={}.", m);
-        let messages = (0..m).into_iter().map(|x| (int_to_bool_vec(x), int_to_bool_vec(x + 1))).collect::<Vec<_>>();
-        let choice_bits = (0..m).into_iter().map(|_| random()).collect::<Vec<_>>();
-        let prediction = ote(messages.clone(), choice_bits.clone(), 0, group);
-        let correct = messages
-            .into_iter()
-            .enumerate()
-            .map(|(i, m)| if choice_bits[i] { m.1 } else { m.0 })
-            .collect::<Vec<_>>();
-        prediction
-            .into_iter()
-            .zip(correct)
-            .for_each(|(p, c)| assert_eq!(p, c))
-    }
-    println!("OK")
+    let len = check_inputs(&messages, &choice)?;
+    // Messages are encoded as group elements, so they cannot be longer than one.
+    let messages_as_usize = Zeroizing::new(messages.iter().map(|(m_0, m_1)| Ok((bool_vec_to_usize(m_0)?, bool_vec_to_usize(m_1)?))).collect::<Result<Vec<_>, OteError>>()?);
+    let sk = create_secret_keys(group, m, rng);
+    let keys = commit_choice(group, &sk, &choice, rng)?;
+    let encrypted_messages = send(group, &keys, &messages_as_usize, rng)?;
+    let res = Zeroizing::new(receive_(group, &encrypted_messages, &sk, &choice)?);
+    Ok(res.iter().map(|x| usize_to_bool_vec_len(x, len)).collect::<Vec<_>>())
 }
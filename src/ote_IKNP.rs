@@ -1,172 +1,236 @@
+use rand::{CryptoRng, RngCore};
+use rand_chacha::ChaCha20Rng;
+
 use crate::common::*;
+use crate::error::OteError;
 use crate::ot_primitive;
 use crate::ot_primitive::bool_vec_to_usize;
 use crate::ot_primitive::usize_to_bool_vec_len;
 use ot_primitive::PublicKey;
 use ot_primitive::SafePrimeGroup;
-use rand::random;
+use ot_primitive::USIZE;
 
-struct Receiver {
-    t: Vec<Vec<bool>>,
-    rand_seeds: Vec<(Vec<bool>, Vec<bool>)>,
-    choice_bits: Vec<bool>,
+pub(crate) struct Receiver {
+    rand_seeds: Zeroizing<Vec<(Vec<bool>, Vec<bool>)>>,
+    prgs: Vec<(Prg, Prg)>,
+    // Index of the next OT, used as counter in the hash so it never repeats across chunks.
+    offset: usize,
+    // Draws the fresh t of every chunk.
+    rng: Box<ChaCha20Rng>,
 }
 
-struct Sender {
-    s: Vec<bool>,
-    messages: Vec<(Vec<bool>, Vec<bool>)>,
+pub(crate) struct Sender {
+    s: SecretBits,
+    prgs: Vec<Prg>,
+    offset: usize,
 }
 
 impl Receiver {
-    fn initialize(k: usize, m: usize, choice_bits: Vec<bool>) -> Receiver {
-        let t = (0..m)
-            .map(|_| random_boolvec_len(k))
-            .collect::<Vec<Vec<bool>>>();
-        let rand_seeds = (0..k)
-            .map(|_| (random_boolvec_len(k), random_boolvec_len(k)))
-            .collect::<Vec<_>>();
-        return Receiver {
-            t,
-            choice_bits,
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(k: usize, rng: &mut R) -> Result<Receiver, OteError> {
+        check_seed_length(k)?;
+        let rand_seeds = Zeroizing::new(
+            (0..k)
+                .map(|_| (random_boolvec_len(k, rng), random_boolvec_len(k, rng)))
+                .collect::<Vec<_>>(),
+        );
+        let prgs = rand_seeds
+            .iter()
+            .map(|(s_0, s_1)| Ok((Prg::new(s_0)?, Prg::new(s_1)?)))
+            .collect::<Result<Vec<_>, OteError>>()?;
+        Ok(Receiver {
             rand_seeds,
-        };
+            prgs,
+            offset: 0,
+            rng: Box::new(fork_rng(rng)),
+        })
+    }
+
+    // Base OTs, in which the receiver sends its seeds and the sender chooses with s.
+    pub(crate) fn setup<R: CryptoRng + RngCore>(&self, sender: &mut Sender, group: &SafePrimeGroup, rng: &mut R) -> Result<(), OteError> {
+        let (sk, keys) = sender.commit_ot_primitive(group, rng)?;
+        let seeds_ot = self.send_ot_primitive(group, &keys, rng)?;
+        sender.receive_ot_primitive(group, &sk, &seeds_ot)
+    }
+
+    pub(crate) fn extend(&mut self, sender: &mut Sender, messages: &[(Vec<bool>, Vec<bool>)], choice_bits: &[bool]) -> Vec<Vec<bool>> {
+        let (t, otp) = self.mask_columns(choice_bits);
+        let y = sender.receive_columns(otp, messages);
+        self.decode(&y, t, choice_bits)
     }
 
-    fn do_protocol(&self, sender: &Sender, group: &SafePrimeGroup) -> Vec<Vec<bool>> {
-        let y = sender.receive_ot_primitive(self, group);
+    // Unmask the chosen message of every pair in y, given the rows t_j of this chunk.
+    pub(crate) fn decode(&mut self, y: &[(Vec<bool>, Vec<bool>)], t: SecretRows, choice_bits: &[bool]) -> Vec<Vec<bool>> {
+        let (offset, t) = self.next_rows(t, choice_bits.len());
         let z = y
             .iter()
-            .zip(&self.t)
+            .zip(t.iter())
             .enumerate()
             .map(|(j, ((yj_0, yj_1), t_j))| {
-                let yj = if self.choice_bits[j] { yj_1 } else { yj_0 };
-                xor_boolvec(yj, &hash_bits(&int_to_bool_vec(j), t_j))
+                let yj = Zeroizing::new(select_boolvec(yj_0, yj_1, ct_choice(choice_bits[j])));
+                xor_boolvec(&yj, &Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), t_j, yj.len())))
             })
             .collect::<Vec<_>>();
         z
     }
 
-    fn send_ot_primitive(
+    // The rows t_j of a chunk of m OTs, together with the hash index of the first one.
+    pub(crate) fn next_rows(&mut self, t: SecretRows, m: usize) -> (usize, SecretRows) {
+        let offset = self.offset;
+        self.offset += m;
+        (offset, t)
+    }
+
+    pub(crate) fn send_ot_primitive<R: CryptoRng + RngCore>(
         &self,
         group: &SafePrimeGroup,
         keys: &Vec<(PublicKey, PublicKey)>,
-    ) -> (ot_primitive::OTParams, Vec<(Vec<bool>, Vec<bool>)>) {
-        let inputs = self
-            .rand_seeds
-            .iter()
-            .map(|(s_0, s_1)| (bool_vec_to_usize(s_0), bool_vec_to_usize(s_1)))
-            .collect::<Vec<_>>();
+        rng: &mut R,
+    ) -> Result<ot_primitive::OTParams, OteError> {
+        let inputs = Zeroizing::new(
+            self.rand_seeds
+                .iter()
+                .map(|(s_0, s_1)| Ok((bool_vec_to_usize(s_0)?, bool_vec_to_usize(s_1)?)))
+                .collect::<Result<Vec<_>, OteError>>()?,
+        );
+        ot_primitive::send(group, keys, &inputs, rng)
+    }
+
+    /**
+     * Pick a fresh random t for the chunk and mask its columns t^i and t^i xor r
+     * with the next bits of the seeds' PRG streams.
+     * Returns the rows t_j, which are needed for decoding, and the masked columns for the sender.
+     */
+    pub(crate) fn mask_columns(&mut self, choice_bits: &[bool]) -> (SecretRows, MessagePairs) {
+        let m = choice_bits.len();
+        let k = self.prgs.len();
+        let t = Zeroizing::new(
+            (0..m)
+                .map(|_| random_boolvec_len(k, self.rng.as_mut()))
+                .collect::<Vec<Vec<bool>>>(),
+        );
+        // Without any rows the transpose has no columns either, but the sender still expects k of them.
+        let mut columns = Zeroizing::new(transpose(&t));
+        columns.resize(k, Vec::new());
         let r_input = self
-            .rand_seeds
-            .iter()
-            .zip(transpose(&self.t))
-            .map(|((s_0, s_1), row)| {
-                let xor = xor_boolvec(&row, &self.choice_bits);
-                let t_i = xor_boolvec(&row, &pseudo_random_gen(s_0, row.len()));
-                let t_i_xor = xor_boolvec(&xor, &pseudo_random_gen(s_1, xor.len()));
+            .prgs
+            .iter_mut()
+            .zip(columns.iter())
+            .map(|((g_0, g_1), row)| {
+                let xor = Zeroizing::new(xor_boolvec(row, choice_bits));
+                let t_i = xor_boolvec(row, &Zeroizing::new(g_0.next_bits(row.len())));
+                let t_i_xor = xor_boolvec(&xor, &Zeroizing::new(g_1.next_bits(xor.len())));
                 (t_i, t_i_xor)
             })
             .collect::<Vec<_>>();
-        return (ot_primitive::send(group, keys, &inputs), r_input);
+        (t, r_input)
     }
 }
 
 impl Sender {
-    fn initialize(k: usize, messages: Vec<(Vec<bool>, Vec<bool>)>) -> Sender {
-        let s = random_boolvec_len(k);
-        return Sender { s, messages };
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(k: usize, rng: &mut R) -> Result<Sender, OteError> {
+        check_seed_length(k)?;
+        let s = Zeroizing::new(random_boolvec_len(k, rng));
+        Ok(Sender {
+            s,
+            prgs: Vec::new(),
+            offset: 0,
+        })
     }
 
-    fn receive_ot_primitive(
-        &self,
-        receiver: &Receiver,
-        group: &SafePrimeGroup,
-    ) -> Vec<(Vec<bool>, Vec<bool>)> {
+    // The sender plays the receiver in the base OTs, with s as its choice bits.
+    pub(crate) fn commit_ot_primitive<R: CryptoRng + RngCore>(&self, group: &SafePrimeGroup, rng: &mut R) -> Result<ot_primitive::Commitment, OteError> {
+        let sk = ot_primitive::create_secret_keys(group, self.s.len(), rng);
+        let keys = ot_primitive::commit_choice(group, &sk, &self.s, rng)?;
+        Ok((sk, keys))
+    }
+
+    pub(crate) fn receive_ot_primitive(&mut self, group: &SafePrimeGroup, sk: &[USIZE], seeds_ot: &ot_primitive::OTParams) -> Result<(), OteError> {
         let k = self.s.len();
-        let m = self.messages.len();
-        let sk = ot_primitive::create_secret_keys(group, k);
-        let keys = ot_primitive::commit_choice(group, &sk, &self.s);
-        let (seeds_ot, otp) = receiver.send_ot_primitive(group, &keys);
-        let seeds = ot_primitive::receive_(group, &seeds_ot, &sk, &self.s);
-        let values = self
-            .s
+        let seeds = Zeroizing::new(ot_primitive::receive_(group, seeds_ot, sk, &self.s)?);
+        self.prgs = seeds
             .iter()
-            .zip(seeds)
-            .zip(otp)
-            .map(|((&s, seed), (x_0, x_1))| {
-                if s {
-                    xor_boolvec(
-                        &x_1,
-                        &pseudo_random_gen(&usize_to_bool_vec_len(&seed, k), m),
-                    )
-                } else {
-                    xor_boolvec(
-                        &x_0,
-                        &pseudo_random_gen(&usize_to_bool_vec_len(&seed, k), m),
-                    )
-                }
-            })
-            .collect::<Vec<_>>();
-        let q = transpose(&values);
+            .map(|seed| Prg::new(&Zeroizing::new(usize_to_bool_vec_len(seed, k))))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
 
-        self.messages
+    pub(crate) fn s(&self) -> &[bool] {
+        &self.s
+    }
+
+    pub(crate) fn receive_columns(&mut self, otp: MessagePairs, messages: &[(Vec<bool>, Vec<bool>)]) -> MessagePairs {
+        let (offset, q) = self.next_rows(otp, messages.len());
+        self.mask_messages(offset, &q, messages)
+    }
+
+    // Mask both messages of every pair with the hashes of q_j and q_j xor s, given the rows q_j of a chunk.
+    pub(crate) fn mask_messages(&self, offset: usize, q: &[Vec<bool>], messages: &[(Vec<bool>, Vec<bool>)]) -> MessagePairs {
+        messages
             .iter()
             .zip(q)
             .enumerate()
             .map(|(j, ((xj_0, xj_1), q_j))| {
-                let yj_0 = xor_boolvec(xj_0, &hash_bits(&int_to_bool_vec(j), &q_j));
-                let yj_1 = xor_boolvec(
-                    xj_1,
-                    &hash_bits(&int_to_bool_vec(j), &xor_boolvec(&self.s, &q_j)),
-                );
-                (yj_0, yj_1)
+                let pad_0 = Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), q_j, xj_0.len()));
+                let q_j_xor_s = Zeroizing::new(xor_boolvec(&self.s, q_j));
+                let pad_1 = Zeroizing::new(hash_bits(&int_to_bool_vec(offset + j), &q_j_xor_s, xj_1.len()));
+                (xor_boolvec(xj_0, &pad_0), xor_boolvec(xj_1, &pad_1))
             })
             .collect::<Vec<_>>()
     }
+
+    // Unmask the columns of a chunk of m OTs and return its rows q_j, together with the hash index of the first one.
+    pub(crate) fn next_rows(&mut self, otp: MessagePairs, m: usize) -> (usize, SecretRows) {
+        let offset = self.offset;
+        self.offset += m;
+        let values = Zeroizing::new(
+            self.s
+                .iter()
+                .zip(self.prgs.iter_mut())
+                .zip(otp)
+                .map(|((&s, prg), (x_0, x_1))| {
+                    let g = Zeroizing::new(prg.next_bits(m));
+                    xor_boolvec(&select_boolvec(&x_0, &x_1, ct_choice(s)), &g)
+                })
+                .collect::<Vec<_>>(),
+        );
+        (offset, Zeroizing::new(transpose(&values)))
+    }
 }
 
-pub fn ote(
+pub fn ote<R: CryptoRng + RngCore>(
     messages: Vec<(Vec<bool>, Vec<bool>)>,
     choice: Vec<bool>,
     k: usize,
     group: &SafePrimeGroup,
-) -> Vec<Vec<bool>> {
-    let m = messages.len();
-    let sender = Sender::initialize(k, messages);
-    let receiver = Receiver::initialize(k, m, choice);
+    rng: &mut R,
+) -> Result<Vec<Vec<bool>>, OteError> {
+    check_inputs(&messages, &choice)?;
+    let mut sender = Sender::initialize(k, rng)?;
+    let mut receiver = Receiver::initialize(k, rng)?;
 
-    receiver.do_protocol(&sender, group)
+    receiver.setup(&mut sender, group, rng)?;
+    Ok(receiver.extend(&mut sender, &messages, &choice))
 }
 
-pub fn run_tests() {
-    let group = &ot_primitive::make_group();
-    println!("Testing IKNP... ");
-    for m in [1, 10000] {
-        for k in [128, 256] {
-            println!("Running protocol with m={} and k={} .", m, k);
-            for _ in 0..1 {
-                let messages = (0..m)
-                    .into_iter()
-                    .map(|x| {
-                        (
-                            int_to_boolvec_len(x, OUTPUT_SIZE),
-                            int_to_boolvec_len(x + 1, OUTPUT_SIZE),
-                        )
-                    })
-                    .collect::<Vec<_>>();
-                let choice_bits = (0..m).into_iter().map(|_| random()).collect::<Vec<_>>();
-                let prediction = ote(messages.clone(), choice_bits.clone(), k, group);
-                let correct = messages
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, m)| if choice_bits[i] { m.1 } else { m.0 })
-                    .collect::<Vec<_>>();
-                prediction
-                    .into_iter()
-                    .zip(correct)
-                    .for_each(|(p, c)| assert_eq!(p, c))
-            }
-        }
+/**
+ * Runs the extension chunk by chunk, so memory stays constant no matter the total number of OTs.
+ * `chunks` yields the messages and choice bits of one chunk at a time (e.g. DEFAULT_CHUNK_SIZE OTs)
+ * and `on_output` is handed the receiver's output for each chunk in order.
+ * The base OTs run once, and every chunk draws a fresh t and continues the seeds' PRG streams.
+ */
+pub fn ote_streaming<I, F, R>(chunks: I, k: usize, group: &SafePrimeGroup, rng: &mut R, mut on_output: F) -> Result<(), OteError>
+where
+    I: IntoIterator<Item = (MessagePairs, Vec<bool>)>,
+    F: FnMut(Vec<Vec<bool>>),
+    R: CryptoRng + RngCore,
+{
+    let mut sender = Sender::initialize(k, rng)?;
+    let mut receiver = Receiver::initialize(k, rng)?;
+
+    receiver.setup(&mut sender, group, rng)?;
+    for (messages, choice) in chunks {
+        check_inputs(&messages, &choice)?;
+        on_output(receiver.extend(&mut sender, &messages, &choice));
     }
-    println!("Everything worked")
+    Ok(())
 }
@@ -0,0 +1,56 @@
+use crate::common::check_seed_length;
+use crate::error::OteError;
+
+// Smallest statistical security parameter that is accepted.
+pub const MIN_STATISTICAL: usize = 40;
+
+/**
+ * Computational and statistical security parameters of the extensions.
+ * k is the number of base OTs and the length of the PRG seeds, so it is 128, 192 or 256.
+ * The statistical parameter bounds the failure probability of checks by 2^-statistical.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityParams {
+    k: usize,
+    statistical: usize,
+}
+
+impl SecurityParams {
+    /**
+     * Parameters for a target security level in bits: k is the level itself and the statistical
+     * parameter scales the usual 40 bits at level 128 with the level.
+     */
+    pub fn from_level(level: usize) -> Result<SecurityParams, OteError> {
+        // k must be valid first, so the statistical parameter below cannot overflow.
+        check_seed_length(level)?;
+        SecurityParams::new(level, level * MIN_STATISTICAL / 128)
+    }
+
+    pub fn new(k: usize, statistical: usize) -> Result<SecurityParams, OteError> {
+        check_seed_length(k)?;
+        if statistical < MIN_STATISTICAL || statistical > k {
+            return Err(OteError::BadParameter(format!(
+                "statistical security parameter must be between {} and k = {}, not {}",
+                MIN_STATISTICAL, k, statistical
+            )));
+        }
+        Ok(SecurityParams { k, statistical })
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn statistical(&self) -> usize {
+        self.statistical
+    }
+}
+
+impl Default for SecurityParams {
+    fn default() -> Self {
+        SecurityParams {
+            k: 128,
+            statistical: MIN_STATISTICAL,
+        }
+    }
+}
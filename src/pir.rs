@@ -0,0 +1,140 @@
+// Symmetric private information retrieval from 1-out-of-N OT: the client fetches row i of a table
+// held by the server, the server learns nothing about i and the client nothing about other rows.
+//
+// Every row is prefixed with its length as 8 bytes and padded with zeros to the longest row, so
+// all rows of the OT have the same length and the client learns only the longest row's length.
+// Each lookup is one 1-out-of-N OT of `ot_n`, so log N 1-out-of-2 OTs on the session.
+// `pir_server` and `pir_client` run the same over a channel, with the OTs extended there.
+
+use rand::{CryptoRng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtConfig, OtExtReceiver, OtExtSender};
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_n::{choice_of, mask_tables, ot_n_shared, unmask};
+use crate::ot_primitive::SafePrimeGroup;
+use crate::session::OtExtSession;
+
+// Bytes of the length in front of every row.
+const LENGTH_BYTES: usize = 8;
+
+// Most lookups of one client over a channel.
+pub const MAX_LOOKUPS: usize = 1 << 16;
+
+// The row as bits, length first and padded to `len` bytes.
+fn encode(row: &[u8], len: usize) -> Vec<bool> {
+    let mut bytes = Vec::with_capacity(LENGTH_BYTES + len);
+    bytes.extend_from_slice(&(row.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(row);
+    bytes.resize(LENGTH_BYTES + len, 0);
+    byte_vec_to_bool_vec(&bytes)
+}
+
+// Every row encoded to the longest row's length, which is returned as well.
+fn encode_table(table: &[Vec<u8>]) -> Result<(SecretRows, usize), OteError> {
+    if table.is_empty() {
+        return Err(OteError::BadParameter("the table has no rows".to_owned()));
+    }
+    let len = table.iter().map(Vec::len).max().unwrap_or(0);
+    Ok((Zeroizing::new(table.iter().map(|row| encode(row, len)).collect()), len))
+}
+
+fn check_indices(indices: &[usize], rows: usize) -> Result<(), OteError> {
+    match indices.iter().find(|&&i| i >= rows) {
+        Some(&i) => Err(OteError::BadParameter(format!("row {} is not in a table of {} rows", i, rows))),
+        None => Ok(()),
+    }
+}
+
+fn decode(bits: &[bool]) -> Result<Vec<u8>, OteError> {
+    let bytes = Zeroizing::new(bool_vec_to_byte_vec(bits));
+    let len = usize::try_from(be_u64(&bytes)).ok().filter(|&len| len <= bytes.len() - LENGTH_BYTES);
+    match len {
+        Some(len) => Ok(bytes[LENGTH_BYTES..LENGTH_BYTES + len].to_vec()),
+        None => Err(OteError::MalformedMessage("retrieved row has a bad length".to_owned())),
+    }
+}
+
+/**
+ * Retrieves row indices[i] of the server's table for every i, with one 1-out-of-N OT each on the
+ * session, where the server is the session's sender. Rows may have any length, including zero.
+ */
+pub fn retrieve<R: CryptoRng + RngCore>(
+    session: &mut OtExtSession,
+    table: &[Vec<u8>],
+    indices: &[usize],
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let (encoded, _) = encode_table(table)?;
+    check_indices(indices, table.len())?;
+    let rows = Zeroizing::new(ot_n_shared(session, &encoded, indices, rng)?);
+    rows.iter().map(|row| decode(row)).collect()
+}
+
+/**
+ * Server of `retrieve` over a channel, as the sender of the OT extension; returns the number of
+ * rows the client fetched. Messages: "pir shape" with the number of rows and the longest row's
+ * length, "pir lookups" with the client's number of lookups, then the OT extension for the keys
+ * and "pir rows" with the table masked once per lookup.
+ */
+pub async fn pir_server<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    table: &[Vec<u8>],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<usize, OteError> {
+    let (encoded, len) = encode_table(table)?;
+    let shape = [(table.len() as u64).to_be_bytes(), (len as u64).to_be_bytes()].concat();
+    chan.label("pir shape").send_bytes(&shape).await?;
+    let lookups = chan.label("pir lookups").recv_bytes().await?;
+    let count = Some(lookups)
+        .filter(|bytes| bytes.len() == 8)
+        .and_then(|bytes| usize::try_from(be_u64(&bytes)).ok())
+        .filter(|&count| count <= MAX_LOOKUPS)
+        .ok_or_else(|| OteError::MalformedMessage(format!("the client asks for more than {} rows", MAX_LOOKUPS)))?;
+    let tables = vec![&encoded[..]; count];
+    let (keys, masked) = mask_tables(&tables, &vec![8 * (LENGTH_BYTES + len); count], config.k(), rng);
+    // A table of one row needs no OTs.
+    if !keys.is_empty() {
+        OtExtSender::new(config, group.clone()).send(chan, keys.to_vec(), rng).await?;
+    }
+    chan.label("pir rows").send_bit_rows(&masked.concat()).await?;
+    Ok(count)
+}
+
+// Client of `retrieve` over a channel: returns row indices[i] of the server's table for every i.
+pub async fn pir_client<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    indices: &[usize],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    if indices.len() > MAX_LOOKUPS {
+        return Err(OteError::BadParameter(format!("{} lookups but at most {} are answered", indices.len(), MAX_LOOKUPS)));
+    }
+    let shape = chan.label("pir shape").recv_bytes().await?;
+    let read = |bytes: &[u8]| usize::try_from(be_u64(bytes)).ok();
+    let (n, bits) = match shape.len() {
+        16 => (read(&shape[..8]), read(&shape[8..]).and_then(|len| len.checked_add(LENGTH_BYTES)?.checked_mul(8))),
+        _ => (None, None),
+    };
+    let (Some(n), Some(bits)) = (n.filter(|&n| n > 0), bits) else {
+        return Err(OteError::MalformedMessage("the table's shape is not a number of rows and a length".to_owned()));
+    };
+    check_indices(indices, n)?;
+    chan.label("pir lookups").send_bytes(&(indices.len() as u64).to_be_bytes()).await?;
+    let choice = choice_of(&vec![n; indices.len()], indices);
+    let received = match choice.is_empty() {
+        true => Zeroizing::new(vec![]),
+        false => Zeroizing::new(OtExtReceiver::new(config, group.clone()).receive(chan, choice.to_vec(), rng).await?),
+    };
+    let rows = indices.len().checked_mul(n).ok_or_else(|| OteError::MalformedMessage(format!("a table of {} rows is too large", n)))?;
+    // With no rows the sender cannot tell their length.
+    let masked = chan.label("pir rows").recv_bit_rows(rows, (rows > 0).then_some(bits)).await?;
+    let rows = Zeroizing::new(unmask(&masked.chunks(n).collect::<Vec<_>>(), indices, &received));
+    rows.iter().map(|row| decode(row)).collect()
+}
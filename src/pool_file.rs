@@ -0,0 +1,252 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use sha3::{Digest, Sha3_256};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::precompute::{RotReceiverPool, RotSenderPool};
+use crate::session::Protocol;
+
+/**
+ * File format for precomputed OT pools, all integers big-endian:
+ *
+ * magic "OTEPOOL\0" | version u16 | protocol u8 | role u8 | k u32 | m u64 | first index u64 | session id [u8; 16]
+ * body: sender = s, then m rows q_j; receiver = m choice bits, then m rows t_j
+ * mac [u8; 32] = SHA3-256(key length u64 | key | everything before the mac)
+ *
+ * Bit vectors are packed with `bool_vec_to_byte_vec`. Only entries that have not been consumed yet are written.
+ *
+ * Every file is single-use, since reusing a random OT breaks its security: saving consumes the
+ * written entries of the pool in memory, a file is never overwritten, and loading removes it.
+ * The rows are in the clear, so on Unix the file can only be read by its owner (mode 0o600).
+ */
+const MAGIC: &[u8; 8] = b"OTEPOOL\0";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 8 + 2 + 1 + 1 + 4 + 8 + 8 + 16;
+const MAC_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Sender,
+    Receiver,
+}
+
+struct Header {
+    protocol: Protocol,
+    role: Role,
+    k: usize,
+    m: usize,
+    first_index: usize,
+    session_id: [u8; 16],
+}
+
+// SHA3 is not subject to length extension, so prefixing the key gives a MAC.
+fn mac(key: &[u8], data: &[u8]) -> [u8; MAC_LEN] {
+    let mut hasher = Sha3_256::new();
+    hasher.update((key.len() as u64).to_be_bytes());
+    hasher.update(key);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn write_header(header: &Header) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    bytes.push(match header.protocol {
+        Protocol::Iknp => 0,
+        Protocol::Alsz => 1,
+    });
+    bytes.push(match header.role {
+        Role::Sender => 0,
+        Role::Receiver => 1,
+    });
+    bytes.extend_from_slice(&(header.k as u32).to_be_bytes());
+    bytes.extend_from_slice(&(header.m as u64).to_be_bytes());
+    bytes.extend_from_slice(&(header.first_index as u64).to_be_bytes());
+    bytes.extend_from_slice(&header.session_id);
+    bytes
+}
+
+fn invalid(msg: &str) -> OteError {
+    OteError::InvalidFile(msg.to_owned())
+}
+
+fn read_header(bytes: &[u8]) -> Result<Header, OteError> {
+    if bytes.len() < HEADER_LEN + MAC_LEN {
+        return Err(invalid("file is truncated"));
+    }
+    if &bytes[0..8] != MAGIC {
+        return Err(invalid("not an OT pool file"));
+    }
+    let version = u16::from_be_bytes([bytes[8], bytes[9]]);
+    if version != VERSION {
+        return Err(OteError::InvalidFile(format!("unsupported version {}", version)));
+    }
+    let protocol = match bytes[10] {
+        0 => Protocol::Iknp,
+        1 => Protocol::Alsz,
+        _ => return Err(invalid("unknown protocol")),
+    };
+    let role = match bytes[11] {
+        0 => Role::Sender,
+        1 => Role::Receiver,
+        _ => return Err(invalid("unknown party role")),
+    };
+    let k = u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]) as usize;
+    let m = be_u64(&bytes[16..24]);
+    let first_index = be_u64(&bytes[24..32]);
+    let m = usize::try_from(m).map_err(|_| invalid("too many entries"))?;
+    let first_index = usize::try_from(first_index).map_err(|_| invalid("first index out of range"))?;
+    Ok(Header {
+        protocol,
+        role,
+        k,
+        m,
+        first_index,
+        session_id: to_array(bytes[32..48].to_vec())?,
+    })
+}
+
+/**
+ * Checks length and MAC of a file and that it belongs to `role`.
+ * Returns the header and the body.
+ */
+fn open<'a>(bytes: &'a [u8], key: &[u8], role: Role) -> Result<(Header, &'a [u8]), OteError> {
+    let header = read_header(bytes)?;
+    let row_bytes = usize::div_ceil(header.k, 8);
+    let first = match header.role {
+        Role::Sender => row_bytes,
+        Role::Receiver => usize::div_ceil(header.m, 8),
+    };
+    let body_len = header
+        .m
+        .checked_mul(row_bytes)
+        .and_then(|x| x.checked_add(first))
+        .ok_or_else(|| invalid("file is too large"))?;
+    if bytes.len() != HEADER_LEN + body_len + MAC_LEN {
+        return Err(invalid("file is truncated or has trailing data"));
+    }
+    let (data, tag) = bytes.split_at(HEADER_LEN + body_len);
+    let expected = mac(key, data);
+    if expected.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b)) != 0 {
+        return Err(OteError::IntegrityCheckFailed);
+    }
+    if header.role != role {
+        return Err(OteError::WrongRole {
+            expected: role,
+            found: header.role,
+        });
+    }
+    Ok((header, &data[HEADER_LEN..]))
+}
+
+fn seal(bytes: &mut Vec<u8>, key: &[u8]) {
+    let tag = mac(key, bytes);
+    bytes.extend_from_slice(&tag);
+}
+
+// Header, then room for the rest of the file, so the buffer of secret bits never has to grow and leave a copy behind.
+fn start_file(header: &Header, first: usize) -> Zeroizing<Vec<u8>> {
+    let mut bytes = write_header(header);
+    bytes.reserve_exact(first + header.m * usize::div_ceil(header.k, 8) + MAC_LEN);
+    Zeroizing::new(bytes)
+}
+
+fn write_bits(bytes: &mut Vec<u8>, bits: &[bool]) {
+    bytes.extend_from_slice(&Zeroizing::new(bool_vec_to_byte_vec(bits)));
+}
+
+fn read_rows(body: &[u8], m: usize, k: usize) -> SecretRows {
+    let row_bytes = usize::div_ceil(k, 8);
+    Zeroizing::new(
+        (0..m)
+            .map(|j| byte_vec_to_bool_vec_len(&body[j * row_bytes..(j + 1) * row_bytes], k))
+            .collect(),
+    )
+}
+
+// Writes a new file, which only its owner may read.
+fn write_new(path: &Path, bytes: &[u8]) -> Result<(), OteError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)?;
+    Ok(())
+}
+
+// Saves the unused entries to a new file at `path` and marks them as consumed in the pool.
+pub fn save_sender_pool(pool: &mut RotSenderPool, path: impl AsRef<Path>, key: &[u8]) -> Result<(), OteError> {
+    let q = &pool.q[pool.consumed..];
+    let header = Header {
+        protocol: pool.protocol,
+        role: Role::Sender,
+        k: pool.s.len(),
+        m: q.len(),
+        first_index: pool.first_index + pool.consumed,
+        session_id: pool.session_id,
+    };
+    let mut bytes = start_file(&header, usize::div_ceil(header.k, 8));
+    write_bits(&mut bytes, &pool.s);
+    q.iter().for_each(|q_j| write_bits(&mut bytes, q_j));
+    seal(&mut bytes, key);
+    write_new(path.as_ref(), &bytes)?;
+    pool.consumed = pool.q.len();
+    Ok(())
+}
+
+// Loads a pool saved by `save_sender_pool` and removes the file once it has been verified.
+pub fn load_sender_pool(path: impl AsRef<Path>, key: &[u8]) -> Result<RotSenderPool, OteError> {
+    let bytes = Zeroizing::new(fs::read(&path)?);
+    let (header, body) = open(&bytes, key, Role::Sender)?;
+    fs::remove_file(path)?;
+    let row_bytes = usize::div_ceil(header.k, 8);
+    Ok(RotSenderPool {
+        protocol: header.protocol,
+        session_id: header.session_id,
+        s: Zeroizing::new(byte_vec_to_bool_vec_len(&body[..row_bytes], header.k)),
+        q: read_rows(&body[row_bytes..], header.m, header.k),
+        first_index: header.first_index,
+        consumed: 0,
+    })
+}
+
+// Saves the unused entries to a new file at `path` and marks them as consumed in the pool.
+pub fn save_receiver_pool(pool: &mut RotReceiverPool, path: impl AsRef<Path>, key: &[u8]) -> Result<(), OteError> {
+    let t = &pool.t[pool.consumed..];
+    let choice_bits = &pool.choice_bits[pool.consumed..];
+    let header = Header {
+        protocol: pool.protocol,
+        role: Role::Receiver,
+        k: t.first().map_or(0, |t_j| t_j.len()),
+        m: t.len(),
+        first_index: pool.first_index + pool.consumed,
+        session_id: pool.session_id,
+    };
+    let mut bytes = start_file(&header, usize::div_ceil(header.m, 8));
+    write_bits(&mut bytes, choice_bits);
+    t.iter().for_each(|t_j| write_bits(&mut bytes, t_j));
+    seal(&mut bytes, key);
+    write_new(path.as_ref(), &bytes)?;
+    pool.consumed = pool.t.len();
+    Ok(())
+}
+
+// Loads a pool saved by `save_receiver_pool` and removes the file once it has been verified.
+pub fn load_receiver_pool(path: impl AsRef<Path>, key: &[u8]) -> Result<RotReceiverPool, OteError> {
+    let bytes = Zeroizing::new(fs::read(&path)?);
+    let (header, body) = open(&bytes, key, Role::Receiver)?;
+    fs::remove_file(path)?;
+    let choice_bytes = usize::div_ceil(header.m, 8);
+    Ok(RotReceiverPool {
+        protocol: header.protocol,
+        session_id: header.session_id,
+        choice_bits: Zeroizing::new(byte_vec_to_bool_vec_len(&body[..choice_bytes], header.m)),
+        t: read_rows(&body[choice_bytes..], header.m, header.k),
+        first_index: header.first_index,
+        consumed: 0,
+    })
+}
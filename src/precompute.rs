@@ -0,0 +1,240 @@
+use std::ops::Range;
+
+use rand::{CryptoRng, RngCore};
+use zeroize::ZeroizeOnDrop;
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::SafePrimeGroup;
+use crate::session::{OtExtSession, Protocol};
+
+/**
+ * Sender's half of a pool of precomputed random OTs.
+ * Entry i holds the row q_i, whose random messages are H(j, q_i) and H(j, q_i xor s)
+ * for the hash index j = first_index + i.
+ */
+pub struct RotSenderPool {
+    pub(crate) protocol: Protocol,
+    pub(crate) session_id: [u8; 16],
+    pub(crate) s: SecretBits,
+    pub(crate) q: SecretRows,
+    pub(crate) first_index: usize,
+    // Entries before this one have already been used.
+    pub(crate) consumed: usize,
+}
+
+// s and the q_j are `Zeroizing`, so they are wiped when the pool is dropped.
+impl ZeroizeOnDrop for RotSenderPool {}
+
+/**
+ * Receiver's half of the pool.
+ * Entry i holds the random choice bit c_i and the row t_i, which gives H(j, t_i) = r_{c_i}.
+ */
+pub struct RotReceiverPool {
+    pub(crate) protocol: Protocol,
+    pub(crate) session_id: [u8; 16],
+    pub(crate) choice_bits: SecretBits,
+    pub(crate) t: SecretRows,
+    pub(crate) first_index: usize,
+    pub(crate) consumed: usize,
+}
+
+// The choice bits and the t_j are `Zeroizing`, so they are wiped when the pool is dropped.
+impl ZeroizeOnDrop for RotReceiverPool {}
+
+// Reserve the next n entries of a pool of the given size.
+fn take(consumed: &mut usize, size: usize, n: usize) -> Result<Range<usize>, OteError> {
+    let available = size - *consumed;
+    if n > available {
+        return Err(OteError::PoolExhausted { requested: n, available });
+    }
+    let range = *consumed..*consumed + n;
+    *consumed += n;
+    Ok(range)
+}
+
+/**
+ * Offline phase: extend m random OTs on the session, without any messages.
+ * The choice bits are picked at random and the outputs are only derived when the entries are used.
+ */
+pub fn precompute<R: CryptoRng + RngCore>(session: &mut OtExtSession, m: usize, rng: &mut R) -> Result<(RotSenderPool, RotReceiverPool), OteError> {
+    let choice_bits = Zeroizing::new(random_boolvec_len(m, rng));
+    let (first_index, q, t) = session.extend_rows(&choice_bits)?;
+    let sender = RotSenderPool {
+        protocol: session.protocol(),
+        session_id: session.id(),
+        s: session.sender_s(),
+        q,
+        first_index,
+        consumed: 0,
+    };
+    let receiver = RotReceiverPool {
+        protocol: session.protocol(),
+        session_id: session.id(),
+        choice_bits,
+        t,
+        first_index,
+        consumed: 0,
+    };
+    Ok((sender, receiver))
+}
+
+impl RotSenderPool {
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn session_id(&self) -> [u8; 16] {
+        self.session_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.q.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.q.is_empty()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.q.len() - self.consumed
+    }
+
+    // Both random messages (r_0, r_1) of entry i, stretched to len bits.
+    fn random_messages(&self, i: usize, len: usize) -> (SecretBits, SecretBits) {
+        let j = int_to_bool_vec(self.first_index + i);
+        let q_i_xor_s = Zeroizing::new(xor_boolvec(&self.q[i], &self.s));
+        (
+            Zeroizing::new(hash_bits(&j, &self.q[i], len)),
+            Zeroizing::new(hash_bits(&j, &q_i_xor_s, len)),
+        )
+    }
+
+    // Consumes the next n entries as they are and returns their random messages of len bits.
+    pub fn take_random(&mut self, n: usize, len: usize) -> Result<Vec<(SecretBits, SecretBits)>, OteError> {
+        let range = take(&mut self.consumed, self.q.len(), n)?;
+        Ok(range.map(|i| self.random_messages(i, len)).collect())
+    }
+
+    /**
+     * Online step of the sender. Given the receiver's corrections e_i = b_i xor c_i it consumes
+     * the next entries and returns (x_0 xor r_{e_i}, x_1 xor r_{1 xor e_i}) for every message pair.
+     */
+    pub fn derandomize(
+        &mut self,
+        corrections: &[bool],
+        messages: &[(Vec<bool>, Vec<bool>)],
+    ) -> Result<MessagePairs, OteError> {
+        if corrections.len() != messages.len() {
+            return Err(OteError::MalformedMessage(format!(
+                "got {} corrections for {} messages",
+                corrections.len(),
+                messages.len()
+            )));
+        }
+        let len = check_message_lengths(messages)?;
+        let range = take(&mut self.consumed, self.q.len(), messages.len())?;
+        Ok(range
+            .zip(corrections)
+            .zip(messages)
+            .map(|((i, &e), (x_0, x_1))| {
+                let (r_0, r_1) = self.random_messages(i, len);
+                // e is sent in the clear, so branching on it reveals nothing.
+                let (r_e, r_not_e) = if e { (r_1, r_0) } else { (r_0, r_1) };
+                (xor_boolvec(x_0, &r_e), xor_boolvec(x_1, &r_not_e))
+            })
+            .collect())
+    }
+}
+
+impl RotReceiverPool {
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn session_id(&self) -> [u8; 16] {
+        self.session_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.t.is_empty()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.t.len() - self.consumed
+    }
+
+    /**
+     * Online step of the receiver, first half. Consumes the next entries and returns the
+     * corrections e_i = b_i xor c_i for the sender, plus the pads r_{c_i} of len bits needed by `unmask`.
+     */
+    pub fn corrections(&mut self, choice: &[bool], len: usize) -> Result<(Vec<bool>, SecretRows), OteError> {
+        let range = take(&mut self.consumed, self.t.len(), choice.len())?;
+        let (corrections, pads) = range
+            .zip(choice)
+            .map(|(i, &b)| {
+                let pad = hash_bits(&int_to_bool_vec(self.first_index + i), &self.t[i], len);
+                (b ^ self.choice_bits[i], pad)
+            })
+            .unzip();
+        Ok((corrections, Zeroizing::new(pads)))
+    }
+
+    // Consumes the next n entries as they are and returns their choice bits c_i and messages r_{c_i} of len bits.
+    pub fn take_random(&mut self, n: usize, len: usize) -> Result<(SecretBits, Vec<SecretBits>), OteError> {
+        let range = take(&mut self.consumed, self.t.len(), n)?;
+        let choice = Zeroizing::new(self.choice_bits[range.clone()].to_vec());
+        let messages = range.map(|i| Zeroizing::new(hash_bits(&int_to_bool_vec(self.first_index + i), &self.t[i], len))).collect();
+        Ok((choice, messages))
+    }
+}
+
+// Online step of the receiver, second half: y_{b_i} xor r_{c_i} = x_{b_i}.
+pub fn unmask(y: &[(Vec<bool>, Vec<bool>)], choice: &[bool], pads: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    y.iter()
+        .zip(choice)
+        .zip(pads)
+        .map(|(((y_0, y_1), &b), pad)| xor_boolvec(&select_boolvec(y_0, y_1, ct_choice(b)), pad))
+        .collect()
+}
+
+/**
+ * Both halves of a pool, for running the online phase in one process like `ote`.
+ */
+pub struct Precomputed {
+    pub sender: RotSenderPool,
+    pub receiver: RotReceiverPool,
+}
+
+impl ZeroizeOnDrop for Precomputed {}
+
+impl Precomputed {
+    pub fn new<R: CryptoRng + RngCore>(protocol: Protocol, m: usize, k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Precomputed, OteError> {
+        let mut session = OtExtSession::new(protocol, k, group, rng)?;
+        let (sender, receiver) = precompute(&mut session, m, rng)?;
+        Ok(Precomputed { sender, receiver })
+    }
+
+    /**
+     * Turn the next choice.len() random OTs into chosen-message OTs with Beaver's trick.
+     * The receiver sends one bit per OT and the sender two masked messages.
+     */
+    pub fn derandomize(
+        &mut self,
+        choice: &[bool],
+        messages: &[(Vec<bool>, Vec<bool>)],
+    ) -> Result<Vec<Vec<bool>>, OteError> {
+        let len = check_inputs(messages, choice)?;
+        let (corrections, pads) = self.receiver.corrections(choice, len)?;
+        let y = self.sender.derandomize(&corrections, messages)?;
+        Ok(unmask(&y, choice, &pads))
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.receiver.remaining()
+    }
+}
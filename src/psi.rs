@@ -0,0 +1,433 @@
+// Private set intersection with the batched OPRF of Kolesnikov, Kumaresan, Rosulek and Trieu,
+// "Efficient Batched Oblivious PRF with Applications to Private Set Intersection" (KKRT16).
+//
+// The OPRF is the ALSZ extension with the choice bit r_j replaced by a w-bit pseudorandom
+// codeword C(r_j). The sender's rows become q_j = t_j xor (C(r_j) and s), so it can evaluate
+// F(j, x) = H(j, q_j xor (C(x) and s)) on any x, which equals the receiver's H(j, t_j) exactly
+// if x = r_j. The receiver cuckoo hashes its items into bins and learns F for the item of every
+// bin. The sender hashes each of its items into all bins it could be in, sends the OPRF values,
+// and the receiver looks them up in a hash table of its own values.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng, RngCore};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_async::{blocking, recv_keys, recv_ot_params, send_keys, send_ot_params};
+use crate::ot_primitive::{self, bool_vec_to_usize, usize_to_bool_vec_len, PublicKey, SafePrimeGroup, USIZE};
+use crate::params::SecurityParams;
+
+// Number of cuckoo hash functions, so every item can sit in one of this many bins.
+pub const HASHES: usize = 3;
+// Bins per receiver item in percent. With three hash functions insertion practically never fails at this load.
+const BIN_PERCENT: usize = 127;
+// Evictions before an insertion gives up, and hash functions tried before the receiver gives up.
+const MAX_EVICTIONS: usize = 1000;
+const MAX_SEEDS: usize = 100;
+// Largest set size a party accepts from its peer, so sizes from the wire stay far from overflowing.
+pub const MAX_ITEMS: usize = 1 << 30;
+
+/**
+ * Width w of the pseudorandom code for seeds of k bits, which is also the number of base OTs.
+ * KKRT need 424 bits at k = 128 for sets of up to 2^24 items, so 4k leaves room.
+ */
+pub fn code_width(k: usize) -> usize {
+    4 * k
+}
+
+// Number of cuckoo bins for n receiver items.
+pub fn bins_for(n: usize) -> usize {
+    usize::div_ceil(n * BIN_PERCENT, 100).max(1)
+}
+
+/**
+ * Length of the OPRF values that are sent. Every one of the HASHES values per sender item is
+ * compared with every bin, so this many bits keep a false match below 2^-statistical.
+ */
+pub fn value_bits(statistical: usize, sender_items: usize, bins: usize) -> usize {
+    let comparisons = HASHES.saturating_mul(sender_items).saturating_mul(bins).max(1);
+    let bits = comparisons.checked_next_power_of_two().map_or(usize::BITS, usize::trailing_zeros);
+    statistical + bits as usize
+}
+
+// SHAKE256 of length-prefixed parts, so different splits of the same bytes hash differently.
+pub(crate) fn shake(parts: &[&[u8]], out: &mut [u8]) {
+    let mut hasher = Shake256::default();
+    for part in parts {
+        hasher.update(&(part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize_xof().read(out);
+}
+
+// Bin of an item under hash function i, for the hash functions chosen by `seed`.
+pub fn cuckoo_bin(seed: &[u8; 16], i: usize, item: &[u8], bins: usize) -> usize {
+    let mut out = [0; 8];
+    shake(&[b"OTE cuckoo", seed, &[i as u8], item], &mut out);
+    (u64::from_be_bytes(out) % bins as u64) as usize
+}
+
+// Codeword C(i || item) of width bits. The hash function is part of the input, so an item has another codeword in each of its bins.
+fn codeword(i: usize, item: &[u8], width: usize) -> Vec<bool> {
+    let mut out = Zeroizing::new(vec![0; usize::div_ceil(width, 8)]);
+    shake(&[b"OTE KKRT code", &[i as u8], item], &mut out);
+    let mut bits = byte_vec_to_bool_vec(&out);
+    bits.truncate(width);
+    bits
+}
+
+// The items of a set without repetitions, in the order they first appear.
+fn distinct(set: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let mut seen = HashSet::new();
+    set.iter().filter(|x| seen.insert(x.as_slice())).cloned().collect()
+}
+
+/**
+ * Cuckoo hash table of the receiver. Bin j holds the index of an item and the hash function
+ * that put it there, or nothing.
+ */
+pub struct CuckooTable {
+    pub seed: [u8; 16],
+    pub bins: Vec<Option<(usize, usize)>>,
+}
+
+impl CuckooTable {
+    // Inserts all items, drawing new hash functions from rng until every item fits.
+    pub fn new<R: CryptoRng + RngCore>(items: &[Vec<u8>], bins: usize, rng: &mut R) -> Result<CuckooTable, OteError> {
+        if items.len() > bins {
+            return Err(OteError::BadParameter(format!("{} items do not fit in {} bins", items.len(), bins)));
+        }
+        (0..MAX_SEEDS)
+            .find_map(|_| CuckooTable::insert_all(items, bins, rng.gen()))
+            .ok_or_else(|| OteError::BadParameter("cuckoo hashing failed, are the items distinct?".to_owned()))
+    }
+
+    fn insert_all(items: &[Vec<u8>], bins: usize, seed: [u8; 16]) -> Option<CuckooTable> {
+        let mut table = vec![None; bins];
+        for index in 0..items.len() {
+            let mut current = (index, 0);
+            let placed = (0..MAX_EVICTIONS).any(|_| {
+                let (item, i) = current;
+                match table[cuckoo_bin(&seed, i, &items[item], bins)].replace(current) {
+                    None => true,
+                    // The evicted item moves on to its next hash function.
+                    Some((evicted, i)) => {
+                        current = (evicted, (i + 1) % HASHES);
+                        false
+                    }
+                }
+            });
+            if !placed {
+                return None;
+            }
+        }
+        Some(CuckooTable { seed, bins: table })
+    }
+
+    // Codeword of the item in every bin, and a random word for the empty bins.
+    fn codewords<R: CryptoRng + RngCore>(&self, items: &[Vec<u8>], width: usize, rng: &mut R) -> SecretRows {
+        Zeroizing::new(
+            self.bins
+                .iter()
+                .map(|bin| match bin {
+                    Some((item, i)) => codeword(*i, &items[*item], width),
+                    None => random_boolvec_len(width, rng),
+                })
+                .collect(),
+        )
+    }
+}
+
+// Receiver of the OPRF, which plays the sender in the w base OTs.
+pub(crate) struct OprfReceiver {
+    seeds: Zeroizing<Vec<(Vec<bool>, Vec<bool>)>>,
+    prgs: Vec<(Prg, Prg)>,
+}
+
+impl OprfReceiver {
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(params: SecurityParams, rng: &mut R) -> Result<OprfReceiver, OteError> {
+        let k = params.k();
+        let seeds = Zeroizing::new(
+            (0..code_width(k))
+                .map(|_| (random_boolvec_len(k, rng), random_boolvec_len(k, rng)))
+                .collect::<Vec<_>>(),
+        );
+        let prgs = seeds
+            .iter()
+            .map(|(k_0, k_1)| Ok((Prg::new(k_0)?, Prg::new(k_1)?)))
+            .collect::<Result<Vec<_>, OteError>>()?;
+        Ok(OprfReceiver { seeds, prgs })
+    }
+
+    pub(crate) fn send_ot_primitive<R: CryptoRng + RngCore>(
+        &self,
+        group: &SafePrimeGroup,
+        keys: &Vec<(PublicKey, PublicKey)>,
+        rng: &mut R,
+    ) -> Result<ot_primitive::OTParams, OteError> {
+        let inputs = Zeroizing::new(
+            self.seeds
+                .iter()
+                .map(|(k_0, k_1)| Ok((bool_vec_to_usize(k_0)?, bool_vec_to_usize(k_1)?)))
+                .collect::<Result<Vec<(USIZE, USIZE)>, OteError>>()?,
+        );
+        ot_primitive::send(group, keys, &inputs, rng)
+    }
+
+    /**
+     * Expands the seeds into the columns t^i and the corrections u^i = t^i xor G(k_i^1) xor c^i,
+     * where c^i is column i of the codewords. Returns the rows t_j and the corrections.
+     */
+    pub(crate) fn encode(&mut self, codewords: &[Vec<bool>]) -> (SecretRows, Vec<Vec<bool>>) {
+        let m = codewords.len();
+        let columns = Zeroizing::new(transpose(codewords));
+        let (t, u): (Vec<_>, Vec<_>) = self
+            .prgs
+            .iter_mut()
+            .zip(columns.iter())
+            .map(|((g_0, g_1), c_i)| {
+                let t_i = g_0.next_bits(m);
+                let mask = Zeroizing::new(xor_boolvec(&t_i, &Zeroizing::new(g_1.next_bits(m))));
+                (t_i, xor_boolvec(&mask, c_i))
+            })
+            .unzip();
+        (Zeroizing::new(transpose(&Zeroizing::new(t))), u)
+    }
+}
+
+// Sender of the OPRF, which plays the receiver in the w base OTs with s as its choice bits.
+pub(crate) struct OprfSender {
+    k: usize,
+    s: SecretBits,
+    prgs: Vec<Prg>,
+}
+
+impl OprfSender {
+    pub(crate) fn initialize<R: CryptoRng + RngCore>(params: SecurityParams, rng: &mut R) -> Result<OprfSender, OteError> {
+        let k = params.k();
+        Ok(OprfSender {
+            k,
+            s: Zeroizing::new(random_boolvec_len(code_width(k), rng)),
+            prgs: Vec::new(),
+        })
+    }
+
+    pub(crate) fn commit_ot_primitive<R: CryptoRng + RngCore>(&self, group: &SafePrimeGroup, rng: &mut R) -> Result<ot_primitive::Commitment, OteError> {
+        let sk = ot_primitive::create_secret_keys(group, self.s.len(), rng);
+        let keys = ot_primitive::commit_choice(group, &sk, &self.s, rng)?;
+        Ok((sk, keys))
+    }
+
+    pub(crate) fn receive_ot_primitive(&mut self, group: &SafePrimeGroup, sk: &[USIZE], res: &ot_primitive::OTParams) -> Result<(), OteError> {
+        let seeds = Zeroizing::new(ot_primitive::receive_(group, res, sk, &self.s)?);
+        self.prgs = seeds
+            .iter()
+            .map(|x| Prg::new(&Zeroizing::new(usize_to_bool_vec_len(x, self.k))))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    // The rows q_j = t_j xor (c_j and s) of the m bins.
+    pub(crate) fn rows(&mut self, u: Vec<Vec<bool>>, m: usize) -> SecretRows {
+        let q = Zeroizing::new(
+            self.prgs
+                .iter_mut()
+                .zip(u)
+                .zip(self.s.iter())
+                .map(|((prg, u_i), &s_i)| {
+                    let g = Zeroizing::new(prg.next_bits(m));
+                    let g_xor_u = Zeroizing::new(xor_boolvec(&u_i, &g));
+                    select_boolvec(&g, &g_xor_u, ct_choice(s_i))
+                })
+                .collect::<Vec<_>>(),
+        );
+        Zeroizing::new(transpose(&q))
+    }
+
+    // F(j, x) = H(j, q_j xor (C(x) and s)), cut to len bits and packed into bytes.
+    fn eval(&self, j: usize, q_j: &[bool], codeword: &[bool], len: usize) -> Vec<u8> {
+        let masked = Zeroizing::new(
+            q_j.iter()
+                .zip(codeword)
+                .zip(self.s.iter())
+                .map(|((&q, &c), &s)| q ^ (c & s))
+                .collect::<Vec<_>>(),
+        );
+        bool_vec_to_byte_vec(&hash_bits(&int_to_bool_vec(j), &masked, len))
+    }
+
+    // The OPRF values of every item in each of its bins, shuffled so their order tells nothing.
+    fn values<R: CryptoRng + RngCore>(&self, q: &[Vec<bool>], set: &[Vec<u8>], seed: &[u8; 16], len: usize, rng: &mut R) -> Vec<Vec<u8>> {
+        let width = self.s.len();
+        let mut values = set
+            .iter()
+            .flat_map(|x| {
+                (0..HASHES).map(move |i| {
+                    let j = cuckoo_bin(seed, i, x, q.len());
+                    self.eval(j, &q[j], &codeword(i, x, width), len)
+                })
+            })
+            .collect::<Vec<_>>();
+        values.shuffle(rng);
+        values
+    }
+}
+
+/**
+ * The receiver's items whose OPRF value is among the sender's values, in the order of `items`.
+ * `t` holds the receiver's rows, so its own value for bin j is H(j, t_j).
+ */
+fn intersect(t: &[Vec<bool>], table: &CuckooTable, items: &[Vec<u8>], values: &[Vec<u8>], len: usize) -> Vec<Vec<u8>> {
+    let own = table
+        .bins
+        .iter()
+        .enumerate()
+        .filter_map(|(j, bin)| bin.map(|(item, _)| (bool_vec_to_byte_vec(&hash_bits(&int_to_bool_vec(j), &t[j], len)), item)))
+        .collect::<HashMap<_, _>>();
+    let mut found = values.iter().filter_map(|v| own.get(v).copied()).collect::<Vec<_>>();
+    found.sort_unstable();
+    found.dedup();
+    found.into_iter().map(|item| items[item].clone()).collect()
+}
+
+/**
+ * Runs both parties in one process and returns the intersection, in the order of `receiver_set`.
+ * Repeated items count once.
+ */
+pub fn psi<R: CryptoRng + RngCore>(
+    sender_set: &[Vec<u8>],
+    receiver_set: &[Vec<u8>],
+    params: SecurityParams,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let (sender_set, receiver_set) = (distinct(sender_set), distinct(receiver_set));
+    let bins = bins_for(receiver_set.len());
+    let table = CuckooTable::new(&receiver_set, bins, rng)?;
+    let mut sender = OprfSender::initialize(params, rng)?;
+    let mut receiver = OprfReceiver::initialize(params, rng)?;
+    let (sk, keys) = sender.commit_ot_primitive(group, rng)?;
+    let res = receiver.send_ot_primitive(group, &keys, rng)?;
+    sender.receive_ot_primitive(group, &sk, &res)?;
+    let (t, u) = receiver.encode(&table.codewords(&receiver_set, code_width(params.k()), rng));
+    let q = sender.rows(u, bins);
+    let len = value_bits(params.statistical(), sender_set.len(), bins);
+    let values = sender.values(&q, &sender_set, &table.seed, len, rng);
+    Ok(intersect(&t, &table, &receiver_set, &values, len))
+}
+
+fn read_u64(bytes: &[u8], what: &str) -> Result<usize, OteError> {
+    if bytes.len() < 8 {
+        return Err(OteError::MalformedMessage(format!("{} is missing", what)));
+    }
+    usize::try_from(be_u64(bytes)).map_err(|_| OteError::MalformedMessage(format!("{} is too large", what)))
+}
+
+// A count from the peer, which must be at most max.
+fn read_count(bytes: &[u8], max: usize, what: &str) -> Result<usize, OteError> {
+    match read_u64(bytes, what)? {
+        n if n > max => Err(OteError::MalformedMessage(format!("{} of {} is more than {}", what, n, max))),
+        n => Ok(n),
+    }
+}
+
+/**
+ * Sender of the PSI: learns nothing but the size of the receiver's table.
+ * Both parties must use the same `params`.
+ */
+pub async fn psi_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    set: &[Vec<u8>],
+    params: SecurityParams,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    let set = distinct(set);
+    let header = chan.label("psi params").recv_bytes().await?;
+    if header.len() != 24 {
+        return Err(OteError::MalformedMessage(format!("PSI parameters of {} bytes instead of 24", header.len())));
+    }
+    let bins = read_count(&header, bins_for(MAX_ITEMS), "number of bins")?;
+    if bins == 0 {
+        return Err(OteError::MalformedMessage("no cuckoo bins".to_owned()));
+    }
+    let seed = to_array::<u8, 16>(header[8..].to_vec())?;
+    chan.label("psi set size").send_bytes(&(set.len() as u64).to_be_bytes()).await?;
+
+    let width = code_width(params.k());
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (mut sender, sk, keys, mut rng) = blocking(move || {
+        let sender = OprfSender::initialize(params, &mut rng)?;
+        let (sk, keys) = sender.commit_ot_primitive(&g, &mut rng)?;
+        Ok((sender, sk, keys, rng))
+    })
+    .await?;
+    send_keys(chan, &keys).await?;
+    let res = recv_ot_params(chan, group, width).await?;
+    let u = chan.label("u_i").recv_bit_rows(width, Some(bins)).await?;
+    let g = group.clone();
+    let values = blocking(move || {
+        sender.receive_ot_primitive(&g, &sk, &res)?;
+        let q = sender.rows(u, bins);
+        let len = value_bits(params.statistical(), set.len(), bins);
+        Ok(sender.values(&q, &set, &seed, len, &mut rng))
+    })
+    .await?;
+    chan.label("psi values").send_bytes(&values.concat()).await
+}
+
+/**
+ * Receiver of the PSI: returns the items of `set` that the sender has as well, in the order of
+ * `set`. Repeated items count once.
+ */
+pub async fn psi_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    set: &[Vec<u8>],
+    params: SecurityParams,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<Vec<u8>>, OteError> {
+    let set = distinct(set);
+    let bins = bins_for(set.len());
+    let table = CuckooTable::new(&set, bins, rng)?;
+    let mut header = (bins as u64).to_be_bytes().to_vec();
+    header.extend_from_slice(&table.seed);
+    chan.label("psi params").send_bytes(&header).await?;
+    let sender_items = read_count(&chan.label("psi set size").recv_bytes().await?, MAX_ITEMS, "sender set size")?;
+
+    let width = code_width(params.k());
+    let keys = recv_keys(chan, group, width).await?;
+    let g = group.clone();
+    let mut rng = fork_rng(rng);
+    let (res, t, u, table, set) = blocking(move || {
+        let mut receiver = OprfReceiver::initialize(params, &mut rng)?;
+        let res = receiver.send_ot_primitive(&g, &keys, &mut rng)?;
+        let (t, u) = receiver.encode(&table.codewords(&set, width, &mut rng));
+        Ok((res, t, u, table, set))
+    })
+    .await?;
+    send_ot_params(chan, &res).await?;
+    chan.label("u_i").send_bit_rows(&u).await?;
+
+    let bytes = chan.label("psi values").recv_bytes().await?;
+    let len = value_bits(params.statistical(), sender_items, bins);
+    let value_bytes = usize::div_ceil(len, 8);
+    if Some(bytes.len()) != sender_items.checked_mul(HASHES * value_bytes) {
+        return Err(OteError::MalformedMessage(format!(
+            "expected {} values of {} bytes for {} items but got {} bytes",
+            HASHES, value_bytes, sender_items, bytes.len()
+        )));
+    }
+    blocking(move || {
+        let values = bytes.chunks_exact(value_bytes).map(|x| x.to_vec()).collect::<Vec<_>>();
+        Ok(intersect(&t, &table, &set, &values, len))
+    })
+    .await
+}
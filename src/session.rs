@@ -0,0 +1,129 @@
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::SafePrimeGroup;
+use crate::{ot_better_network, ote_IKNP};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Iknp,
+    Alsz,
+}
+
+// Hash index of the first OT, the sender's rows q_j and the receiver's rows t_j.
+type ExtendedRows = (usize, SecretRows, SecretRows);
+
+enum Parties {
+    Iknp(ote_IKNP::Sender, ote_IKNP::Receiver),
+    Alsz(ot_better_network::Sender, ot_better_network::Receiver),
+}
+
+/**
+ * Long-lived OT extension session.
+ * The k base OTs run once in `new`; afterwards `extend` can be called any number of times.
+ * Both parties keep the PRG streams seeded by the base OTs and the hash counter between calls.
+ * Neither is ever rewound, so every extension consumes fresh PRG output and fresh hash indices.
+ */
+pub struct OtExtSession {
+    protocol: Protocol,
+    // Random identifier, so material from different sessions can be told apart.
+    id: [u8; 16],
+    k: usize,
+    parties: Parties,
+    // Total number of OTs extended so far.
+    extended: usize,
+}
+
+impl OtExtSession {
+    pub fn new<R: CryptoRng + RngCore>(protocol: Protocol, k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<OtExtSession, OteError> {
+        let parties = match protocol {
+            Protocol::Iknp => {
+                let mut sender = ote_IKNP::Sender::initialize(k, rng)?;
+                let receiver = ote_IKNP::Receiver::initialize(k, rng)?;
+                receiver.setup(&mut sender, group, rng)?;
+                Parties::Iknp(sender, receiver)
+            }
+            Protocol::Alsz => {
+                let mut sender = ot_better_network::Sender::initialize(k, rng)?;
+                let receiver = ot_better_network::Receiver::initialize(k, rng)?;
+                receiver.setup(&mut sender, group, rng)?;
+                Parties::Alsz(sender, receiver)
+            }
+        };
+        Ok(OtExtSession {
+            protocol,
+            id: rng.gen(),
+            k,
+            parties,
+            extended: 0,
+        })
+    }
+
+    // Count m more OTs, failing if the hash counter would wrap around and repeat.
+    fn count(&mut self, m: usize) -> Result<(), OteError> {
+        self.extended = self
+            .extended
+            .checked_add(m)
+            .ok_or_else(|| OteError::BadParameter("hash counter of the session overflowed".to_owned()))?;
+        Ok(())
+    }
+
+    // Extend m = messages.len() more OTs without redoing the base OTs.
+    pub fn extend(&mut self, messages: &[(Vec<bool>, Vec<bool>)], choice: &[bool]) -> Result<Vec<Vec<bool>>, OteError> {
+        check_inputs(messages, choice)?;
+        self.count(choice.len())?;
+        Ok(match &mut self.parties {
+            Parties::Iknp(sender, receiver) => receiver.extend(sender, messages, choice),
+            Parties::Alsz(sender, receiver) => receiver.extend(sender, messages, choice),
+        })
+    }
+
+    /**
+     * Extend random OTs with the given choice bits but without messages.
+     * Returns the hash index of the first OT, the sender's rows q_j and the receiver's rows
+     * t_j, which satisfy t_j = q_j xor (choice_j * s).
+     */
+    pub(crate) fn extend_rows(&mut self, choice: &[bool]) -> Result<ExtendedRows, OteError> {
+        let m = choice.len();
+        self.count(m)?;
+        Ok(match &mut self.parties {
+            Parties::Iknp(sender, receiver) => {
+                let (t, otp) = receiver.mask_columns(choice);
+                let (first_index, q) = sender.next_rows(otp, m);
+                let (_, t) = receiver.next_rows(t, m);
+                (first_index, q, t)
+            }
+            Parties::Alsz(sender, receiver) => {
+                let (t, u) = receiver.compute_t_and_u(choice);
+                let (first_index, q) = sender.next_rows(u, m);
+                let (_, t) = receiver.next_rows(t, m);
+                (first_index, q, t)
+            }
+        })
+    }
+
+    // The sender's secret s, needed to turn its rows q_j into both random messages.
+    pub(crate) fn sender_s(&self) -> SecretBits {
+        match &self.parties {
+            Parties::Iknp(sender, _) => Zeroizing::new(sender.s().to_vec()),
+            Parties::Alsz(sender, _) => Zeroizing::new(sender.s().to_vec()),
+        }
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn id(&self) -> [u8; 16] {
+        self.id
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn extended(&self) -> usize {
+        self.extended
+    }
+}
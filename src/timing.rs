@@ -0,0 +1,194 @@
+// Dudect-style detection of data-dependent timing in the functions that handle secret bits.
+//
+// Every target is run on inputs of two classes, e.g. choice bit 0 and choice bit 1, in random
+// order, and the run times of the classes are compared with Welch's t-test, as in
+// "Dude, is my code constant time?" (Reparaz, Balasch, Verbauwhede 2017).
+
+use std::time::Instant;
+
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::{self, make_test_group, SafePrimeGroup, USIZE};
+use crate::{ot_better_network, ote_IKNP, precompute};
+
+// |t| above this is taken as a leak. Dudect treats 4.5 as suspicious and 10 as certain.
+pub const T_THRESHOLD: f64 = 10.0;
+
+/**
+ * Running Welch's t-test on two classes of measurements, with the mean and variance of
+ * each class updated online (Welford).
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Welch {
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl Welch {
+    pub fn new() -> Welch {
+        Welch::default()
+    }
+
+    pub fn push(&mut self, class: bool, x: f64) {
+        let c = class as usize;
+        self.n[c] += 1.0;
+        let delta = x - self.mean[c];
+        self.mean[c] += delta / self.n[c];
+        self.m2[c] += delta * (x - self.mean[c]);
+    }
+
+    pub fn samples(&self) -> usize {
+        (self.n[0] + self.n[1]) as usize
+    }
+
+    // The t statistic, 0 while a class has fewer than two measurements.
+    pub fn t(&self) -> f64 {
+        if self.n[0] < 2.0 || self.n[1] < 2.0 {
+            return 0.0;
+        }
+        let var = |c: usize| self.m2[c] / (self.n[c] - 1.0);
+        let se = (var(0) / self.n[0] + var(1) / self.n[1]).sqrt();
+        if se == 0.0 {
+            return 0.0;
+        }
+        (self.mean[0] - self.mean[1]) / se
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TimingReport {
+    pub name: String,
+    pub samples: usize,
+    // The largest |t| over the uncropped and the cropped measurements.
+    pub t: f64,
+}
+
+impl TimingReport {
+    pub fn leaks(&self) -> bool {
+        self.t.abs() > T_THRESHOLD
+    }
+}
+
+/**
+ * Times `f` on `samples` inputs made by `prepare`, whose class is picked at random for each one.
+ * Like dudect the test is also run on the measurements below a few percentiles, since the
+ * slow outliers from interrupts and scheduling hide small differences.
+ */
+pub fn measure<I, P, F, R>(name: &str, samples: usize, rng: &mut R, mut prepare: P, mut f: F) -> TimingReport
+where
+    P: FnMut(bool, &mut R) -> I,
+    F: FnMut(&I),
+    R: CryptoRng + RngCore,
+{
+    let inputs = (0..samples)
+        .map(|_| {
+            let class = rng.gen::<bool>();
+            (class, prepare(class, rng))
+        })
+        .collect::<Vec<_>>();
+    let times = inputs
+        .iter()
+        .map(|(class, input)| {
+            let start = Instant::now();
+            f(input);
+            (*class, start.elapsed().as_nanos() as f64)
+        })
+        .collect::<Vec<_>>();
+    let mut sorted = times.iter().map(|&(_, x)| x).collect::<Vec<_>>();
+    sorted.sort_by(f64::total_cmp);
+    let t = [1.0, 0.9, 0.5]
+        .into_iter()
+        .map(|percentile| {
+            let cutoff = sorted.get((percentile * (samples as f64 - 1.0)) as usize).copied().unwrap_or(0.0);
+            let mut welch = Welch::new();
+            times.iter().filter(|&&(_, x)| x <= cutoff).for_each(|&(class, x)| welch.push(class, x));
+            welch.t()
+        })
+        .fold(0.0, |acc: f64, t| if t.abs() > acc.abs() { t } else { acc });
+    TimingReport {
+        name: name.to_owned(),
+        samples,
+        t,
+    }
+}
+
+// The same bit for every entry, so the classes differ as much as possible.
+fn constant_bits(bit: bool, len: usize) -> Vec<bool> {
+    vec![bit; len]
+}
+
+// One OT with choice bit `class` for `receive_`, from keys and ciphertexts made up front.
+fn base_ot_input<R: CryptoRng + RngCore>(group: &SafePrimeGroup, class: bool, rng: &mut R) -> Result<(ot_primitive::SecretKeys, ot_primitive::OTParams, Vec<bool>), OteError> {
+    let choice = vec![class];
+    let sk = ot_primitive::create_secret_keys(group, 1, rng);
+    let keys = ot_primitive::commit_choice(group, &sk, &choice, rng)?;
+    let messages = vec![(USIZE::from(rng.gen::<u64>()), USIZE::from(rng.gen::<u64>()))];
+    let encrypted = ot_primitive::send(group, &keys, &messages, rng)?;
+    Ok((sk, encrypted, choice))
+}
+
+// Keeps the first error of the timed calls, which cannot return it from inside `measure`.
+fn keep_error<T>(failure: &mut Option<OteError>, result: Result<T, OteError>) -> Option<T> {
+    match result {
+        Ok(x) => Some(x),
+        Err(e) => {
+            failure.get_or_insert(e);
+            None
+        }
+    }
+}
+
+/**
+ * Checks the functions that take secret choice bits, with `samples` measurements each:
+ * the bit selection of the extensions, the base OT steps on the small test group, the
+ * decoding of IKNP and ALSZ and the unmasking of precomputed OTs.
+ * The classes are all choice bits 0 against all choice bits 1.
+ */
+pub fn check_choice_handling<R: CryptoRng + RngCore>(samples: usize, rng: &mut R) -> Result<Vec<TimingReport>, OteError> {
+    let group = &make_test_group(rng)?;
+    let m = 64;
+    let len = OUTPUT_SIZE;
+    let mut reports = Vec::new();
+
+    let l = random_boolvec_len(len, rng);
+    let r = random_boolvec_len(len, rng);
+    reports.push(measure("select_boolvec", samples, rng, |class, _| ct_choice(class), |&choice| {
+        std::hint::black_box(select_boolvec(&l, &r, choice));
+    }));
+
+    let sk = ot_primitive::create_secret_keys(group, 1, rng);
+    let mut commit_rng = fork_rng(rng);
+    let mut failure = None;
+    reports.push(measure("commit_choice", samples, rng, |class, _| vec![class], |choice| {
+        std::hint::black_box(keep_error(&mut failure, ot_primitive::commit_choice(group, &sk, choice, &mut commit_rng)));
+    }));
+
+    let inputs = (0..2).map(|class| base_ot_input(group, class == 1, rng)).collect::<Result<Vec<_>, _>>()?;
+    reports.push(measure("receive_", samples, rng, |class, _| &inputs[class as usize], |(sk, encrypted, choice)| {
+        std::hint::black_box(keep_error(&mut failure, ot_primitive::receive_(group, encrypted, sk, choice)));
+    }));
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    let y = (0..m).map(|_| (random_boolvec_len(len, rng), random_boolvec_len(len, rng))).collect::<Vec<_>>();
+    let t = Zeroizing::new((0..m).map(|_| random_boolvec_len(128, rng)).collect::<Vec<_>>());
+    let mut iknp = ote_IKNP::Receiver::initialize(128, rng)?;
+    reports.push(measure("IKNP decode", samples, rng, |class, _| constant_bits(class, m), |choice| {
+        std::hint::black_box(iknp.decode(&y, t.clone(), choice));
+    }));
+    let t_columns = Zeroizing::new(transpose(&t));
+    let mut alsz = ot_better_network::Receiver::initialize(128, rng)?;
+    reports.push(measure("ALSZ decode", samples, rng, |class, _| constant_bits(class, m), |choice| {
+        std::hint::black_box(alsz.decode(&y, t_columns.clone(), choice));
+    }));
+
+    let pads = y.iter().map(|(y_0, _)| y_0.clone()).collect::<Vec<_>>();
+    reports.push(measure("unmask", samples, rng, |class, _| constant_bits(class, m), |choice| {
+        std::hint::black_box(precompute::unmask(&y, choice, &pads));
+    }));
+    Ok(reports)
+}
@@ -0,0 +1,68 @@
+// In-process runs of the extensions that record their intermediate values, for known-answer tests.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::{PublicKey, SafePrimeGroup};
+use crate::session::Protocol;
+use crate::{ot_better_network, ote_IKNP};
+
+/**
+ * The values computed by one run of the extension, in the order they are computed.
+ * `u` is what the receiver sends after the base OTs: for IKNP the masked columns t^i and
+ * t^i xor r interleaved as on the channel, for ALSZ the corrections u^i.
+ * `q` and `t` hold the rows q_j of the sender and t_j of the receiver, which are wiped on drop.
+ */
+pub struct Trace {
+    pub base_ot_keys: Vec<(PublicKey, PublicKey)>,
+    pub u: Vec<Vec<bool>>,
+    pub q: SecretRows,
+    pub t: SecretRows,
+    pub y: MessagePairs,
+    pub output: Vec<Vec<bool>>,
+}
+
+/**
+ * Runs the extension step by step like `ote` and records every step.
+ * The randomness is drawn in the same order, so with the same rng the output equals that of `ote`.
+ */
+pub fn trace<R: CryptoRng + RngCore>(
+    protocol: Protocol,
+    messages: &[(Vec<bool>, Vec<bool>)],
+    choice: &[bool],
+    k: usize,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Trace, OteError> {
+    check_inputs(messages, choice)?;
+    let m = choice.len();
+    match protocol {
+        Protocol::Iknp => {
+            let mut sender = ote_IKNP::Sender::initialize(k, rng)?;
+            let mut receiver = ote_IKNP::Receiver::initialize(k, rng)?;
+            let (sk, base_ot_keys) = sender.commit_ot_primitive(group, rng)?;
+            let seeds_ot = receiver.send_ot_primitive(group, &base_ot_keys, rng)?;
+            sender.receive_ot_primitive(group, &sk, &seeds_ot)?;
+            let (t, otp) = receiver.mask_columns(choice);
+            let u = otp.iter().flat_map(|(c_0, c_1)| [c_0.clone(), c_1.clone()]).collect();
+            let (offset, q) = sender.next_rows(otp, m);
+            let y = sender.mask_messages(offset, &q, messages);
+            let output = receiver.decode(&y, t.clone(), choice);
+            Ok(Trace { base_ot_keys, u, q, t, y, output })
+        }
+        Protocol::Alsz => {
+            let mut sender = ot_better_network::Sender::initialize(k, rng)?;
+            let mut receiver = ot_better_network::Receiver::initialize(k, rng)?;
+            let (sk, base_ot_keys) = sender.commit_ot_primitive(group, rng)?;
+            let res = receiver.send_ot_primitive(group, &base_ot_keys, rng)?;
+            sender.receive_ot_primitive(group, &sk, &res)?;
+            let (t_columns, u) = receiver.compute_t_and_u(choice);
+            let t = Zeroizing::new(transpose(&t_columns));
+            let (offset, q) = sender.next_rows(u.clone(), m);
+            let y = sender.mask_messages(offset, &q, messages);
+            let output = receiver.decode(&y, t_columns, choice);
+            Ok(Trace { base_ot_keys, u, q, t, y, output })
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Transcripts of the messages on a `Channel`, and replay of one party against a recorded transcript.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::channel::Channel;
+use crate::common::be_u64;
+use crate::error::OteError;
+
+/**
+ * File format for transcripts, all integers big-endian:
+ *
+ * magic "OTETRAN\0" | version u16 | number of messages u64
+ * per message: direction u8 (0 = sent, 1 = received) | label length u16 | label | length u64 | bytes
+ */
+const MAGIC: &[u8; 8] = b"OTETRAN\0";
+const VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+// One message as seen by the party that recorded it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub direction: Direction,
+    pub label: String,
+    pub bytes: Vec<u8>,
+}
+
+// All messages of one party, in the order they were sent and received.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    messages: Vec<Message>,
+}
+
+fn invalid(msg: &str) -> OteError {
+    OteError::InvalidFile(msg.to_owned())
+}
+
+// Splits n bytes off the front of the input, failing if there are fewer.
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], OteError> {
+    if bytes.len() < n {
+        return Err(invalid("transcript is truncated"));
+    }
+    let (front, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Ok(front)
+}
+
+fn take_u16(bytes: &mut &[u8]) -> Result<u16, OteError> {
+    let x = take(bytes, 2)?;
+    Ok(u16::from_be_bytes([x[0], x[1]]))
+}
+
+impl Transcript {
+    pub fn new() -> Transcript {
+        Transcript { messages: Vec::new() }
+    }
+
+    pub fn push(&mut self, direction: Direction, label: &str, bytes: &[u8]) {
+        self.messages.push(Message {
+            direction,
+            label: label.to_owned(),
+            bytes: bytes.to_vec(),
+        });
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /**
+     * Index of the first message in which the two transcripts differ, None if they are equal.
+     * If one is a prefix of the other, this is the length of the shorter one.
+     */
+    pub fn first_divergence(&self, other: &Transcript) -> Option<usize> {
+        let common = self.messages.iter().zip(&other.messages).position(|(a, b)| a != b);
+        match common {
+            Some(i) => Some(i),
+            None if self.len() != other.len() => Some(self.len().min(other.len())),
+            None => None,
+        }
+    }
+
+    /**
+     * Describes where a replayed run left this recorded one: the index and label of the first
+     * differing message and the first byte in which it differs. None if they are equal.
+     */
+    pub fn describe_divergence(&self, replayed: &Transcript) -> Option<String> {
+        let i = self.first_divergence(replayed)?;
+        Some(match (self.messages.get(i), replayed.messages.get(i)) {
+            (Some(a), Some(b)) if a.direction != b.direction || a.label != b.label => format!(
+                "message {}: recorded {:?} \"{}\" but replayed {:?} \"{}\"",
+                i, a.direction, a.label, b.direction, b.label
+            ),
+            (Some(a), Some(b)) => {
+                let byte = a.bytes.iter().zip(&b.bytes).position(|(x, y)| x != y).unwrap_or(a.bytes.len().min(b.bytes.len()));
+                format!(
+                    "message {} ({:?} \"{}\"): {} recorded and {} replayed bytes, first difference at byte {}",
+                    i, a.direction, a.label, a.bytes.len(), b.bytes.len(), byte
+                )
+            }
+            (Some(a), None) => format!("replay stopped before message {} ({:?} \"{}\")", i, a.direction, a.label),
+            (None, Some(b)) => format!("replay went on with message {} ({:?} \"{}\")", i, b.direction, b.label),
+            (None, None) => unreachable!("first_divergence is within the longer transcript"),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_be_bytes());
+        bytes.extend_from_slice(&(self.messages.len() as u64).to_be_bytes());
+        for message in &self.messages {
+            bytes.push(match message.direction {
+                Direction::Sent => 0,
+                Direction::Received => 1,
+            });
+            bytes.extend_from_slice(&(message.label.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(message.label.as_bytes());
+            bytes.extend_from_slice(&(message.bytes.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(&message.bytes);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Transcript, OteError> {
+        let bytes = &mut bytes;
+        if take(bytes, 8)? != MAGIC {
+            return Err(invalid("not a transcript file"));
+        }
+        let version = take_u16(bytes)?;
+        if version != VERSION {
+            return Err(OteError::InvalidFile(format!("unsupported version {}", version)));
+        }
+        let count = be_u64(take(bytes, 8)?);
+        let mut transcript = Transcript::new();
+        for _ in 0..count {
+            let direction = match take(bytes, 1)?[0] {
+                0 => Direction::Sent,
+                1 => Direction::Received,
+                _ => return Err(invalid("unknown direction")),
+            };
+            let label_len = take_u16(bytes)? as usize;
+            let label = std::str::from_utf8(take(bytes, label_len)?).map_err(|_| invalid("label is not UTF-8"))?;
+            let len = usize::try_from(be_u64(take(bytes, 8)?)).map_err(|_| invalid("message is too large"))?;
+            transcript.push(direction, label, take(bytes, len)?);
+        }
+        if !bytes.is_empty() {
+            return Err(invalid("transcript has trailing data"));
+        }
+        Ok(transcript)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), OteError> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Transcript, OteError> {
+        Transcript::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+// One line per message, with the first bytes in hex.
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, message) in self.messages.iter().enumerate() {
+            let arrow = match message.direction {
+                Direction::Sent => "->",
+                Direction::Received => "<-",
+            };
+            let prefix = message.bytes.iter().take(8).map(|x| format!("{:02x}", x)).collect::<String>();
+            writeln!(f, "{:>4} {} {:<20} {:>10} bytes  {}", i, arrow, message.label, message.bytes.len(), prefix)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Stream that plays the received messages of a transcript back, framed like `Channel` sends them.
+ * Writes are accepted and dropped. Reading past the last recorded message gives end of file.
+ */
+pub struct ReplayStream {
+    inbound: VecDeque<u8>,
+}
+
+impl ReplayStream {
+    pub fn new(recorded: &Transcript) -> ReplayStream {
+        let inbound = recorded
+            .messages
+            .iter()
+            .filter(|x| x.direction == Direction::Received)
+            .flat_map(|x| (x.bytes.len() as u64).to_be_bytes().into_iter().chain(x.bytes.iter().copied()))
+            .collect();
+        ReplayStream { inbound }
+    }
+}
+
+impl AsyncRead for ReplayStream {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let inbound = &mut self.get_mut().inbound;
+        // Only ever drained from the front, so all bytes are in the first slice.
+        let (front, _) = inbound.as_slices();
+        let n = buf.remaining().min(front.len());
+        buf.put_slice(&front[..n]);
+        inbound.drain(..n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for ReplayStream {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/**
+ * Channel for replaying one party offline: it receives that party's recorded inbound messages
+ * and records everything again. Running the party on it with the same inputs and a generator with
+ * the same seed reproduces its computation step by step, and `describe_divergence` on the
+ * recorded and the new transcript shows the first message that came out differently.
+ */
+pub fn replay_channel(recorded: &Transcript) -> Channel<ReplayStream> {
+    let mut chan = Channel::new(ReplayStream::new(recorded));
+    chan.record();
+    chan
+}
@@ -0,0 +1,386 @@
+// Subfield vector OLE from the correlated OTs of the extensions.
+//
+// The sender holds Delta and b, the receiver u and w = b + u * Delta, entry by entry.
+// Over GF(2^128) with u in GF(2) this is exactly what an extension with k = 128 leaves behind:
+// Delta = s, b_j = q_j and w_j = t_j = q_j xor u_j s, read as field elements.
+// Over F_p, u_j = sum u_j,i 2^i is fed bit by bit into COTs with correlations Delta * 2^i as in
+// `arith`, and b_j and w_j are the sums of the sender's m_0 and of the receiver's outputs.
+//
+// In malicious mode the receiver's u is checked against w as in KOS15, by the two parties over a
+// channel: the sender sends a seed for random chi_j, the receiver answers x = sum chi_j u_j and
+// z = sum chi_j w_j from its output, and the sender checks z = sum chi_j b_j + x * Delta with its
+// own. A few extra entries of random u with fixed coefficients mask x and are dropped afterwards.
+//
+// `vole_gf128` and `vole_fp` run both parties on a session in one process, where there is no one
+// to check, so they are semi-honest only. The `_sender` and `_receiver` drivers run one party each
+// over a `Channel` and in malicious mode run the check before they return.
+
+use rand::{CryptoRng, Rng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtReceiver, OtExtSender};
+use crate::arith::{correlated_ots, correlated_ots_receiver, correlated_ots_sender, gilboa_choice, gilboa_deltas, sums, Modulus};
+use crate::channel::Channel;
+use crate::common::*;
+use crate::error::OteError;
+use crate::params::SecurityParams;
+use crate::psi::shake;
+use crate::session::OtExtSession;
+
+// Entries of random u that malicious mode appends over GF(2^128) to mask x in the check.
+pub const GF128_MASKS: usize = 128;
+
+// Bytes of the seed the coefficients chi_j are drawn from.
+const SEED_BYTES: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoleMode {
+    SemiHonest,
+    Malicious,
+}
+
+/**
+ * Product in GF(2^128) with the modulus x^128 + x^7 + x^2 + x + 1, where bit i of the u128 is
+ * the coefficient of x^i. Runs in time independent of the operands.
+ */
+pub fn gf128_mul(a: u128, b: u128) -> u128 {
+    let (mut a, mut res) = (a, 0u128);
+    for i in 0..128 {
+        res ^= a & (b >> i & 1).wrapping_neg();
+        a = (a << 1) ^ ((a >> 127).wrapping_neg() & 0x87);
+    }
+    res
+}
+
+// A row of 128 bits as a field element, first bit highest.
+fn to_gf128(row: &[bool]) -> u128 {
+    row.iter().fold(0, |acc, &b| acc << 1 | b as u128)
+}
+
+pub struct Gf128Sender {
+    pub delta: Zeroizing<u128>,
+    pub b: Zeroizing<Vec<u128>>,
+}
+
+pub struct Gf128Receiver {
+    pub u: SecretBits,
+    pub w: Zeroizing<Vec<u128>>,
+}
+
+pub struct FpSender {
+    pub delta: Zeroizing<u64>,
+    pub b: Zeroizing<Vec<u64>>,
+}
+
+pub struct FpReceiver {
+    pub u: Zeroizing<Vec<u64>>,
+    pub w: Zeroizing<Vec<u64>>,
+}
+
+fn check_k(k: usize) -> Result<(), OteError> {
+    match k {
+        128 => Ok(()),
+        k => Err(OteError::BadParameter(format!("VOLE over GF(2^128) needs k = 128, not {}", k))),
+    }
+}
+
+// The outputs of both parties from the rows of an extension with k = 128.
+fn gf128_sender(q: &[Vec<bool>], s: &[bool]) -> Gf128Sender {
+    Gf128Sender {
+        delta: Zeroizing::new(to_gf128(s)),
+        b: Zeroizing::new(q.iter().map(|q_j| to_gf128(q_j)).collect()),
+    }
+}
+
+fn gf128_receiver(t: &[Vec<bool>], u: SecretBits) -> Gf128Receiver {
+    Gf128Receiver {
+        u,
+        w: Zeroizing::new(t.iter().map(|t_j| to_gf128(t_j)).collect()),
+    }
+}
+
+// The receiver's u, with GF128_MASKS random entries appended in malicious mode.
+fn masked_bits<R: CryptoRng + RngCore>(u: &[bool], mode: VoleMode, rng: &mut R) -> SecretBits {
+    match mode {
+        VoleMode::SemiHonest => Zeroizing::new(u.to_vec()),
+        VoleMode::Malicious => Zeroizing::new([u, &random_boolvec_len(GF128_MASKS, rng)].concat()),
+    }
+}
+
+// Subfield VOLE over GF(2^128) with the receiver's bits u, semi-honest, on a session with k = 128.
+pub fn vole_gf128(session: &mut OtExtSession, u: &[bool]) -> Result<(Gf128Sender, Gf128Receiver), OteError> {
+    check_k(session.k())?;
+    let (_, q, t) = session.extend_rows(u)?;
+    Ok((gf128_sender(&q, &session.sender_s()), gf128_receiver(&t, Zeroizing::new(u.to_vec()))))
+}
+
+/**
+ * Sender of a VOLE over GF(2^128) with n entries over a channel, as the sender of `ot`, which
+ * must have k = 128. In malicious mode GF128_MASKS more entries are extended for the check, which
+ * fails with ConsistencyCheckFailed if the receiver's u does not match its w.
+ * Messages: the extension without its y_j, then in malicious mode "vole seed" and "vole x,z".
+ */
+pub async fn vole_gf128_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    ot: &OtExtSender,
+    n: usize,
+    mode: VoleMode,
+    rng: &mut R,
+) -> Result<Gf128Sender, OteError> {
+    check_k(ot.config().k())?;
+    let masks = if mode == VoleMode::Malicious { GF128_MASKS } else { 0 };
+    let (_, q, s) = ot.send_rows(chan, n + masks, rng).await?;
+    let mut sender = gf128_sender(&q, &s);
+    if mode == VoleMode::Malicious {
+        check_gf128_sender(chan, &mut sender, rng).await?;
+    }
+    Ok(sender)
+}
+
+// Receiver of `vole_gf128_sender` with the bits u, which must be n of them.
+pub async fn vole_gf128_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    ot: &OtExtReceiver,
+    u: &[bool],
+    mode: VoleMode,
+    rng: &mut R,
+) -> Result<Gf128Receiver, OteError> {
+    check_k(ot.config().k())?;
+    let u = masked_bits(u, mode, rng);
+    let (_, t) = ot.receive_rows(chan, u.to_vec(), rng).await?;
+    let mut receiver = gf128_receiver(&t, u);
+    if mode == VoleMode::Malicious {
+        check_gf128_receiver(chan, &mut receiver).await?;
+    }
+    Ok(receiver)
+}
+
+// Number of entries before the masks, of a VOLE with `len` entries.
+fn unmasked(len: usize, masks: usize) -> Result<usize, OteError> {
+    len.checked_sub(masks)
+        .ok_or_else(|| OteError::BadParameter(format!("{} entries are fewer than the {} masks of the check", len, masks)))
+}
+
+// The coefficients of one repetition of the check, 16 bytes each from the seed.
+fn coefficients(seed: &[u8], rep: usize, n: usize) -> Vec<u128> {
+    let mut bytes = vec![0; 16 * n];
+    shake(&[b"VOLE chi", seed, &(rep as u64).to_be_bytes()], &mut bytes);
+    bytes.chunks_exact(16).map(|x| u128::from_be_bytes(x.try_into().expect("chunks of 16 bytes"))).collect()
+}
+
+// The chi_j over GF(2^128): random for the n entries and x^i for mask i, which makes x uniform.
+fn gf128_chi(seed: &[u8], n: usize) -> Vec<u128> {
+    coefficients(seed, 0, n).into_iter().chain((0..GF128_MASKS).map(|i| 1u128 << i)).collect()
+}
+
+async fn send_seed<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(chan: &mut Channel<S>, rng: &mut R) -> Result<[u8; SEED_BYTES], OteError> {
+    let seed = rng.gen::<[u8; SEED_BYTES]>();
+    chan.label("vole seed").send_bytes(&seed).await?;
+    Ok(seed)
+}
+
+async fn recv_seed<S: AsyncRead + AsyncWrite + Unpin>(chan: &mut Channel<S>) -> Result<Vec<u8>, OteError> {
+    let seed = chan.label("vole seed").recv_bytes().await?;
+    if seed.len() != SEED_BYTES {
+        return Err(OteError::MalformedMessage(format!("a seed of {} bytes instead of {}", seed.len(), SEED_BYTES)));
+    }
+    Ok(seed)
+}
+
+// The receiver's answer, which must have `len` bytes.
+async fn recv_answer<S: AsyncRead + AsyncWrite + Unpin>(chan: &mut Channel<S>, len: usize) -> Result<Vec<u8>, OteError> {
+    let answer = chan.label("vole x,z").recv_bytes().await?;
+    if answer.len() != len {
+        return Err(OteError::MalformedMessage(format!("x and z of {} bytes instead of {}", answer.len(), len)));
+    }
+    Ok(answer)
+}
+
+// Sender's side of the check over GF(2^128): checks the receiver's x and z against b and Delta and drops the masks.
+async fn check_gf128_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    sender: &mut Gf128Sender,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    let n = unmasked(sender.b.len(), GF128_MASKS)?;
+    let chi = gf128_chi(&send_seed(chan, rng).await?, n);
+    let answer = recv_answer(chan, 32).await?;
+    let (x, z) = (u128::from_be_bytes(answer[..16].try_into().expect("16 bytes")), u128::from_be_bytes(answer[16..].try_into().expect("16 bytes")));
+    let expected = chi.iter().zip(sender.b.iter()).fold(gf128_mul(x, *sender.delta), |acc, (&c, &b)| acc ^ gf128_mul(c, b));
+    if z != expected {
+        return Err(OteError::ConsistencyCheckFailed("receiver's u does not match w over GF(2^128)".to_owned()));
+    }
+    sender.b.truncate(n);
+    Ok(())
+}
+
+// Receiver's side of the check over GF(2^128): answers x and z for the sender's chi_j and drops the masks.
+async fn check_gf128_receiver<S: AsyncRead + AsyncWrite + Unpin>(chan: &mut Channel<S>, receiver: &mut Gf128Receiver) -> Result<(), OteError> {
+    if receiver.w.len() != receiver.u.len() {
+        return Err(OteError::BadParameter("the VOLE outputs differ in length".to_owned()));
+    }
+    let n = unmasked(receiver.u.len(), GF128_MASKS)?;
+    let chi = gf128_chi(&recv_seed(chan).await?, n);
+    let x = chi.iter().zip(receiver.u.iter()).fold(0, |acc, (&c, &u)| acc ^ (c & (u as u128).wrapping_neg()));
+    let z = chi.iter().zip(receiver.w.iter()).fold(0, |acc, (&c, &w)| acc ^ gf128_mul(c, w));
+    chan.label("vole x,z").send_bytes(&[x.to_be_bytes(), z.to_be_bytes()].concat()).await?;
+    receiver.u.truncate(n);
+    receiver.w.truncate(n);
+    Ok(())
+}
+
+// Subfield VOLE over the prime field of `modulus` with the sender's delta and the receiver's u, semi-honest.
+pub fn vole_fp(session: &mut OtExtSession, modulus: Modulus, delta: u64, u: &[u64]) -> Result<(FpSender, FpReceiver), OteError> {
+    prime(modulus)?;
+    modulus.check(u)?;
+    modulus.check(&[delta])?;
+    let deltas = gilboa_deltas(modulus, &vec![delta; u.len()]);
+    let (m_0, outputs) = correlated_ots(session, modulus, &deltas, &gilboa_choice(modulus, u))?;
+    Ok((
+        FpSender {
+            delta: Zeroizing::new(delta),
+            b: Zeroizing::new(sums(modulus, &m_0)),
+        },
+        FpReceiver {
+            u: Zeroizing::new(u.to_vec()),
+            w: Zeroizing::new(sums(modulus, &outputs)),
+        },
+    ))
+}
+
+/**
+ * Repetitions of the check over F_p, and so entries of random u that malicious mode appends,
+ * for a soundness error of 2^-statistical: a cheat passes each repetition with probability
+ * 1/p < 2^-(bits - 1).
+ */
+pub fn fp_masks(modulus: Modulus, params: SecurityParams) -> usize {
+    params.statistical().div_ceil((modulus.bits() - 1).max(1))
+}
+
+/**
+ * Sender of a VOLE over the prime field of `modulus` with n entries over a channel, as the
+ * sender of `ot` and holding delta. In malicious mode `fp_masks` more entries for the statistical
+ * parameter of `ot`'s configuration are extended for the check, which fails with
+ * ConsistencyCheckFailed if the receiver's u does not match its w.
+ * Messages: those of `correlated_ots_sender`, then in malicious mode "vole seed" and "vole x,z".
+ */
+pub async fn vole_fp_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    ot: &OtExtSender,
+    modulus: Modulus,
+    delta: u64,
+    n: usize,
+    mode: VoleMode,
+    rng: &mut R,
+) -> Result<FpSender, OteError> {
+    prime(modulus)?;
+    modulus.check(&[delta])?;
+    let masks = if mode == VoleMode::Malicious { fp_masks(modulus, ot.config().params()) } else { 0 };
+    let deltas = gilboa_deltas(modulus, &vec![delta; n + masks]);
+    let m_0 = correlated_ots_sender(chan, modulus, &deltas, ot.config(), ot.group(), rng).await?;
+    let mut sender = FpSender {
+        delta: Zeroizing::new(delta),
+        b: Zeroizing::new(sums(modulus, &m_0)),
+    };
+    if mode == VoleMode::Malicious {
+        check_fp_sender(chan, modulus, ot.config().params(), &mut sender, rng).await?;
+    }
+    Ok(sender)
+}
+
+// Receiver of `vole_fp_sender` with the elements u, which must be n of them.
+pub async fn vole_fp_receiver<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    ot: &OtExtReceiver,
+    modulus: Modulus,
+    u: &[u64],
+    mode: VoleMode,
+    rng: &mut R,
+) -> Result<FpReceiver, OteError> {
+    let p = prime(modulus)?;
+    modulus.check(u)?;
+    let u = match mode {
+        VoleMode::SemiHonest => Zeroizing::new(u.to_vec()),
+        VoleMode::Malicious => {
+            let masks = (0..fp_masks(modulus, ot.config().params())).map(|_| rng.gen_range(0..p)).collect::<Vec<_>>();
+            Zeroizing::new([u, &masks].concat())
+        }
+    };
+    let outputs = correlated_ots_receiver(chan, modulus, &gilboa_choice(modulus, &u), ot.config(), ot.group(), rng).await?;
+    let mut receiver = FpReceiver {
+        w: Zeroizing::new(sums(modulus, &outputs)),
+        u,
+    };
+    if mode == VoleMode::Malicious {
+        check_fp_receiver(chan, modulus, ot.config().params(), &mut receiver).await?;
+    }
+    Ok(receiver)
+}
+
+fn prime(modulus: Modulus) -> Result<u64, OteError> {
+    modulus
+        .prime_value()
+        .ok_or_else(|| OteError::BadParameter(format!("VOLE over F_p needs a prime modulus, not {:?}", modulus)))
+}
+
+// The chi_j of every repetition: random for the n entries, and 1 for the repetition's own mask.
+fn fp_chi(p: u64, seed: &[u8], n: usize, reps: usize) -> Vec<Vec<u64>> {
+    (0..reps)
+        .map(|r| {
+            let random = coefficients(seed, r, n).into_iter().map(|c| (c % p as u128) as u64);
+            random.chain((0..reps).map(|i| (i == r) as u64)).collect()
+        })
+        .collect()
+}
+
+fn dot(modulus: Modulus, chi: &[u64], v: &[u64]) -> u64 {
+    chi.iter().zip(v).fold(0, |acc, (&c, &x)| modulus.add(acc, modulus.mul(c, x)))
+}
+
+// Sender's side of the check over F_p: checks x and z of every repetition against b and Delta and drops the masks.
+async fn check_fp_sender<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    params: SecurityParams,
+    sender: &mut FpSender,
+    rng: &mut R,
+) -> Result<(), OteError> {
+    let (p, reps) = (prime(modulus)?, fp_masks(modulus, params));
+    let n = unmasked(sender.b.len(), reps)?;
+    let chi = fp_chi(p, &send_seed(chan, rng).await?, n, reps);
+    let answer = recv_answer(chan, 16 * reps).await?;
+    for (chi, xz) in chi.iter().zip(answer.chunks_exact(16)) {
+        let (x, z) = (be_u64(&xz[..8]), be_u64(&xz[8..]));
+        if x >= p || z >= p {
+            return Err(OteError::MalformedMessage(format!("x or z is not an element of {:?}", modulus)));
+        }
+        if z != modulus.add(dot(modulus, chi, &sender.b), modulus.mul(x, *sender.delta)) {
+            return Err(OteError::ConsistencyCheckFailed(format!("receiver's u does not match w over {:?}", modulus)));
+        }
+    }
+    sender.b.truncate(n);
+    Ok(())
+}
+
+// Receiver's side of the check over F_p: answers x and z of every repetition and drops the masks.
+async fn check_fp_receiver<S: AsyncRead + AsyncWrite + Unpin>(
+    chan: &mut Channel<S>,
+    modulus: Modulus,
+    params: SecurityParams,
+    receiver: &mut FpReceiver,
+) -> Result<(), OteError> {
+    if receiver.w.len() != receiver.u.len() {
+        return Err(OteError::BadParameter("the VOLE outputs differ in length".to_owned()));
+    }
+    let (p, reps) = (prime(modulus)?, fp_masks(modulus, params));
+    let n = unmasked(receiver.u.len(), reps)?;
+    let chi = fp_chi(p, &recv_seed(chan).await?, n, reps);
+    let answer = chi
+        .iter()
+        .flat_map(|chi| [dot(modulus, chi, &receiver.u).to_be_bytes(), dot(modulus, chi, &receiver.w).to_be_bytes()].concat())
+        .collect::<Vec<_>>();
+    chan.label("vole x,z").send_bytes(&answer).await?;
+    receiver.u.truncate(n);
+    receiver.w.truncate(n);
+    Ok(())
+}
@@ -0,0 +1,289 @@
+// Yao's garbled circuits with free XOR (Kolesnikov, Schneider 2008), half gates (Zahur, Rosulek,
+// Evans 2015) and point-and-permute.
+//
+// Every wire w has the labels W_0 and W_1 = W_0 xor Delta for a secret Delta with lowest bit 1,
+// so the lowest bit of the label the evaluator holds, its colour, is the wire value xor the
+// colour of W_0. XOR, INV and EQW gates need no table and an AND gate needs two 128-bit rows.
+// The hash is the fixed-key AES construction H(x, i) = pi(sigma(x) xor i) xor sigma(x) xor i of
+// Guo, Katz, Wang and Yu 2020, with sigma(x_L || x_R) = (x_L xor x_R) || x_L.
+//
+// Party 0 garbles and party 1 evaluates. The evaluator gets the labels of its own input bits
+// with the OT extension, decodes the outputs with the colours of the output wires and sends
+// the result back to the garbler.
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use rand::{CryptoRng, Rng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::api::{OtExtConfig, OtExtReceiver, OtExtSender};
+use crate::channel::Channel;
+use crate::circuit::{Circuit, Gate};
+use crate::common::*;
+use crate::error::OteError;
+use crate::ot_primitive::SafePrimeGroup;
+use crate::ote_IKNP;
+
+pub type Label = u128;
+
+// Bytes of one label on the channel.
+pub const LABEL_BYTES: usize = 16;
+
+// The key of the fixed-key AES is public, any constant works.
+const FIXED_KEY: [u8; 16] = *b"OTE yao fixedkey";
+
+// x if the bit is set and 0 otherwise, without a branch on the bit.
+fn times(bit: bool, x: Label) -> Label {
+    x & (bit as u128).wrapping_neg()
+}
+
+fn colour(x: Label) -> bool {
+    x & 1 == 1
+}
+
+// Fixed-key AES used as a random permutation.
+struct Hash {
+    aes: Aes128,
+}
+
+impl Hash {
+    fn new() -> Hash {
+        Hash {
+            aes: Aes128::new(&FIXED_KEY.into()),
+        }
+    }
+
+    fn hash(&self, x: Label, tweak: u64) -> Label {
+        let (left, right) = ((x >> 64) as u64, x as u64);
+        let y = ((((left ^ right) as u128) << 64) | left as u128) ^ tweak as u128;
+        let mut block = y.to_be_bytes().into();
+        self.aes.encrypt_block(&mut block);
+        u128::from_be_bytes(block.into()) ^ y
+    }
+}
+
+// What the garbler sends besides the labels: two rows per AND gate, the active label of every
+// EQ gate and the colour of W_0 of every output wire.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GarbledCircuit {
+    pub tables: Vec<[Label; 2]>,
+    pub constants: Vec<Label>,
+    pub decoding: Vec<bool>,
+}
+
+// The garbler's secrets: Delta and the labels W_0 of all input wires.
+pub struct Encoding {
+    delta: Zeroizing<Label>,
+    inputs: Zeroizing<Vec<Label>>,
+}
+
+impl Encoding {
+    // The label of input wire `wire` for the value `bit`.
+    pub fn label(&self, wire: usize, bit: bool) -> Label {
+        self.inputs[wire] ^ times(bit, *self.delta)
+    }
+
+    // Both labels of every input wire in the range, as OT messages.
+    fn label_pairs(&self, wires: std::ops::Range<usize>) -> MessagePairs {
+        wires.map(|w| (label_to_bits(self.label(w, false)), label_to_bits(self.label(w, true)))).collect()
+    }
+}
+
+pub fn label_to_bits(x: Label) -> Vec<bool> {
+    byte_vec_to_bool_vec(&x.to_be_bytes())
+}
+
+pub fn bits_to_label(v: &[bool]) -> Result<Label, OteError> {
+    if v.len() != 8 * LABEL_BYTES {
+        return Err(OteError::MalformedMessage(format!("label of {} bits", v.len())));
+    }
+    Ok(Label::from_be_bytes(to_array(bool_vec_to_byte_vec(v))?))
+}
+
+/**
+ * Garbles the circuit. The result is sent to the evaluator, the encoding stays with the garbler.
+ * The tables of the AND gates and the EQ labels are in gate order.
+ */
+pub fn garble<R: CryptoRng + RngCore>(circuit: &Circuit, rng: &mut R) -> Result<(GarbledCircuit, Encoding), OteError> {
+    circuit.check()?;
+    let hash = Hash::new();
+    let delta = Zeroizing::new(rng.gen::<Label>() | 1);
+    let input_wires = circuit.inputs.iter().sum::<usize>();
+    let mut zero = Zeroizing::new(vec![0; circuit.wires]);
+    zero[..input_wires].iter_mut().for_each(|w| *w = rng.gen());
+    let mut tables = Vec::with_capacity(circuit.and_gates());
+    let mut constants = Vec::new();
+    for (j, gate) in circuit.gates.iter().enumerate() {
+        zero[gate.output()] = match *gate {
+            Gate::Xor(a, b, _) => zero[a] ^ zero[b],
+            Gate::Inv(a, _) => zero[a] ^ *delta,
+            Gate::Eqw(a, _) => zero[a],
+            Gate::Eq(c, _) => {
+                let w_0 = rng.gen::<Label>();
+                constants.push(w_0 ^ times(c, *delta));
+                w_0
+            }
+            Gate::And(a, b, _) => {
+                let (a_0, b_0) = (zero[a], zero[b]);
+                let (p_a, p_b) = (colour(a_0), colour(b_0));
+                let (tweak_g, tweak_e) = (2 * j as u64, 2 * j as u64 + 1);
+                // Garbler half gate a and p_b, then evaluator half gate a and (b xor p_b).
+                let h_a0 = hash.hash(a_0, tweak_g);
+                let t_g = h_a0 ^ hash.hash(a_0 ^ *delta, tweak_g) ^ times(p_b, *delta);
+                let w_g = h_a0 ^ times(p_a, t_g);
+                let h_b0 = hash.hash(b_0, tweak_e);
+                let t_e = h_b0 ^ hash.hash(b_0 ^ *delta, tweak_e) ^ a_0;
+                let w_e = h_b0 ^ times(p_b, t_e ^ a_0);
+                tables.push([t_g, t_e]);
+                w_g ^ w_e
+            }
+        };
+    }
+    let decoding = zero[circuit.output_wires()].iter().map(|&w| colour(w)).collect();
+    let inputs = Zeroizing::new(zero[..input_wires].to_vec());
+    Ok((GarbledCircuit { tables, constants, decoding }, Encoding { delta, inputs }))
+}
+
+// Evaluates the garbled circuit on one label per input wire and returns the labels of the output wires.
+pub fn evaluate(circuit: &Circuit, garbled: &GarbledCircuit, inputs: &[Label]) -> Result<Vec<Label>, OteError> {
+    circuit.check()?;
+    let input_wires = circuit.inputs.iter().sum::<usize>();
+    let eq_gates = circuit.gates.iter().filter(|gate| matches!(gate, Gate::Eq(..))).count();
+    if inputs.len() != input_wires || garbled.tables.len() != circuit.and_gates() || garbled.constants.len() != eq_gates {
+        return Err(OteError::MalformedMessage(format!(
+            "{} input labels, {} tables and {} constants for a circuit with {} inputs, {} AND and {} EQ gates",
+            inputs.len(),
+            garbled.tables.len(),
+            garbled.constants.len(),
+            input_wires,
+            circuit.and_gates(),
+            eq_gates
+        )));
+    }
+    let hash = Hash::new();
+    let mut wires = vec![0; circuit.wires];
+    wires[..input_wires].copy_from_slice(inputs);
+    let (mut tables, mut constants) = (garbled.tables.iter(), garbled.constants.iter());
+    for (j, gate) in circuit.gates.iter().enumerate() {
+        wires[gate.output()] = match *gate {
+            Gate::Xor(a, b, _) => wires[a] ^ wires[b],
+            Gate::Inv(a, _) | Gate::Eqw(a, _) => wires[a],
+            Gate::Eq(..) => *constants.next().expect("counted above"),
+            Gate::And(a, b, _) => {
+                let [t_g, t_e] = *tables.next().expect("counted above");
+                let (a, b) = (wires[a], wires[b]);
+                let w_g = hash.hash(a, 2 * j as u64) ^ times(colour(a), t_g);
+                let w_e = hash.hash(b, 2 * j as u64 + 1) ^ times(colour(b), t_e ^ a);
+                w_g ^ w_e
+            }
+        };
+    }
+    Ok(wires[circuit.output_wires()].to_vec())
+}
+
+pub fn decode(garbled: &GarbledCircuit, outputs: &[Label]) -> Vec<bool> {
+    outputs.iter().zip(&garbled.decoding).map(|(&w, &d)| colour(w) ^ d).collect()
+}
+
+fn check_inputs(circuit: &Circuit, party: usize, inputs: &[bool]) -> Result<(), OteError> {
+    if circuit.inputs.len() != 2 {
+        return Err(OteError::BadParameter(format!("Yao needs a circuit of 2 parties, not {}", circuit.inputs.len())));
+    }
+    if inputs.len() != circuit.inputs[party] {
+        return Err(OteError::BadParameter(format!(
+            "party {} has {} input bits but the circuit takes {}",
+            party,
+            inputs.len(),
+            circuit.inputs[party]
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Runs both parties in one process, with the evaluator's input labels transferred by
+ * `ote_IKNP::ote`, and returns the values of the output wires.
+ * inputs[0] are the garbler's bits and inputs[1] the evaluator's.
+ */
+pub fn yao<R: CryptoRng + RngCore>(circuit: &Circuit, inputs: [&[bool]; 2], k: usize, group: &SafePrimeGroup, rng: &mut R) -> Result<Vec<bool>, OteError> {
+    check_inputs(circuit, 0, inputs[0])?;
+    check_inputs(circuit, 1, inputs[1])?;
+    let (garbled, encoding) = garble(circuit, rng)?;
+    let mut labels = circuit.input_wires(0).zip(inputs[0]).map(|(w, &x)| encoding.label(w, x)).collect::<Vec<_>>();
+    let received = ote_IKNP::ote(encoding.label_pairs(circuit.input_wires(1)), inputs[1].to_vec(), k, group, rng)?;
+    for label in received {
+        labels.push(bits_to_label(&label)?);
+    }
+    Ok(decode(&garbled, &evaluate(circuit, &garbled, &labels)?))
+}
+
+fn labels_to_bytes(labels: &[Label]) -> Vec<u8> {
+    labels.iter().flat_map(|x| x.to_be_bytes()).collect()
+}
+
+fn labels_from_bytes(bytes: &[u8], n: usize, what: &str) -> Result<Vec<Label>, OteError> {
+    if bytes.len() != n * LABEL_BYTES {
+        return Err(OteError::MalformedMessage(format!("{} of {} bytes instead of {}", what, bytes.len(), n * LABEL_BYTES)));
+    }
+    Ok(bytes.chunks_exact(LABEL_BYTES).map(|x| Label::from_be_bytes(x.try_into().expect("chunks of 16 bytes"))).collect())
+}
+
+/**
+ * Garbler of a Yao evaluation over a channel; returns the values of the output wires.
+ * Messages: "yao tables", "yao constants", "yao decoding" and "yao garbler labels", then the
+ * OT extension for the evaluator's labels and "yao outputs" from the evaluator.
+ */
+pub async fn yao_garbler<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    circuit: &Circuit,
+    inputs: &[bool],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    check_inputs(circuit, 0, inputs)?;
+    let (garbled, encoding) = garble(circuit, rng)?;
+    chan.label("yao tables").send_bytes(&labels_to_bytes(&garbled.tables.concat())).await?;
+    chan.label("yao constants").send_bytes(&labels_to_bytes(&garbled.constants)).await?;
+    chan.label("yao decoding").send_bit_rows(std::slice::from_ref(&garbled.decoding)).await?;
+    let labels = circuit.input_wires(0).zip(inputs).map(|(w, &x)| encoding.label(w, x)).collect::<Vec<_>>();
+    chan.label("yao garbler labels").send_bytes(&labels_to_bytes(&labels)).await?;
+    if circuit.inputs[1] > 0 {
+        let sender = OtExtSender::new(config, group.clone());
+        sender.send(chan, encoding.label_pairs(circuit.input_wires(1)), rng).await?;
+    }
+    Ok(chan.label("yao outputs").recv_bit_rows(1, Some(garbled.decoding.len())).await?.remove(0))
+}
+
+// Evaluator of a Yao evaluation over a channel; returns the values of the output wires.
+pub async fn yao_evaluator<S: AsyncRead + AsyncWrite + Unpin, R: CryptoRng + RngCore>(
+    chan: &mut Channel<S>,
+    circuit: &Circuit,
+    inputs: &[bool],
+    config: OtExtConfig,
+    group: &SafePrimeGroup,
+    rng: &mut R,
+) -> Result<Vec<bool>, OteError> {
+    check_inputs(circuit, 1, inputs)?;
+    circuit.check()?;
+    let tables = labels_from_bytes(&chan.label("yao tables").recv_bytes().await?, 2 * circuit.and_gates(), "garbled tables")?;
+    let eq_gates = circuit.gates.iter().filter(|gate| matches!(gate, Gate::Eq(..))).count();
+    let constants = labels_from_bytes(&chan.label("yao constants").recv_bytes().await?, eq_gates, "constant labels")?;
+    let outputs = circuit.output_wires().len();
+    let decoding = chan.label("yao decoding").recv_bit_rows(1, Some(outputs)).await?.remove(0);
+    let garbled = GarbledCircuit {
+        tables: tables.chunks_exact(2).map(|x| [x[0], x[1]]).collect(),
+        constants,
+        decoding,
+    };
+    let mut labels = labels_from_bytes(&chan.label("yao garbler labels").recv_bytes().await?, circuit.inputs[0], "garbler labels")?;
+    if circuit.inputs[1] > 0 {
+        let receiver = OtExtReceiver::new(config, group.clone());
+        for label in receiver.receive(chan, inputs.to_vec(), rng).await? {
+            labels.push(bits_to_label(&label)?);
+        }
+    }
+    let result = decode(&garbled, &evaluate(circuit, &garbled, &labels)?);
+    chan.label("yao outputs").send_bit_rows(std::slice::from_ref(&result)).await?;
+    Ok(result)
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use rand::Rng;
+
+use ote::arith::{correlated_ots, multiply, multiply_receiver, multiply_sender, Modulus};
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::transcript::Direction;
+use ote::{OtExtConfig, OtExtSession, OteError, Protocol, SecurityParams};
+
+use common::{channels, runtime, seeded_rng};
+
+fn random_elements<R: Rng>(modulus: Modulus, n: usize, rng: &mut R) -> Vec<u64> {
+    (0..n)
+        .map(|_| match (modulus.prime_value(), modulus.bits()) {
+            (Some(p), _) => rng.gen_range(0..p),
+            (None, 64) => rng.gen(),
+            (None, k) => rng.gen_range(0..1 << k),
+        })
+        .collect()
+}
+
+#[test]
+fn shares_add_up_to_the_products() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let mut session = OtExtSession::new(protocol, 128, group, rng).unwrap();
+        let moduli = [
+            Modulus::power_of_two(64).unwrap(),
+            Modulus::power_of_two(16).unwrap(),
+            Modulus::power_of_two(1).unwrap(),
+            Modulus::prime(101).unwrap(),
+            Modulus::prime((1 << 61) - 1).unwrap(),
+            Modulus::prime(u64::MAX - 58).unwrap(),
+        ];
+        for modulus in moduli {
+            let mut x = random_elements(modulus, 200, rng);
+            let mut y = random_elements(modulus, 200, rng);
+            // The largest element and zero on both sides.
+            let max = modulus.neg(1);
+            x[..3].copy_from_slice(&[max, 0, max]);
+            y[..3].copy_from_slice(&[max, max, 0]);
+            let before = session.extended();
+            let (a, b) = multiply(&mut session, modulus, &x, &y).unwrap();
+            assert_eq!(session.extended() - before, 200 * modulus.bits());
+            for i in 0..200 {
+                assert_eq!(modulus.add(a[i], b[i]), modulus.mul(x[i], y[i]), "{:?}", modulus);
+            }
+        }
+    }
+}
+
+#[test]
+fn shares_over_a_channel_add_up_to_the_products() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (protocol, modulus) in [(Protocol::Iknp, Modulus::power_of_two(32).unwrap()), (Protocol::Alsz, Modulus::prime(65537).unwrap())] {
+        let config = OtExtConfig::new(protocol, SecurityParams::default());
+        let x = random_elements(modulus, 50, rng);
+        let y = random_elements(modulus, 50, rng);
+        let (a, b, transcript) = runtime().block_on(async {
+            let (mut chan_0, mut chan_1) = channels();
+            chan_0.record();
+            let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+            let (a, b) = tokio::try_join!(
+                multiply_sender(&mut chan_0, modulus, &x, config, group, rng_0),
+                multiply_receiver(&mut chan_1, modulus, &y, config, group, rng_1)
+            )
+            .unwrap();
+            (a, b, chan_0.take_transcript().unwrap())
+        });
+        for i in 0..50 {
+            assert_eq!(modulus.add(a[i], b[i]), modulus.mul(x[i], y[i]), "{:?}", modulus);
+        }
+        // Besides the base OTs the sender only sends one correction per COT.
+        let sent = transcript.messages().iter().filter(|m| m.direction == Direction::Sent).collect::<Vec<_>>();
+        assert_eq!(sent.iter().map(|m| m.label.as_str()).collect::<Vec<_>>(), ["base-ot keys", "cot corrections"]);
+        assert_eq!(sent[1].bytes.len(), 8 * 50 * modulus.bits());
+    }
+}
+
+#[test]
+fn correlated_ots_differ_by_the_correlation() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    let modulus = Modulus::prime(65537).unwrap();
+    let deltas = random_elements(modulus, 100, rng);
+    let choice = random_boolvec_len(100, rng);
+    let (m_0, outputs) = correlated_ots(&mut session, modulus, &deltas, &choice).unwrap();
+    for i in 0..100 {
+        let expected = if choice[i] { modulus.add(m_0[i], deltas[i]) } else { m_0[i] };
+        assert_eq!(outputs[i], expected);
+    }
+    assert_ne!(m_0[0], m_0[1]);
+}
+
+#[test]
+fn bad_moduli_and_values_are_rejected() {
+    for k in [0, 65] {
+        assert!(matches!(Modulus::power_of_two(k), Err(OteError::BadParameter(_))));
+    }
+    for p in [0, 1, 4, 100, u64::MAX] {
+        assert!(matches!(Modulus::prime(p), Err(OteError::BadParameter(_))));
+    }
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    let modulus = Modulus::prime(101).unwrap();
+    assert!(matches!(multiply(&mut session, modulus, &[101], &[1]), Err(OteError::BadParameter(_))));
+    assert!(matches!(multiply(&mut session, Modulus::power_of_two(8).unwrap(), &[1, 2], &[256, 0]), Err(OteError::BadParameter(_))));
+    assert!(matches!(multiply(&mut session, modulus, &[1], &[]), Err(OteError::BadParameter(_))));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+
+use ote::common::random_boolvec_len;
+use ote::ot_async;
+use ote::ot_primitive::{make_test_group, SafePrimeGroup, USIZE};
+use ote::{BaseOt, Channel, MessagePairs, OtExtConfig, OtExtReceiver, OtExtSender, OteError, Protocol, SecurityParams};
+
+use common::{channels, chosen, counting_messages, random_messages, runtime, seeded_rng, streams};
+
+// Stream that keeps a copy of everything written to it, so the transcripts of two runs can be compared.
+struct Recorder {
+    stream: DuplexStream,
+    written: Vec<u8>,
+}
+
+impl AsyncRead for Recorder {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Recorder {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.stream).poll_write(cx, buf))?;
+        this.written.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+fn recording_channels() -> (Channel<Recorder>, Channel<Recorder>) {
+    let (a, b) = streams();
+    let recorder = |stream| Channel::new(Recorder { stream, written: Vec::new() });
+    (recorder(a), recorder(b))
+}
+
+// Bytes written by the sender and by the receiver.
+type Transcript = (Vec<u8>, Vec<u8>);
+
+/**
+ * Runs both parties of the configured extension, the sender with `seeded_rng(seed)` and the
+ * receiver with `seeded_rng(seed + 1)`, and returns the receiver's output and the transcript.
+ */
+fn run_extension(config: OtExtConfig, group: &SafePrimeGroup, messages: MessagePairs, choice: Vec<bool>, seed: u64) -> Result<(Vec<Vec<bool>>, Transcript), OteError> {
+    let sender = OtExtSender::new(config, group.clone());
+    let receiver = OtExtReceiver::new(config, group.clone());
+    let (sender_rng, receiver_rng) = (&mut seeded_rng(seed), &mut seeded_rng(seed + 1));
+    runtime().block_on(async {
+        let (mut sender_chan, mut receiver_chan) = recording_channels();
+        // try_join drops the other party on the first error, which then sees its channel close instead of waiting forever.
+        let ((), received) = tokio::try_join!(
+            sender.send(&mut sender_chan, messages, sender_rng),
+            receiver.receive(&mut receiver_chan, choice, receiver_rng)
+        )?;
+        Ok((received, (sender_chan.into_inner().written, receiver_chan.into_inner().written)))
+    })
+}
+
+#[test]
+fn base_ot_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = make_test_group(rng).unwrap();
+    let base_ot = BaseOt::new(group);
+    let messages = (0..10u64).map(|x| (USIZE::from(x), USIZE::from(x + 1))).collect::<Vec<_>>();
+    let choice = random_boolvec_len(10, rng);
+    let receiver_rng = &mut seeded_rng(1);
+    let received = runtime().block_on(async {
+        let (mut sender_chan, mut receiver_chan) = recording_channels();
+        let (sent, received) = tokio::join!(
+            base_ot.send(&mut sender_chan, messages.clone(), rng),
+            base_ot.receive(&mut receiver_chan, choice.clone(), receiver_rng)
+        );
+        sent.unwrap();
+        received.unwrap()
+    });
+    for (i, x) in received.into_iter().enumerate() {
+        assert_eq!(x, if choice[i] { messages[i].1 } else { messages[i].0 });
+    }
+}
+
+#[test]
+fn extensions_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        for level in [128, 192, 256] {
+            for m in [0, 1, 1000] {
+                let config = OtExtConfig::new(protocol, SecurityParams::from_level(level).unwrap());
+                let messages = counting_messages(m);
+                let choice = random_boolvec_len(m, rng);
+                let (output, _) = run_extension(config, group, messages.clone(), choice.clone(), 2).unwrap();
+                assert_eq!(output, chosen(&messages, &choice));
+            }
+        }
+    }
+}
+
+#[test]
+fn long_messages_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(50, 1000, rng);
+    let choice = random_boolvec_len(50, rng);
+    let (output, _) = run_extension(OtExtConfig::default(), group, messages.clone(), choice.clone(), 2).unwrap();
+    assert_eq!(output, chosen(&messages, &choice));
+}
+
+#[test]
+fn seeded_runs_have_identical_transcripts() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(100, 64, rng);
+    let choice = random_boolvec_len(100, rng);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let config = OtExtConfig::new(protocol, SecurityParams::default());
+        let run = |seed| run_extension(config, group, messages.clone(), choice.clone(), seed).unwrap();
+        let (output, transcript) = run(2);
+        assert_eq!(output, chosen(&messages, &choice));
+        assert_eq!(run(2), (output.clone(), transcript.clone()));
+        let (other_output, other_transcript) = run(4);
+        assert_eq!(other_output, output);
+        assert_ne!(other_transcript.0, transcript.0);
+        assert_ne!(other_transcript.1, transcript.1);
+    }
+}
+
+#[test]
+fn sender_rejects_mismatched_messages() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = vec![(vec![false; 8], vec![true; 7])];
+    let result = runtime().block_on(async {
+        let (mut sender_chan, _receiver_chan) = recording_channels();
+        ot_async::iknp_send(&mut sender_chan, messages, 128, group, rng).await
+    });
+    assert!(matches!(result, Err(OteError::MessageLengthMismatch { index: 0, .. })));
+}
+
+#[test]
+fn security_params_are_validated() {
+    for level in [0, 64, 100, 129, 512, usize::MAX] {
+        assert!(matches!(SecurityParams::from_level(level), Err(OteError::BadParameter(_))));
+    }
+    assert!(matches!(SecurityParams::new(128, 20), Err(OteError::BadParameter(_))));
+    assert!(matches!(SecurityParams::new(128, 129), Err(OteError::BadParameter(_))));
+    let params = SecurityParams::from_level(192).unwrap();
+    assert_eq!((params.k(), params.statistical()), (192, 60));
+    assert_eq!(SecurityParams::default(), SecurityParams::from_level(128).unwrap());
+}
+
+#[test]
+fn frames_above_the_limit_are_rejected_before_reading() {
+    runtime().block_on(async {
+        let (mut sender, mut receiver) = channels();
+        assert_eq!(receiver.max_frame_bytes(), ote::channel::DEFAULT_MAX_FRAME_BYTES);
+        receiver.set_max_frame_bytes(4);
+        sender.send_bytes(&[1, 2, 3, 4]).await.unwrap();
+        assert_eq!(receiver.recv_bytes().await.unwrap(), [1, 2, 3, 4]);
+        sender.send_bytes(&[0; 5]).await.unwrap();
+        assert!(matches!(receiver.recv_bytes().await, Err(OteError::MalformedMessage(_))));
+
+        // A frame that ends early is an I/O error, whatever length it announced.
+        let (mut a, b) = streams();
+        let mut receiver = Channel::new(b);
+        tokio::io::AsyncWriteExt::write_all(&mut a, &[&10u64.to_be_bytes()[..], &[7; 3]].concat()).await.unwrap();
+        drop(a);
+        assert!(matches!(receiver.recv_bytes().await, Err(OteError::Io(_))));
+    });
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use ote::common::int_to_bool_vec;
+use ote::ot_primitive::{self, make_test_group, USIZE};
+use ote::OteError;
+
+use common::{chosen, random_messages, seeded_rng};
+
+#[test]
+fn base_ot_returns_chosen_messages() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = (0..10).map(|x| (int_to_bool_vec(x), int_to_bool_vec(x + 1))).collect::<Vec<_>>();
+    let choice = ote::common::random_boolvec_len(10, rng);
+    let output = ot_primitive::ote(messages.clone(), choice.clone(), 0, group, rng).unwrap();
+    assert_eq!(output, chosen(&messages, &choice));
+}
+
+#[test]
+fn base_ot_of_seeds() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for k in [128, 256] {
+        let messages = random_messages(4, k, rng);
+        let choice = vec![false, true, true, false];
+        let output = ot_primitive::ote(messages.clone(), choice.clone(), 0, group, rng).unwrap();
+        assert_eq!(output, chosen(&messages, &choice));
+    }
+}
+
+#[test]
+fn base_ot_rejects_bad_inputs() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(2, 64, rng);
+    assert!(matches!(
+        ot_primitive::ote(messages, vec![true], 0, group, rng),
+        Err(OteError::BadParameter(_))
+    ));
+    let messages = vec![(vec![false; 64], vec![false; 63])];
+    assert!(matches!(
+        ot_primitive::ote(messages, vec![true], 0, group, rng),
+        Err(OteError::MessageLengthMismatch { index: 0, .. })
+    ));
+    // Larger than the 383 bit order of the test group.
+    let messages = vec![(vec![true; 384], vec![true; 384])];
+    assert!(matches!(
+        ot_primitive::ote(messages, vec![true], 0, group, rng),
+        Err(OteError::BadParameter(_))
+    ));
+}
+
+#[test]
+fn secret_key_count_must_match_choices() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let sk = ot_primitive::create_secret_keys(group, 2, rng);
+    assert!(matches!(
+        ot_primitive::commit_choice(group, &sk, &vec![true; 3], rng),
+        Err(OteError::BadParameter(_))
+    ));
+    let keys = ot_primitive::commit_choice(group, &sk, &vec![true; 2], rng).unwrap();
+    let messages = vec![(USIZE::from(1u32), USIZE::from(2u32))];
+    assert!(matches!(ot_primitive::send(group, &keys, &messages, rng), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn group_elements_round_trip() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let sk = ot_primitive::create_secret_keys(group, 1, rng);
+    let keys = ot_primitive::commit_choice(group, &sk, &vec![false], rng).unwrap();
+    let bytes = ot_primitive::elem_to_bytes(&keys[0].0);
+    let elem = ot_primitive::elem_from_bytes(group, &bytes).unwrap();
+    assert_eq!(elem.retrieve(), keys[0].0.retrieve());
+    assert!(ot_primitive::elem_from_bytes(group, &bytes[1..]).is_none());
+    assert!(ot_primitive::elem_from_bytes(group, &[0xff; ot_primitive::ELEM_BYTES]).is_none());
+}
+
+#[test]
+fn seeded_rng_gives_identical_group_and_ciphertexts() {
+    let run = |seed| {
+        let rng = &mut seeded_rng(seed);
+        let group = &make_test_group(rng).unwrap();
+        let sk = ot_primitive::create_secret_keys(group, 8, rng);
+        let keys = ot_primitive::commit_choice(group, &sk, &vec![true, false, false, true, true, true, false, false], rng).unwrap();
+        let messages = (0..8u32).map(|x| (USIZE::from(x), USIZE::from(x + 8))).collect::<Vec<_>>();
+        let encrypted = ot_primitive::send(group, &keys, &messages, rng).unwrap();
+        keys.iter()
+            .flat_map(|(k_0, k_1)| [k_0, k_1])
+            .chain(encrypted.iter().flat_map(|((c_0, d_0), (c_1, d_1))| [c_0, d_0, c_1, d_1]))
+            .flat_map(ot_primitive::elem_to_bytes)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(run(0), run(0));
+    assert_ne!(run(0), run(1));
+}
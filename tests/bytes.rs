@@ -0,0 +1,120 @@
+mod common;
+
+use rand::Rng;
+
+use ote::bytes::{alsz, base_ot, choice_bytes, iknp, max_base_ot_bytes, pack_choice, unpack_choice};
+use ote::ot_primitive::make_test_group;
+use ote::{BaseOt, OtExtConfig, OtExtReceiver, OtExtSender, OteError, Protocol, SecurityParams};
+
+use common::{channels, runtime, seeded_rng};
+
+type BytePairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+fn random_pairs<R: Rng>(n: usize, len: usize, rng: &mut R) -> BytePairs {
+    let mut bytes = |_| (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+    (0..n).map(|i| (bytes(i), bytes(i))).collect()
+}
+
+fn chosen(messages: &BytePairs, choice: &[bool]) -> Vec<Vec<u8>> {
+    messages.iter().zip(choice).map(|((x_0, x_1), &b)| if b { x_1.clone() } else { x_0.clone() }).collect()
+}
+
+#[test]
+fn choice_bitmaps_are_least_significant_bit_first() {
+    assert_eq!(pack_choice(&[true, false, true]), vec![0b101]);
+    assert_eq!(pack_choice(&[false; 8]), vec![0]);
+    let nine = [true, false, false, false, false, false, false, true, true];
+    assert_eq!(pack_choice(&nine), vec![0b1000_0001, 0b1]);
+    assert_eq!(unpack_choice(&[0b1000_0001, 0b1], 9).unwrap(), nine);
+    assert_eq!(unpack_choice(&[0b101], 3).unwrap(), [true, false, true]);
+    assert!(unpack_choice(&[], 0).unwrap().is_empty());
+    assert_eq!((choice_bytes(0), choice_bytes(8), choice_bytes(9)), (0, 1, 2));
+    // Wrong lengths and set bits beyond the last choice are rejected.
+    for (bitmap, n) in [(vec![0b1], 9), (vec![0, 0], 8), (vec![0b1000], 3), (vec![0, 0b10], 9)] {
+        assert!(matches!(unpack_choice(&bitmap, n), Err(OteError::BadParameter(_))), "{:?} for {}", bitmap, n);
+    }
+}
+
+#[test]
+fn in_process_ots_of_byte_strings() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (n, len) in [(13, 32), (8, 1), (21, 0), (1, 300)] {
+        let messages = random_pairs(n, len, rng);
+        let choice = (0..n).map(|_| rng.gen()).collect::<Vec<bool>>();
+        let expected = chosen(&messages, &choice);
+        assert_eq!(iknp(&messages, &pack_choice(&choice), 128, group, rng).unwrap(), expected);
+        assert_eq!(alsz(&messages, &pack_choice(&choice), 128, group, rng).unwrap(), expected);
+        if len <= max_base_ot_bytes(group) {
+            assert_eq!(base_ot(&messages, &pack_choice(&choice), group, rng).unwrap(), expected);
+        }
+    }
+    // Slices work as messages as well.
+    let messages = [(&b"left"[..], &b"rite"[..]), (b"zero", b"one!")];
+    assert_eq!(alsz(&messages, &[0b10], 128, group, rng).unwrap(), [b"left".to_vec(), b"one!".to_vec()]);
+}
+
+#[test]
+fn bad_byte_inputs_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut messages = random_pairs(3, 4, rng);
+    messages[2].1.pop();
+    assert!(matches!(
+        iknp(&messages, &[0], 128, group, rng),
+        Err(OteError::MessageLengthMismatch { index: 2, expected: 4, len_0: 4, len_1: 3 })
+    ));
+    messages[2].1.push(0);
+    assert!(matches!(alsz(&messages, &[0, 0], 128, group, rng), Err(OteError::BadParameter(_))));
+    let long = random_pairs(2, max_base_ot_bytes(group) + 1, rng);
+    assert!(matches!(base_ot(&long, &[0b11], group, rng), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn async_extensions_with_byte_strings() {
+    let rng = &mut seeded_rng(0);
+    let group = make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let config = OtExtConfig::new(protocol, SecurityParams::default());
+        let (sender, receiver) = (OtExtSender::new(config, group.clone()), OtExtReceiver::new(config, group.clone()));
+        let messages = random_pairs(21, 5, rng);
+        let choice = (0..21).map(|_| rng.gen()).collect::<Vec<bool>>();
+        let bitmap = pack_choice(&choice);
+        let (sender_rng, receiver_rng) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        let received = runtime().block_on(async {
+            let (mut sender_chan, mut receiver_chan) = channels();
+            tokio::try_join!(
+                sender.send_bytes(&mut sender_chan, &messages, sender_rng),
+                receiver.receive_bytes(&mut receiver_chan, &bitmap, 21, receiver_rng)
+            )
+        });
+        assert_eq!(received.unwrap().1, chosen(&messages, &choice));
+    }
+}
+
+#[test]
+fn async_base_ot_with_byte_strings() {
+    let rng = &mut seeded_rng(0);
+    let group = make_test_group(rng).unwrap();
+    let base = BaseOt::new(group.clone());
+    let max = max_base_ot_bytes(&group);
+    for len in [0, 1, max] {
+        let messages = random_pairs(5, len, rng);
+        let choice = (0..5).map(|_| rng.gen()).collect::<Vec<bool>>();
+        let bitmap = pack_choice(&choice);
+        let (sender_rng, receiver_rng) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        let received = runtime().block_on(async {
+            let (mut sender_chan, mut receiver_chan) = channels();
+            tokio::try_join!(
+                base.send_bytes(&mut sender_chan, &messages, sender_rng),
+                base.receive_bytes(&mut receiver_chan, &bitmap, 5, len, receiver_rng)
+            )
+        });
+        assert_eq!(received.unwrap().1, chosen(&messages, &choice), "{} bytes", len);
+    }
+    let long = random_pairs(2, max + 1, rng);
+    let runtime = runtime();
+    let (mut sender_chan, mut receiver_chan) = channels();
+    assert!(matches!(runtime.block_on(base.send_bytes(&mut sender_chan, &long, rng)), Err(OteError::BadParameter(_))));
+    assert!(matches!(runtime.block_on(base.receive_bytes(&mut receiver_chan, &[0b01], 2, max + 1, rng)), Err(OteError::BadParameter(_))));
+}
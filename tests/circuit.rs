@@ -0,0 +1,110 @@
+mod common;
+
+use std::path::Path;
+
+use ote::circuit::{Circuit, Gate};
+use ote::gmw::gmw;
+use ote::ot_primitive::make_test_group;
+use ote::{OteError, Protocol};
+
+use common::seeded_rng;
+
+fn load(name: &str) -> Circuit {
+    Circuit::load(&Path::new("tests/circuits").join(name)).unwrap()
+}
+
+// Least significant bit first.
+fn bits(x: u64, n: usize) -> Vec<bool> {
+    (0..n).map(|i| x >> i & 1 == 1).collect()
+}
+
+fn value(bits: &[bool]) -> u64 {
+    bits.iter().rev().fold(0, |acc, &b| acc << 1 | b as u64)
+}
+
+#[test]
+fn reference_circuits_compute_their_functions() {
+    let adder = load("adder8.txt");
+    let compare = load("compare8.txt");
+    assert_eq!((adder.inputs.as_slice(), adder.outputs.as_slice()), (&[8, 8][..], &[8][..]));
+    for x in 0..256 {
+        for y in 0..256 {
+            let inputs = [bits(x, 8), bits(y, 8)];
+            assert_eq!(value(&adder.evaluate(&inputs).unwrap()[0]), (x + y) % 256);
+            assert_eq!(compare.evaluate(&inputs).unwrap(), [[x > y]]);
+        }
+    }
+
+    let misc = load("misc.txt");
+    assert_eq!(misc.gates.iter().filter(|gate| matches!(gate, Gate::And(..))).count(), 2);
+    for x in 0..4 {
+        for y in 0..4 {
+            let (a, b) = (bits(x, 2), bits(y, 2));
+            let output = misc.evaluate(&[a.clone(), b.clone()]).unwrap();
+            assert_eq!(output, [vec![a[0] & b[0], a[1] & b[1]], vec![a[0]]]);
+        }
+    }
+}
+
+/**
+ * Known answers of the published adder64.txt of https://nigelsmart.github.io/MPC-Circuits/,
+ * which has to be put into tests/circuits unmodified: cargo test --test circuit -- --ignored
+ */
+#[test]
+#[ignore = "needs the published adder64.txt in tests/circuits"]
+fn published_adder64_known_answers() {
+    let adder = load("adder64.txt");
+    assert_eq!((adder.inputs.as_slice(), adder.outputs.as_slice()), (&[64, 64][..], &[64][..]));
+    for (x, y, sum) in [
+        (0, 0, 0),
+        (1, u64::MAX, 0),
+        (u64::MAX, u64::MAX, u64::MAX - 1),
+        (1 << 63, 1 << 63, 0),
+        (0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210, u64::MAX),
+        (0xdead_beef_0000_0001, 0x0000_0001_ffff_ffff, 0xdead_bef1_0000_0000),
+    ] {
+        assert_eq!(value(&adder.evaluate(&[bits(x, 64), bits(y, 64)]).unwrap()[0]), sum, "{:#x} + {:#x}", x, y);
+    }
+}
+
+#[test]
+fn gmw_agrees_with_the_plaintext_evaluator() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (name, x, y) in [("adder8.txt", 77, 201), ("compare8.txt", 130, 129), ("misc.txt", 1, 3)] {
+        let circuit = load(name);
+        let n = circuit.inputs[0];
+        let (x, y) = (bits(x, n), bits(y, circuit.inputs[1]));
+        let expected = circuit.evaluate(&[x.clone(), y.clone()]).unwrap();
+        let output = gmw(&circuit, [&x, &y], Protocol::Alsz, 128, group, rng).unwrap();
+        assert_eq!(circuit.split_outputs(&output), expected, "{}", name);
+    }
+}
+
+#[test]
+fn malformed_circuits_are_rejected() {
+    let misc = std::fs::read_to_string("tests/circuits/misc.txt").unwrap();
+    assert_eq!(Circuit::parse(&misc).unwrap(), load("misc.txt"));
+    let bad = [
+        // Header with one gate too many.
+        misc.replacen("5 10", "6 10", 1),
+        misc.replace("INV", "NOT"),
+        misc.replace("1 1 1 4 EQ", "1 1 2 4 EQ"),
+        misc.replace("4 2 0 1 2 3 7 8 MAND", "4 2 0 1 2 7 8 MAND"),
+        // Reads wire 6 before the XOR writes it.
+        misc.replace("1 1 0 5 EQW", "1 1 6 5 EQW"),
+        // Writes the output wire 9 twice.
+        misc.replace("1 1 1 4 EQ", "1 1 1 9 EQ"),
+        misc.replace("2 2 2", "2 2"),
+        String::new(),
+        // Headers and gates with sizes that must not be allocated or added up as they are.
+        misc.replacen("5 10", "18446744073709551615 18446744073709551615", 1),
+        misc.replacen("5 10", "5 1000000000000", 1),
+        misc.replace("2 2 2", "2 18446744073709551615 2"),
+        misc.replace("1 1 0 5 EQW", "18446744073709551615 1 0 5 EQW"),
+    ];
+    for text in bad {
+        assert!(matches!(Circuit::parse(&text), Err(OteError::InvalidFile(_))), "{}", text);
+    }
+    assert!(matches!(load("misc.txt").evaluate(&[vec![true; 2]]), Err(OteError::BadParameter(_))));
+}
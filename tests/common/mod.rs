@@ -0,0 +1,54 @@
+// Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use tokio::io::DuplexStream;
+
+use ote::common::{int_to_boolvec_len, random_boolvec_len, MessagePairs, OUTPUT_SIZE};
+use ote::Channel;
+
+// Buffer of the in-memory pipe between the two parties of a test.
+const PIPE_BYTES: usize = 1 << 16;
+
+// Tests draw all randomness from a seeded rng, so a failing run can be repeated exactly.
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+pub fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().unwrap()
+}
+
+// The two ends of an in-memory pipe, for tests that wrap or write to the streams themselves.
+pub fn streams() -> (DuplexStream, DuplexStream) {
+    tokio::io::duplex(PIPE_BYTES)
+}
+
+// Channels of the two parties, connected to each other.
+pub fn channels() -> (Channel<DuplexStream>, Channel<DuplexStream>) {
+    let (a, b) = streams();
+    (Channel::new(a), Channel::new(b))
+}
+
+// m message pairs (x, x + 1) of OUTPUT_SIZE bits, so every message is different.
+pub fn counting_messages(m: usize) -> MessagePairs {
+    (0..m)
+        .map(|x| (int_to_boolvec_len(x, OUTPUT_SIZE), int_to_boolvec_len(x + 1, OUTPUT_SIZE)))
+        .collect()
+}
+
+pub fn random_messages(m: usize, len: usize, rng: &mut ChaCha20Rng) -> MessagePairs {
+    (0..m)
+        .map(|_| (random_boolvec_len(len, rng), random_boolvec_len(len, rng)))
+        .collect()
+}
+
+// The message the receiver should learn in every OT.
+pub fn chosen(messages: &MessagePairs, choice: &[bool]) -> Vec<Vec<bool>> {
+    messages
+        .iter()
+        .zip(choice)
+        .map(|((x_0, x_1), &b)| if b { x_1.clone() } else { x_0.clone() })
+        .collect()
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use ote::common::*;
+use ote::ot_primitive::{bool_vec_to_usize, usize_to_bool_vec_len, USIZE};
+use ote::precompute::{RotReceiverPool, RotSenderPool};
+use zeroize::ZeroizeOnDrop;
+use ote::{OteError, Precomputed};
+
+use common::seeded_rng;
+
+fn bits(s: &str) -> Vec<bool> {
+    s.chars().map(|c| c == '1').collect()
+}
+
+#[test]
+fn integers_to_bits_are_big_endian() {
+    assert_eq!(int_to_boolvec_len(5, 4), bits("0101"));
+    assert_eq!(int_to_boolvec_len(5, 2), bits("01"));
+    assert_eq!(int_to_boolvec_len(1, 0), bits(""));
+    assert_eq!(int_to_boolvec_len(usize::MAX, 70)[..6], bits("000000"));
+    assert_eq!(byte_to_boolvec(0b1000_0011), bits("10000011"));
+    assert_eq!(boolvec_to_u8(&bits("10000011")), 0b1000_0011);
+    let x = int_to_bool_vec(6);
+    assert_eq!(x.len(), 64);
+    assert_eq!(x[61..], bits("110"));
+    assert_eq!(int_vec_to_bool_vec(&[1, 2])[..64], int_to_bool_vec(1)[..]);
+}
+
+#[test]
+fn packing_pads_in_front() {
+    let rng = &mut seeded_rng(0);
+    assert_eq!(bool_vec_to_byte_vec(&bits("101")), vec![0b101]);
+    assert_eq!(bool_vec_to_byte_vec(&bits("1000000001")), vec![0b10, 1]);
+    assert_eq!(byte_vec_to_bool_vec(&[0b101]), bits("00000101"));
+    assert_eq!(byte_vec_to_bool_vec_len(&[0b101], 3), bits("101"));
+    assert_eq!(byte_vec_to_bool_vec_len(&[0b101], 10), bits("0000000101"));
+    for len in [0, 1, 7, 8, 9, 100] {
+        let v = random_boolvec_len(len, rng);
+        assert_eq!(v.len(), len);
+        assert_eq!(byte_vec_to_bool_vec_len(&bool_vec_to_byte_vec(&v), len), v);
+    }
+}
+
+#[test]
+fn xor_and_transpose() {
+    assert_eq!(xor_boolvec(&bits("1100"), &bits("1010")), bits("0110"));
+    assert_eq!(xor_boolvec(&bits("11"), &bits("101")), bits("01"));
+    let matrix = vec![bits("101"), bits("011")];
+    assert_eq!(transpose(&matrix), vec![bits("10"), bits("01"), bits("11")]);
+    assert_eq!(transpose(&transpose(&matrix)), matrix);
+    assert!(transpose(&[]).is_empty());
+}
+
+#[test]
+fn group_integers_round_trip() {
+    let rng = &mut seeded_rng(0);
+    let v = random_boolvec_len(200, rng);
+    let x = bool_vec_to_usize(&v).unwrap();
+    assert_eq!(usize_to_bool_vec_len(&x, 200), v);
+    assert_eq!(bool_vec_to_usize(&bits("11")).unwrap(), USIZE::from(3u32));
+    assert!(matches!(bool_vec_to_usize(&vec![true; 2049]), Err(OteError::BadParameter(_))));
+    let v = random_boolvec_len(2048, rng);
+    assert_eq!(usize_to_bool_vec_len(&bool_vec_to_usize(&v).unwrap(), 2048), v);
+}
+
+// Compiles only for types whose secrets are overwritten when they are dropped.
+fn wiped_on_drop<T: ZeroizeOnDrop>() {}
+
+#[test]
+fn secret_holders_are_wiped_on_drop() {
+    wiped_on_drop::<SecretBits>();
+    wiped_on_drop::<SecretRows>();
+    wiped_on_drop::<RotSenderPool>();
+    wiped_on_drop::<RotReceiverPool>();
+    wiped_on_drop::<Precomputed>();
+}
+
+#[test]
+fn to_array_checks_length() {
+    let a: [u8; 2] = to_array(vec![1, 2]).unwrap();
+    assert_eq!(a, [1, 2]);
+    assert!(matches!(to_array::<u8, 3>(vec![1, 2]), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn hash_has_requested_length() {
+    let rng = &mut seeded_rng(0);
+    let v = random_boolvec_len(128, rng);
+    let j = int_to_bool_vec(7);
+    for len in [0, 1, 255, 256, 257, 5000] {
+        assert_eq!(hash_bits(&v, &j, len).len(), len);
+    }
+    let long = hash_bits(&v, &j, 1000);
+    assert_eq!(hash_bits(&v, &j, 300), long[..300]);
+    assert_ne!(hash_bits(&v, &int_to_bool_vec(8), 300), long[..300]);
+}
+
+#[test]
+fn prg_streams_continue_across_calls() {
+    let rng = &mut seeded_rng(0);
+    for len in [128, 192, 256] {
+        let seed = random_boolvec_len(len, rng);
+        let all = Prg::new(&seed).unwrap().next_bits(1000);
+        let mut prg = Prg::new(&seed).unwrap();
+        let mut parts = Vec::new();
+        for n in [1, 7, 0, 300, 692] {
+            parts.extend(prg.next_bits(n));
+        }
+        assert_eq!(parts, all);
+        assert_eq!(pseudo_random_gen(&seed, 1000).unwrap(), all);
+    }
+    let seed = random_boolvec_len(128, rng);
+    assert_eq!(
+        pseudo_random_gen_cha_cha(&seed, 100).unwrap(),
+        Prg::new_cha_cha(&seed).unwrap().next_bits(100)
+    );
+    assert_ne!(pseudo_random_gen_cha_cha(&seed, 100).unwrap(), pseudo_random_gen_aes(&seed, 100).unwrap());
+}
+
+#[test]
+fn prg_rejects_bad_seeds() {
+    for len in [0, 64, 129, 512] {
+        assert!(matches!(Prg::new(&vec![false; len]), Err(OteError::BadParameter(_))));
+    }
+    assert!(matches!(Prg::new_aes(&[false; 192]), Err(OteError::BadParameter(_))));
+    assert!(matches!(Prg::new_cha_cha(&[false; 192]), Err(OteError::BadParameter(_))));
+    assert!(Prg::new_shake(&[false; 192]).is_ok());
+}
+
+#[test]
+fn message_length_checks() {
+    assert_eq!(check_message_lengths(&[]).unwrap(), 0);
+    let messages = vec![(bits("10"), bits("01")), (bits("11"), bits("00"))];
+    assert_eq!(check_message_lengths(&messages).unwrap(), 2);
+    assert_eq!(check_inputs(&messages, &[true, false]).unwrap(), 2);
+    assert!(matches!(check_inputs(&messages, &[true]), Err(OteError::BadParameter(_))));
+    let messages = vec![(bits("10"), bits("01")), (bits("11"), bits("0"))];
+    assert!(matches!(
+        check_message_lengths(&messages),
+        Err(OteError::MessageLengthMismatch { index: 1, expected: 2, len_0: 2, len_1: 1 })
+    ));
+}
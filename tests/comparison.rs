@@ -0,0 +1,76 @@
+mod common;
+
+use rand::Rng;
+
+use ote::comparison::{less_than, less_than_receiver, less_than_sender, ComparisonConfig};
+use ote::ot_primitive::make_test_group;
+use ote::{OtExtConfig, OtExtSession, OteError, Protocol, SecurityParams};
+
+use common::{channels, runtime, seeded_rng};
+
+fn random_values<R: Rng>(bits: usize, n: usize, rng: &mut R) -> Vec<u64> {
+    (0..n).map(|_| if bits == 64 { rng.gen() } else { rng.gen_range(0..1 << bits) }).collect()
+}
+
+#[test]
+fn shares_reconstruct_to_the_comparison() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    // Digits that divide the bits, that do not, one digit for everything and single bits.
+    for (bits, digit_bits, batch) in [(64, 4, 1024), (32, 5, 7), (8, 8, 100), (16, 1, 50), (1, 3, 10)] {
+        let config = ComparisonConfig::new(bits, digit_bits, batch).unwrap();
+        let mut x = random_values(bits, 100, rng);
+        let mut y = random_values(bits, 100, rng);
+        // Equal values, and values that differ in the lowest bit only.
+        y[0] = x[0];
+        x[1] = y[1] | 1;
+        y[1] &= !1;
+        let (a, b) = less_than(&mut session, &config, &x, &y, rng).unwrap();
+        for i in 0..100 {
+            assert_eq!(a[i] ^ b[i], x[i] < y[i], "{} < {} with {:?}", x[i], y[i], config);
+        }
+        // The sender's shares alone are random.
+        assert!(a.contains(&true) && a.contains(&false));
+    }
+}
+
+#[test]
+fn shares_over_a_channel_reconstruct_to_the_comparison() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    // Three batches, the last one short, and digits that do not divide the bits.
+    let config = ComparisonConfig::new(16, 5, 7).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let ot_config = OtExtConfig::new(protocol, SecurityParams::default());
+        let x = random_values(16, 20, rng);
+        let mut y = random_values(16, 20, rng);
+        y[0] = x[0];
+        let (a, b) = runtime().block_on(async {
+            let (mut chan_0, mut chan_1) = channels();
+            let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+            tokio::try_join!(
+                less_than_sender(&mut chan_0, &config, &x, ot_config, group, rng_0),
+                less_than_receiver(&mut chan_1, &config, &y, ot_config, group, rng_1)
+            )
+            .unwrap()
+        });
+        for i in 0..20 {
+            assert_eq!(a[i] ^ b[i], x[i] < y[i], "{} < {} with {:?}", x[i], y[i], protocol);
+        }
+    }
+}
+
+#[test]
+fn bad_configurations_and_values_are_rejected() {
+    for (bits, digit_bits, batch) in [(0, 4, 1), (65, 4, 1), (64, 0, 1), (64, 17, 1), (64, 4, 0)] {
+        assert!(matches!(ComparisonConfig::new(bits, digit_bits, batch), Err(OteError::BadParameter(_))));
+    }
+    assert_eq!(ComparisonConfig::default(), ComparisonConfig::new(64, 4, 1024).unwrap());
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    let config = ComparisonConfig::new(8, 4, 10).unwrap();
+    assert!(matches!(less_than(&mut session, &config, &[256], &[1], rng), Err(OteError::BadParameter(_))));
+    assert!(matches!(less_than(&mut session, &config, &[1, 2], &[1], rng), Err(OteError::BadParameter(_))));
+}
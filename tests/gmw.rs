@@ -0,0 +1,172 @@
+mod common;
+
+use ote::circuit::{Circuit, Gate};
+use ote::gmw::{gmw, triples};
+use ote::ot_primitive::make_test_group;
+use ote::transcript::Direction;
+use ote::{gmw_party, OtExtConfig, OteError, Protocol};
+
+use common::{channels, runtime, seeded_rng};
+
+fn bits(x: u64, n: usize) -> Vec<bool> {
+    (0..n).map(|i| x >> i & 1 == 1).collect()
+}
+
+fn value(bits: &[bool]) -> u64 {
+    bits.iter().rev().fold(0, |acc, &b| acc << 1 | b as u64)
+}
+
+/**
+ * Ripple-carry adder of two n-bit numbers mod 2^n, least significant bit first. Its AND depth is n - 1.
+ * Party 0 inputs x on wires 0..n, party 1 inputs y on wires n..2n, the sum is on the last n wires.
+ */
+fn adder(n: usize) -> Circuit {
+    let intermediate = 5 * n;
+    let wires = 2 * n + intermediate + n;
+    let out = |i: usize| wires - n + i;
+    let mut next = 2 * n;
+    let mut fresh = || {
+        next += 1;
+        next - 1
+    };
+    let mut gates = Vec::new();
+    let mut carry = None;
+    for i in 0..n {
+        let (x, y) = (i, n + i);
+        let xy = fresh();
+        gates.push(Gate::Xor(x, y, xy));
+        match carry {
+            None => {
+                gates.push(Gate::Xor(x, y, out(i)));
+                if i + 1 == n {
+                    break;
+                }
+                let c = fresh();
+                gates.push(Gate::And(x, y, c));
+                carry = Some(c);
+            }
+            Some(c) => {
+                gates.push(Gate::Xor(xy, c, out(i)));
+                if i + 1 == n {
+                    break;
+                }
+                // c' = (x and y) xor (c and (x xor y))
+                let (both, prop, c_next) = (fresh(), fresh(), fresh());
+                gates.push(Gate::And(x, y, both));
+                gates.push(Gate::And(c, xy, prop));
+                gates.push(Gate::Xor(both, prop, c_next));
+                carry = Some(c_next);
+            }
+        }
+    }
+    Circuit {
+        wires,
+        inputs: vec![n, n],
+        outputs: vec![n],
+        gates,
+    }
+}
+
+// NOT of the AND of all 2n input bits, as a balanced tree of depth log 2n.
+fn nand_tree(n: usize) -> Circuit {
+    let mut gates = Vec::new();
+    let mut layer = (0..2 * n).collect::<Vec<_>>();
+    let mut next = 2 * n;
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| match *pair {
+                [a, b] => {
+                    gates.push(Gate::And(a, b, next));
+                    next += 1;
+                    next - 1
+                }
+                [a] => a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    gates.push(Gate::Inv(layer[0], next));
+    Circuit {
+        wires: next + 1,
+        inputs: vec![n, n],
+        outputs: vec![1],
+        gates,
+    }
+}
+
+#[test]
+fn triples_multiply() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let (t_0, t_1) = triples(300, protocol, 128, group, rng).unwrap();
+        assert_eq!((t_0.len(), t_1.len()), (300, 300));
+        for i in 0..300 {
+            let (a, b) = (t_0.a[i] ^ t_1.a[i], t_0.b[i] ^ t_1.b[i]);
+            assert_eq!(t_0.c[i] ^ t_1.c[i], a & b);
+        }
+        // The shares are random, not all zero.
+        assert!(t_0.a.contains(&true) && t_1.b.contains(&true) && t_0.c.contains(&true));
+    }
+}
+
+#[test]
+fn gmw_matches_plaintext() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let circuit = adder(16);
+    circuit.check().unwrap();
+    assert_eq!(circuit.layers().len(), 16);
+    for (x, y) in [(0, 0), (12345, 54321), (0xffff, 1)] {
+        let sum = gmw(&circuit, [&bits(x, 16), &bits(y, 16)], Protocol::Alsz, 128, group, rng).unwrap();
+        assert_eq!(value(&sum), (x + y) % (1 << 16));
+    }
+
+    let circuit = nand_tree(5);
+    let ones = vec![true; 5];
+    let mut some = ones.clone();
+    some[3] = false;
+    assert_eq!(gmw(&circuit, [&ones, &ones], Protocol::Iknp, 128, group, rng).unwrap(), [false]);
+    assert_eq!(gmw(&circuit, [&ones, &some], Protocol::Iknp, 128, group, rng).unwrap(), [true]);
+}
+
+#[test]
+fn bad_circuits_and_inputs_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut circuit = adder(4);
+    assert!(matches!(
+        gmw(&circuit, [&bits(1, 4), &bits(1, 3)], Protocol::Alsz, 128, group, rng),
+        Err(OteError::BadParameter(_))
+    ));
+    // Reading a wire before it is written.
+    circuit.gates.swap(2, 4);
+    assert!(matches!(circuit.check(), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn gmw_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let circuit = adder(8);
+    let config = OtExtConfig::default();
+    let (x, y) = (200, 99);
+    let (sum_0, sum_1, transcript) = runtime().block_on(async {
+        let (mut chan_0, mut chan_1) = channels();
+        chan_0.record();
+        let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        let (input_0, input_1) = (bits(x, 8), bits(y, 8));
+        let (sum_0, sum_1) = tokio::try_join!(
+            gmw_party(&mut chan_0, &circuit, 0, &input_0, config, group, rng_0),
+            gmw_party(&mut chan_1, &circuit, 1, &input_1, config, group, rng_1)
+        )
+        .unwrap();
+        (sum_0, sum_1, chan_0.take_transcript().unwrap())
+    });
+    assert_eq!(value(&sum_0), (x + y) % 256);
+    assert_eq!(sum_0, sum_1);
+    // One round of openings per layer with AND gates.
+    let openings = transcript.messages().iter().filter(|m| m.label == "gmw d,e" && m.direction == Direction::Sent).count();
+    assert_eq!(openings, circuit.layers().iter().filter(|(and_gates, _)| !and_gates.is_empty()).count());
+}
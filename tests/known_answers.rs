@@ -0,0 +1,151 @@
+// Replays IKNP and ALSZ against the frozen vectors in tests/vectors/extension_kat.json.
+// Any change to `transpose`, `hash_bits`, the PRGs or the base OT that alters a single bit fails here.
+// After an intended change of the outputs, regenerate the file with
+// OTE_UPDATE_VECTORS=1 cargo test --test known_answers
+mod common;
+
+use serde::{Deserialize, Serialize};
+
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::{elem_to_bytes, make_test_group, PublicKey};
+use ote::trace::{trace, Trace};
+use ote::{ot_better_network, ote_IKNP, Protocol};
+
+use common::{chosen, random_messages, seeded_rng};
+
+const VECTOR_FILE: &str = "tests/vectors/extension_kat.json";
+
+/**
+ * One known-answer vector. The run is `trace` with the rng `seeded_rng(seed)`, after it made
+ * the test group. Bit strings are written as '0' and '1' characters, group elements as
+ * big-endian hex without leading zero bytes.
+ */
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Vector {
+    protocol: String,
+    seed: u64,
+    k: usize,
+    messages: Vec<(String, String)>,
+    choice: String,
+    base_ot_keys: Vec<(String, String)>,
+    u: Vec<String>,
+    q: Vec<String>,
+    t: Vec<String>,
+    y: Vec<(String, String)>,
+    output: Vec<String>,
+}
+
+fn to_bits(v: &[bool]) -> String {
+    v.iter().map(|&b| if b { '1' } else { '0' }).collect()
+}
+
+fn from_bits(s: &str) -> Vec<bool> {
+    s.chars().map(|c| c == '1').collect()
+}
+
+fn to_hex(e: &PublicKey) -> String {
+    let bytes = elem_to_bytes(e);
+    let first = bytes.iter().position(|&x| x != 0).unwrap_or(bytes.len());
+    bytes[first..].iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn protocol_name(protocol: Protocol) -> String {
+    match protocol {
+        Protocol::Iknp => "IKNP".to_owned(),
+        Protocol::Alsz => "ALSZ".to_owned(),
+    }
+}
+
+fn pairs(v: &[(Vec<bool>, Vec<bool>)]) -> Vec<(String, String)> {
+    v.iter().map(|(x_0, x_1)| (to_bits(x_0), to_bits(x_1))).collect()
+}
+
+fn rows(v: &[Vec<bool>]) -> Vec<String> {
+    v.iter().map(|x| to_bits(x)).collect()
+}
+
+// Runs the vector's inputs and returns the vector as computed by the code under test.
+fn replay(protocol: Protocol, seed: u64, k: usize, messages: Vec<(Vec<bool>, Vec<bool>)>, choice: Vec<bool>) -> Vector {
+    let rng = &mut seeded_rng(seed);
+    let group = &make_test_group(rng).unwrap();
+    let Trace { base_ot_keys, u, q, t, y, output } = trace(protocol, &messages, &choice, k, group, rng).unwrap();
+    Vector {
+        protocol: protocol_name(protocol),
+        seed,
+        k,
+        messages: pairs(&messages),
+        choice: to_bits(&choice),
+        base_ot_keys: base_ot_keys.iter().map(|(k_0, k_1)| (to_hex(k_0), to_hex(k_1))).collect(),
+        u: rows(&u),
+        q: rows(&q),
+        t: rows(&t),
+        y: pairs(&y),
+        output: rows(&output),
+    }
+}
+
+// Six OTs of 300 bit messages, so the pads cover more than one SHAKE256 block of output.
+fn fresh_vectors() -> Vec<Vector> {
+    [(Protocol::Iknp, 1), (Protocol::Alsz, 2)]
+        .into_iter()
+        .map(|(protocol, seed)| {
+            let inputs = &mut seeded_rng(1000 + seed);
+            let messages = random_messages(6, 300, inputs);
+            let choice = random_boolvec_len(6, inputs);
+            replay(protocol, seed, 128, messages, choice)
+        })
+        .collect()
+}
+
+#[test]
+fn extensions_match_known_answers() {
+    if std::env::var_os("OTE_UPDATE_VECTORS").is_some() {
+        let json = serde_json::to_string_pretty(&fresh_vectors()).unwrap();
+        std::fs::write(VECTOR_FILE, json + "\n").unwrap();
+    }
+    let vectors: Vec<Vector> = serde_json::from_str(&std::fs::read_to_string(VECTOR_FILE).unwrap()).unwrap();
+    assert_eq!(vectors.len(), 2);
+    for expected in vectors {
+        let protocol = match expected.protocol.as_str() {
+            "IKNP" => Protocol::Iknp,
+            "ALSZ" => Protocol::Alsz,
+            other => panic!("unknown protocol {}", other),
+        };
+        let messages = expected.messages.iter().map(|(x_0, x_1)| (from_bits(x_0), from_bits(x_1))).collect::<Vec<_>>();
+        let choice = from_bits(&expected.choice);
+        let actual = replay(protocol, expected.seed, expected.k, messages.clone(), choice.clone());
+        // Field by field, so a failure names the first step that changed.
+        let name = &expected.protocol;
+        assert_eq!(actual.base_ot_keys, expected.base_ot_keys, "base OT keys of {}", name);
+        assert_eq!(actual.u, expected.u, "u of {}", name);
+        assert_eq!(actual.q, expected.q, "q of {}", name);
+        assert_eq!(actual.t, expected.t, "t of {}", name);
+        assert_eq!(actual.y, expected.y, "y of {}", name);
+        assert_eq!(actual.output, expected.output, "output of {}", name);
+        assert_eq!(actual, expected);
+        assert_eq!(rows(&chosen(&messages, &choice)), expected.output);
+    }
+}
+
+#[test]
+fn trace_matches_ote() {
+    let rng = &mut seeded_rng(0);
+    let messages = random_messages(20, 64, rng);
+    let choice = random_boolvec_len(20, rng);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let rng = &mut seeded_rng(5);
+        let group = &make_test_group(rng).unwrap();
+        let traced = trace(protocol, &messages, &choice, 128, group, rng).unwrap();
+        let rng = &mut seeded_rng(5);
+        let group = &make_test_group(rng).unwrap();
+        let output = match protocol {
+            Protocol::Iknp => ote_IKNP::ote(messages.clone(), choice.clone(), 128, group, rng),
+            Protocol::Alsz => ot_better_network::ote(messages.clone(), choice.clone(), 128, group, rng),
+        };
+        assert_eq!(output.unwrap(), traced.output);
+        // t_j = q_j xor (r_j * s) for every row.
+        for ((q_j, t_j), &r_j) in traced.q.iter().zip(traced.t.iter()).zip(&choice) {
+            assert_eq!(q_j == t_j, !r_j);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+use ote::oprf::{oprf_query, oprf_serve, OprfClient, OprfServer, ServeLimits, OUTPUT_BYTES};
+use ote::ot_primitive::{make_test_group, ELEM_BYTES};
+use ote::OteError;
+
+use common::{runtime, seeded_rng};
+
+fn inputs(range: std::ops::Range<u32>) -> Vec<Vec<u8>> {
+    range.map(|x| format!("password {}", x).into_bytes()).collect()
+}
+
+#[test]
+fn client_gets_the_prf_values() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let server = OprfServer::new(group, rng);
+    let x = [inputs(0..20), inputs(3..4), vec![vec![]]].concat();
+    let (client, request) = OprfClient::request(group, &x, rng);
+    let values = client.finish(&server.respond(&request).unwrap()).unwrap();
+    assert_eq!(values, x.iter().map(|x| server.evaluate(x)).collect::<Vec<_>>());
+    assert!(values.iter().all(|v| v.len() == OUTPUT_BYTES));
+    // Repeated inputs get the same value, under fresh blinds as well.
+    assert_eq!(values[3], values[20]);
+    let (client, again) = OprfClient::request(group, &x[..1], rng);
+    assert_ne!(again[..], request[..ELEM_BYTES]);
+    assert_eq!(client.finish(&server.respond(&again).unwrap()).unwrap()[0], values[0]);
+    // Another key is another function.
+    assert_ne!(OprfServer::new(group, rng).evaluate(&x[0]), values[0]);
+}
+
+#[test]
+fn bad_requests_and_responses_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let server = OprfServer::new(group, rng);
+    let (_, request) = OprfClient::request(group, &inputs(0..2), rng);
+    assert!(matches!(server.respond(&request[1..]), Err(OteError::MalformedMessage(_))));
+    // Zero is below p but not in the group, and all ones is not reduced modulo p.
+    for bad in [vec![0; ELEM_BYTES], [request[..ELEM_BYTES].to_vec(), vec![0xff; ELEM_BYTES]].concat()] {
+        assert!(matches!(server.respond(&bad), Err(OteError::MalformedMessage(_))));
+    }
+    let response = server.respond(&request).unwrap();
+    let (client, _) = OprfClient::request(group, &inputs(0..3), rng);
+    assert!(matches!(client.finish(&response), Err(OteError::MalformedMessage(_))));
+}
+
+#[test]
+fn queries_over_tcp_on_localhost() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let server = Arc::new(OprfServer::new(group, rng));
+    let x = inputs(0..10);
+    let expected = x.iter().map(|x| server.evaluate(x)).collect::<Vec<_>>();
+    runtime().block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(oprf_serve(listener, server, ServeLimits::default(), |peer, e| panic!("{:?}: {}", peer, e)));
+        assert_eq!(oprf_query(addr, group, &x, rng).await.unwrap(), expected);
+        assert_eq!(oprf_query(addr, group, &x[4..6], rng).await.unwrap(), expected[4..6]);
+        assert!(oprf_query(addr, group, &[], rng).await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn server_enforces_its_limits() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let server = Arc::new(OprfServer::new(group, rng));
+    let limits = ServeLimits {
+        max_connections: 1,
+        timeout: Duration::from_millis(200),
+        max_elements: 4,
+    };
+    let (reports, failures) = mpsc::channel();
+    let report = move |_, e| reports.send(e).unwrap();
+    let x = inputs(0..5);
+    runtime().block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(oprf_serve(listener, server.clone(), limits, report));
+        assert!(oprf_query(addr, group, &x, rng).await.is_err());
+        assert!(matches!(failures.recv().unwrap(), OteError::MalformedMessage(_)));
+        // A client that never finishes its request holds the only slot until it times out.
+        let mut stalled = TcpStream::connect(addr).await.unwrap();
+        stalled.write_all(&[0; 3]).await.unwrap();
+        assert_eq!(oprf_query(addr, group, &x[..4], rng).await.unwrap(), x[..4].iter().map(|x| server.evaluate(x)).collect::<Vec<_>>());
+        assert!(matches!(failures.recv().unwrap(), OteError::Io(e) if e.kind() == std::io::ErrorKind::TimedOut));
+    });
+}
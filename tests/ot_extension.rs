@@ -0,0 +1,140 @@
+mod common;
+
+use rand_chacha::ChaCha20Rng;
+
+use ote::common::{random_boolvec_len, MessagePairs};
+use ote::ot_primitive::{make_test_group, SafePrimeGroup};
+use ote::{ot_better_network, ote_IKNP, OteError};
+
+use common::{chosen, counting_messages, random_messages, seeded_rng};
+
+type OteFn = fn(MessagePairs, Vec<bool>, usize, &SafePrimeGroup, &mut ChaCha20Rng) -> Result<Vec<Vec<bool>>, OteError>;
+type StreamingFn = fn(Vec<(MessagePairs, Vec<bool>)>, usize, &SafePrimeGroup, &mut ChaCha20Rng, &mut dyn FnMut(Vec<Vec<bool>>)) -> Result<(), OteError>;
+
+const PROTOCOLS: [(&str, OteFn); 2] = [("IKNP", ote_IKNP::ote), ("ALSZ", ot_better_network::ote)];
+
+fn streaming_protocols() -> [(&'static str, StreamingFn); 2] {
+    [
+        ("IKNP", |chunks, k, group, rng, f| ote_IKNP::ote_streaming(chunks, k, group, rng, f)),
+        ("ALSZ", |chunks, k, group, rng, f| ot_better_network::ote_streaming(chunks, k, group, rng, f)),
+    ]
+}
+
+fn check(ote: OteFn, messages: MessagePairs, k: usize, group: &SafePrimeGroup, rng: &mut ChaCha20Rng) {
+    let choice = random_boolvec_len(messages.len(), rng);
+    let output = ote(messages.clone(), choice.clone(), k, group, rng).unwrap();
+    assert_eq!(output, chosen(&messages, &choice));
+}
+
+#[test]
+fn correct_for_all_sizes() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        for k in [128, 256] {
+            for m in [1, 2, 127, 128, 129, 10000] {
+                check(ote, counting_messages(m), k, group, rng);
+            }
+        }
+    }
+}
+
+#[test]
+fn correct_for_k_192() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        check(ote, counting_messages(1000), 192, group, rng);
+    }
+}
+
+#[test]
+fn zero_ots() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        assert!(ote(Vec::new(), Vec::new(), 128, group, rng).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn any_message_length() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        for len in [1, 1000] {
+            check(ote, random_messages(100, len, rng), 128, group, rng);
+        }
+    }
+}
+
+#[test]
+fn mismatched_lengths_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        let messages = vec![(vec![false; 300], vec![true; 300]), (vec![false; 300], vec![true; 299])];
+        assert!(matches!(
+            ote(messages, vec![false, true], 128, group, rng),
+            Err(OteError::MessageLengthMismatch { index: 1, expected: 300, len_0: 300, len_1: 299 })
+        ));
+        let messages = vec![(vec![false; 8], vec![true; 8]), (vec![false; 9], vec![true; 9])];
+        assert!(matches!(
+            ote(messages, vec![false, true], 128, group, rng),
+            Err(OteError::MessageLengthMismatch { index: 1, expected: 8, len_0: 9, len_1: 9 })
+        ));
+        assert!(matches!(
+            ote(counting_messages(3), vec![true; 2], 128, group, rng),
+            Err(OteError::BadParameter(_))
+        ));
+    }
+}
+
+#[test]
+fn unsupported_k_is_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote) in PROTOCOLS {
+        for k in [0, 100, 129, 512] {
+            assert!(matches!(ote(counting_messages(1), vec![false], k, group, rng), Err(OteError::BadParameter(_))));
+        }
+    }
+}
+
+#[test]
+fn streaming_matches_one_shot() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = counting_messages(10000);
+    let choice = random_boolvec_len(10000, rng);
+    for (_, ote_streaming) in streaming_protocols() {
+        for chunk_size in [999, 1] {
+            let chunks = messages
+                .chunks(chunk_size)
+                .zip(choice.chunks(chunk_size))
+                .map(|(x, c)| (x.to_vec(), c.to_vec()))
+                .take(if chunk_size == 1 { 50 } else { usize::MAX })
+                .collect::<Vec<_>>();
+            let total = chunks.iter().map(|(x, _)| x.len()).sum::<usize>();
+            let mut output = Vec::new();
+            ote_streaming(chunks, 128, group, rng, &mut |z| output.extend(z)).unwrap();
+            assert_eq!(output, chosen(&messages[..total].to_vec(), &choice[..total]));
+        }
+    }
+}
+
+#[test]
+fn streaming_rejects_bad_chunk() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for (_, ote_streaming) in streaming_protocols() {
+        let chunks = vec![
+            (counting_messages(5), vec![true; 5]),
+            (counting_messages(5), vec![true; 4]),
+        ];
+        let mut outputs = 0;
+        let result = ote_streaming(chunks, 128, group, rng, &mut |_| outputs += 1);
+        assert!(matches!(result, Err(OteError::BadParameter(_))));
+        assert_eq!(outputs, 1);
+    }
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use rand::Rng;
+
+use ote::common::random_boolvec_len;
+use ote::ot_n::{choice_bits, ot_n};
+use ote::ot_primitive::make_test_group;
+use ote::{OtExtSession, OteError, Protocol};
+
+use common::seeded_rng;
+
+#[test]
+fn receiver_gets_the_chosen_rows() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    assert_eq!([1, 2, 3, 4, 5, 1024].map(choice_bits), [0, 1, 2, 2, 3, 10]);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let mut session = OtExtSession::new(protocol, 128, group, rng).unwrap();
+        let sizes = [1, 2, 3, 8, 17, 256];
+        let tables = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| (0..n).map(|_| random_boolvec_len(10 * i + 1, rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let choices = sizes.iter().map(|&n| rng.gen_range(0..n)).collect::<Vec<_>>();
+        let output = ot_n(&mut session, &tables, &choices, rng).unwrap();
+        for ((table, &c), row) in tables.iter().zip(&choices).zip(&output) {
+            assert_eq!(row, &table[c]);
+        }
+        assert_eq!(session.extended(), sizes.map(choice_bits).iter().sum::<usize>());
+    }
+}
+
+#[test]
+fn bad_tables_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    let table = vec![vec![true; 4], vec![false; 4], vec![true; 4]];
+    for (tables, choices) in [
+        (vec![table.clone()], vec![3]),
+        (vec![table.clone()], vec![0, 1]),
+        (vec![vec![vec![true; 4], vec![true; 3]]], vec![0]),
+        (vec![vec![]], vec![0]),
+    ] {
+        assert!(matches!(ot_n(&mut session, &tables, &choices, rng), Err(OteError::BadParameter(_))));
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use rand::Rng;
+
+use ote::ot_primitive::make_test_group;
+use ote::pir::retrieve;
+use ote::{pir_client, pir_server, OtExtConfig, OtExtSession, OteError, Protocol};
+
+use common::{channels, runtime, seeded_rng};
+
+// Rows of random bytes and random lengths, with an empty row in front.
+fn table<R: Rng>(n: usize, max_len: usize, rng: &mut R) -> Vec<Vec<u8>> {
+    (0..n)
+        .map(|i| if i == 0 { vec![] } else { (0..rng.gen_range(0..=max_len)).map(|_| rng.gen()).collect() })
+        .collect()
+}
+
+#[test]
+fn client_gets_the_rows_it_asks_for() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let mut session = OtExtSession::new(protocol, 128, group, rng).unwrap();
+        for (n, max_len) in [(1, 10), (2, 0), (3, 5), (8, 33), (13, 100), (256, 16), (1000, 4)] {
+            let table = table(n, max_len, rng);
+            let indices = [vec![0, n - 1], (0..5).map(|_| rng.gen_range(0..n)).collect()].concat();
+            let rows = retrieve(&mut session, &table, &indices, rng).unwrap();
+            assert_eq!(rows, indices.iter().map(|&i| table[i].clone()).collect::<Vec<_>>(), "{} rows", n);
+        }
+    }
+}
+
+#[test]
+fn bad_tables_and_indices_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    assert!(matches!(retrieve(&mut session, &[], &[0], rng), Err(OteError::BadParameter(_))));
+    let table = vec![b"a".to_vec(), b"bc".to_vec(), b"".to_vec()];
+    assert!(matches!(retrieve(&mut session, &table, &[1, 3], rng), Err(OteError::BadParameter(_))));
+    assert!(retrieve(&mut session, &table, &[], rng).unwrap().is_empty());
+}
+
+#[test]
+fn pir_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let config = OtExtConfig::default();
+    runtime().block_on(async {
+        for (n, indices) in [(1, vec![0, 0]), (13, vec![12, 0, 5, 5]), (40, vec![])] {
+            let table = table(n, 20, rng);
+            let (mut chan_0, mut chan_1) = channels();
+            let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+            let (count, rows) = tokio::try_join!(
+                pir_server(&mut chan_0, &table, config, group, rng_0),
+                pir_client(&mut chan_1, &indices, config, group, rng_1)
+            )
+            .unwrap();
+            assert_eq!(count, indices.len());
+            assert_eq!(rows, indices.iter().map(|&i| table[i].clone()).collect::<Vec<_>>(), "{} rows", n);
+        }
+    });
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use std::path::PathBuf;
+
+use rand_chacha::ChaCha20Rng;
+
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::pool_file::{load_receiver_pool, load_sender_pool, save_receiver_pool, save_sender_pool, Role};
+use ote::precompute::precompute;
+use ote::{OtExtSession, OteError, Precomputed, Protocol};
+
+use common::{chosen, counting_messages, seeded_rng};
+
+// Length of the header in the file format described in `pool_file`.
+const HEADER_LEN: usize = 48;
+const KEY: &[u8] = b"pool file test key";
+
+// Files in the temp directory, unique per test so the tests can run in parallel.
+fn paths(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir();
+    let id = std::process::id();
+    (
+        dir.join(format!("ote_{}_{}_sender_pool", name, id)),
+        dir.join(format!("ote_{}_{}_receiver_pool", name, id)),
+    )
+}
+
+fn saved_pools(protocol: Protocol, name: &str, rng: &mut ChaCha20Rng) -> (OtExtSession, PathBuf, PathBuf) {
+    let group = &make_test_group(rng).unwrap();
+    let (sender_path, receiver_path) = paths(name);
+    let mut session = OtExtSession::new(protocol, 128, group, rng).unwrap();
+    let (sender, receiver) = precompute(&mut session, 1000, rng).unwrap();
+    // Use some entries before saving, only the rest may end up in the files.
+    let mut pool = Precomputed { sender, receiver };
+    pool.derandomize(&[true; 10], &vec![(vec![false; 8], vec![true; 8]); 10]).unwrap();
+    save_sender_pool(&mut pool.sender, &sender_path, KEY).unwrap();
+    save_receiver_pool(&mut pool.receiver, &receiver_path, KEY).unwrap();
+    // The saved entries are gone from the pool, and files are never overwritten.
+    assert_eq!(pool.remaining(), 0);
+    assert!(matches!(save_sender_pool(&mut pool.sender, &sender_path, KEY), Err(OteError::Io(_))));
+    (session, sender_path, receiver_path)
+}
+
+#[test]
+fn saved_pools_can_be_used_after_loading() {
+    let rng = &mut seeded_rng(0);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let (session, sender_path, receiver_path) = saved_pools(protocol, &format!("load_{:?}", protocol), rng);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&sender_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let mut pool = Precomputed {
+            sender: load_sender_pool(&sender_path, KEY).unwrap(),
+            receiver: load_receiver_pool(&receiver_path, KEY).unwrap(),
+        };
+        assert_eq!(pool.remaining(), 990);
+        assert_eq!(pool.sender.session_id(), session.id());
+        assert_eq!(pool.receiver.protocol(), protocol);
+        let messages = counting_messages(990);
+        let choice = random_boolvec_len(990, rng);
+        assert_eq!(pool.derandomize(&choice, &messages).unwrap(), chosen(&messages, &choice));
+        // Loading used up the files.
+        assert!(matches!(load_sender_pool(&sender_path, KEY), Err(OteError::Io(_))));
+        assert!(matches!(load_receiver_pool(&receiver_path, KEY), Err(OteError::Io(_))));
+    }
+}
+
+#[test]
+fn damaged_or_foreign_files_are_rejected() {
+    let (_, sender_path, receiver_path) = saved_pools(Protocol::Iknp, "damaged", &mut seeded_rng(0));
+    assert!(matches!(
+        load_sender_pool(&receiver_path, KEY),
+        Err(OteError::WrongRole { expected: Role::Sender, found: Role::Receiver })
+    ));
+    assert!(matches!(
+        load_receiver_pool(&receiver_path, b"wrong key"),
+        Err(OteError::IntegrityCheckFailed)
+    ));
+    let mut bytes = std::fs::read(&sender_path).unwrap();
+    bytes[HEADER_LEN + 20] ^= 1;
+    std::fs::write(&sender_path, &bytes).unwrap();
+    assert!(matches!(load_sender_pool(&sender_path, KEY), Err(OteError::IntegrityCheckFailed)));
+    bytes.truncate(bytes.len() - 1);
+    std::fs::write(&sender_path, &bytes).unwrap();
+    assert!(matches!(load_sender_pool(&sender_path, KEY), Err(OteError::InvalidFile(_))));
+    std::fs::write(&sender_path, b"not a pool").unwrap();
+    assert!(matches!(load_sender_pool(&sender_path, KEY), Err(OteError::InvalidFile(_))));
+    std::fs::remove_file(&sender_path).unwrap();
+    assert!(matches!(load_sender_pool(&sender_path, KEY), Err(OteError::Io(_))));
+    std::fs::remove_file(receiver_path).ok();
+}
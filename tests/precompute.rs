@@ -0,0 +1,86 @@
+mod common;
+
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::precompute::{precompute, unmask};
+use ote::{OtExtSession, OteError, Precomputed, Protocol};
+
+use common::{chosen, counting_messages, random_messages, seeded_rng};
+
+#[test]
+fn derandomized_ots_return_chosen_messages() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let mut pool = Precomputed::new(protocol, 1000, 128, group, rng).unwrap();
+        for m in [1, 500, 499] {
+            let messages = counting_messages(m);
+            let choice = random_boolvec_len(m, rng);
+            assert_eq!(pool.derandomize(&choice, &messages).unwrap(), chosen(&messages, &choice));
+        }
+        assert_eq!(pool.remaining(), 0);
+        assert!(matches!(
+            pool.derandomize(&[true], &counting_messages(1)),
+            Err(OteError::PoolExhausted { requested: 1, available: 0 })
+        ));
+    }
+}
+
+#[test]
+fn pool_messages_can_have_any_length() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut pool = Precomputed::new(Protocol::Alsz, 100, 128, group, rng).unwrap();
+    for len in [1, 1000] {
+        let messages = random_messages(50, len, rng);
+        let choice = random_boolvec_len(50, rng);
+        assert_eq!(pool.derandomize(&choice, &messages).unwrap(), chosen(&messages, &choice));
+    }
+}
+
+#[test]
+fn online_phase_split_between_parties() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    let (mut sender, mut receiver) = precompute(&mut session, 10, rng).unwrap();
+    assert_eq!((sender.len(), receiver.len()), (10, 10));
+    let messages = random_messages(10, 64, rng);
+    let choice = random_boolvec_len(10, rng);
+    let (corrections, pads) = receiver.corrections(&choice, 64).unwrap();
+    let y = sender.derandomize(&corrections, &messages).unwrap();
+    assert_eq!(unmask(&y, &choice, &pads), chosen(&messages, &choice));
+    assert_eq!((sender.remaining(), receiver.remaining()), (0, 0));
+}
+
+#[test]
+fn mismatched_inputs_are_rejected() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut pool = Precomputed::new(Protocol::Iknp, 10, 128, group, rng).unwrap();
+    assert!(matches!(
+        pool.derandomize(&[true, false], &counting_messages(1)),
+        Err(OteError::BadParameter(_))
+    ));
+    let messages = vec![(vec![true; 4], vec![true; 5])];
+    assert!(matches!(
+        pool.derandomize(&[true], &messages),
+        Err(OteError::MessageLengthMismatch { index: 0, .. })
+    ));
+    assert_eq!(pool.remaining(), 10);
+}
+
+#[test]
+fn random_entries_agree_on_the_chosen_message() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    let (mut sender, mut receiver) = precompute(&mut session, 20, rng).unwrap();
+    let sent = sender.take_random(20, 16).unwrap();
+    let (choice, received) = receiver.take_random(20, 16).unwrap();
+    for (((r_0, r_1), &c), r_c) in sent.iter().zip(choice.iter()).zip(&received) {
+        assert_eq!(r_c, if c { r_1 } else { r_0 });
+        assert_ne!(r_0, r_1);
+    }
+    assert_eq!((sender.remaining(), receiver.remaining()), (0, 0));
+}
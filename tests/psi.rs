@@ -0,0 +1,78 @@
+mod common;
+
+use ote::ot_primitive::make_test_group;
+use ote::psi::{bins_for, cuckoo_bin, psi, value_bits, CuckooTable, HASHES};
+use ote::{psi_receiver, psi_sender, OteError, SecurityParams};
+
+use common::{channels, runtime, seeded_rng};
+
+fn items(range: std::ops::Range<u32>) -> Vec<Vec<u8>> {
+    range.map(|x| format!("item {}", x).into_bytes()).collect()
+}
+
+#[test]
+fn cuckoo_table_holds_every_item_in_one_of_its_bins() {
+    let rng = &mut seeded_rng(0);
+    for n in [1, 2, 10, 1000] {
+        let set = items(0..n);
+        let table = CuckooTable::new(&set, bins_for(set.len()), rng).unwrap();
+        let mut placed = table.bins.iter().flatten().map(|&(item, _)| item).collect::<Vec<_>>();
+        placed.sort_unstable();
+        assert_eq!(placed, (0..n as usize).collect::<Vec<_>>());
+        for (j, &(item, i)) in table.bins.iter().enumerate().filter_map(|(j, x)| Some((j, x.as_ref()?))) {
+            assert!(i < HASHES);
+            assert_eq!(cuckoo_bin(&table.seed, i, &set[item], table.bins.len()), j);
+        }
+    }
+}
+
+#[test]
+fn psi_finds_the_intersection() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let params = SecurityParams::default();
+    // Repeated items count once.
+    let sender_set = [items(50..250), items(50..60)].concat();
+    let receiver_set = [items(0..100), items(70..72)].concat();
+    assert_eq!(psi(&sender_set, &receiver_set, params, group, rng).unwrap(), items(50..100));
+    assert!(psi(&sender_set, &[], params, group, rng).unwrap().is_empty());
+}
+
+#[test]
+fn psi_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let params = SecurityParams::default();
+    let sender_set = [items(0..30), items(1000..1300)].concat();
+    let receiver_set = items(10..500);
+    let runtime = runtime();
+    let ((), intersection) = runtime.block_on(async {
+        let (mut sender_chan, mut receiver_chan) = channels();
+        let (sender_rng, receiver_rng) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        tokio::try_join!(
+            psi_sender(&mut sender_chan, &sender_set, params, group, sender_rng),
+            psi_receiver(&mut receiver_chan, &receiver_set, params, group, receiver_rng)
+        )
+        .unwrap()
+    });
+    assert_eq!(intersection, items(10..30));
+}
+
+#[test]
+fn receiver_rejects_a_huge_sender_set_size() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let set = items(0..10);
+    let runtime = runtime();
+    let result = runtime.block_on(async {
+        let (mut peer, mut receiver_chan) = channels();
+        let receiver = psi_receiver(&mut receiver_chan, &set, SecurityParams::default(), group, rng);
+        let peer = async {
+            peer.recv_bytes().await?;
+            peer.send_bytes(&u64::MAX.to_be_bytes()).await
+        };
+        tokio::join!(receiver, peer).0
+    });
+    assert!(matches!(result, Err(OteError::MalformedMessage(_))));
+    assert!(value_bits(40, usize::MAX, usize::MAX) > 40);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::{OtExtSession, OteError, Protocol};
+
+use common::{chosen, counting_messages, random_messages, seeded_rng};
+
+#[test]
+fn session_extends_repeatedly() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        for k in [128, 256] {
+            let mut session = OtExtSession::new(protocol, k, group, rng).unwrap();
+            for m in [1, 128, 1000, 7, 0] {
+                let messages = counting_messages(m);
+                let choice = random_boolvec_len(m, rng);
+                let output = session.extend(&messages, &choice).unwrap();
+                assert_eq!(output, chosen(&messages, &choice));
+            }
+            assert_eq!(session.extended(), 1136);
+            assert_eq!((session.protocol(), session.k()), (protocol, k));
+        }
+    }
+}
+
+#[test]
+fn session_message_lengths_may_change_between_calls() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    for len in [1, 500, 3] {
+        let messages = random_messages(20, len, rng);
+        let choice = random_boolvec_len(20, rng);
+        assert_eq!(session.extend(&messages, &choice).unwrap(), chosen(&messages, &choice));
+    }
+}
+
+#[test]
+fn session_rejects_bad_inputs_without_consuming() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    assert!(matches!(OtExtSession::new(Protocol::Iknp, 64, group, rng), Err(OteError::BadParameter(_))));
+    let mut session = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    assert!(matches!(
+        session.extend(&counting_messages(2), &[true]),
+        Err(OteError::BadParameter(_))
+    ));
+    assert_eq!(session.extended(), 0);
+}
+
+#[test]
+fn sessions_have_distinct_ids() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let a = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    let b = OtExtSession::new(Protocol::Iknp, 128, group, rng).unwrap();
+    assert_ne!(a.id(), b.id());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use sha3::{Digest, Sha3_256};
+
+use ote::common::{ct_choice, select_boolvec};
+use ote::timing::{measure, Welch};
+
+use common::seeded_rng;
+
+// Hashes `blocks` KiB, the work whose duration is measured.
+fn work(blocks: usize) {
+    let mut hasher = Sha3_256::new();
+    (0..blocks).for_each(|_| hasher.update([0; 1024]));
+    std::hint::black_box(hasher.finalize());
+}
+
+#[test]
+fn welch_t_separates_shifted_classes() {
+    let mut same = Welch::new();
+    let mut shifted = Welch::new();
+    for i in 0..1000 {
+        let noise = (i / 2 % 10) as f64;
+        same.push(i % 2 == 0, 100.0 + noise);
+        shifted.push(i % 2 == 0, 100.0 + noise + if i % 2 == 0 { 5.0 } else { 0.0 });
+    }
+    assert_eq!(same.samples(), 1000);
+    assert!(same.t().abs() < 1.0);
+    assert!(shifted.t() < -10.0);
+    assert_eq!(Welch::new().t(), 0.0);
+}
+
+// Measures wall-clock time, so it only runs on demand on an otherwise idle machine:
+// cargo test --test timing -- --ignored
+#[test]
+#[ignore = "wall-clock timing is unreliable under parallel tests and on loaded machines"]
+fn branching_on_the_class_is_flagged() {
+    let rng = &mut seeded_rng(0);
+    let report = measure("branch", 2000, rng, |class, _| class, |&class| work(if class { 8 } else { 1 }));
+    assert!(report.leaks(), "t = {}", report.t);
+    let report = measure("no branch", 2000, rng, |class, _| class, |_| work(4));
+    assert!(!report.leaks(), "t = {}", report.t);
+}
+
+#[test]
+fn select_picks_by_choice() {
+    let (l, r) = (vec![true, false, true], vec![false, false, true]);
+    assert_eq!(select_boolvec(&l, &r, ct_choice(false)), l);
+    assert_eq!(select_boolvec(&l, &r, ct_choice(true)), r);
+}
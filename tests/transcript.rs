@@ -0,0 +1,137 @@
+mod common;
+
+use ote::common::{random_boolvec_len, MessagePairs};
+use ote::ot_primitive::{make_test_group, SafePrimeGroup};
+use ote::transcript::{replay_channel, Direction, Transcript};
+use ote::{OtExtConfig, OtExtReceiver, OtExtSender, OteError, Protocol, SecurityParams};
+
+use common::{channels, random_messages, runtime, seeded_rng};
+
+const SENDER_SEED: u64 = 1;
+const RECEIVER_SEED: u64 = 2;
+
+fn config(protocol: Protocol) -> OtExtConfig {
+    OtExtConfig::new(protocol, SecurityParams::default())
+}
+
+// Runs both parties over recording channels and returns the sender's and the receiver's transcript.
+fn recorded_run(protocol: Protocol, group: &SafePrimeGroup, messages: &MessagePairs, choice: &[bool]) -> (Transcript, Transcript) {
+    let sender = OtExtSender::new(config(protocol), group.clone());
+    let receiver = OtExtReceiver::new(config(protocol), group.clone());
+    runtime().block_on(async {
+        let (mut sender_chan, mut receiver_chan) = channels();
+        sender_chan.record();
+        receiver_chan.record();
+        let (sender_rng, receiver_rng) = (&mut seeded_rng(SENDER_SEED), &mut seeded_rng(RECEIVER_SEED));
+        tokio::try_join!(
+            sender.send(&mut sender_chan, messages.clone(), sender_rng),
+            receiver.receive(&mut receiver_chan, choice.to_vec(), receiver_rng)
+        )
+        .unwrap();
+        (sender_chan.take_transcript().unwrap(), receiver_chan.take_transcript().unwrap())
+    })
+}
+
+// Replays the receiver against its recorded transcript and returns its output and the new transcript.
+fn replay_receiver(protocol: Protocol, group: &SafePrimeGroup, recorded: &Transcript, choice: &[bool]) -> (Result<Vec<Vec<bool>>, OteError>, Transcript) {
+    let receiver = OtExtReceiver::new(config(protocol), group.clone());
+    runtime().block_on(async {
+        let mut chan = replay_channel(recorded);
+        let output = receiver.receive(&mut chan, choice.to_vec(), &mut seeded_rng(RECEIVER_SEED)).await;
+        (output, chan.take_transcript().unwrap())
+    })
+}
+
+#[test]
+fn both_parties_record_the_same_labelled_messages() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(100, 64, rng);
+    let choice = random_boolvec_len(100, rng);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let (sender, receiver) = recorded_run(protocol, group, &messages, &choice);
+        let labels = sender.messages().iter().map(|x| (x.direction, x.label.as_str())).collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                (Direction::Sent, "base-ot keys"),
+                (Direction::Received, "base-ot ciphertexts"),
+                (Direction::Received, "u_i"),
+                (Direction::Sent, "y_j"),
+            ]
+        );
+        for (s, r) in sender.messages().iter().zip(receiver.messages()) {
+            assert_ne!(s.direction, r.direction);
+            assert_eq!((&s.label, &s.bytes), (&r.label, &r.bytes));
+        }
+        assert!(sender.to_string().contains("base-ot ciphertexts"));
+    }
+}
+
+#[test]
+fn replaying_a_party_reproduces_it() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(100, 64, rng);
+    let choice = random_boolvec_len(100, rng);
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let (sender, receiver) = recorded_run(protocol, group, &messages, &choice);
+        let (output, replayed) = replay_receiver(protocol, group, &receiver, &choice);
+        assert_eq!(replayed, receiver);
+        assert_eq!(receiver.describe_divergence(&replayed), None);
+        assert_eq!(output.unwrap(), common::chosen(&messages, &choice));
+
+        let replayed = runtime().block_on(async {
+            let mut chan = replay_channel(&sender);
+            OtExtSender::new(config(protocol), group.clone())
+                .send(&mut chan, messages.clone(), &mut seeded_rng(SENDER_SEED))
+                .await
+                .unwrap();
+            chan.take_transcript().unwrap()
+        });
+        assert_eq!(sender.first_divergence(&replayed), None);
+    }
+}
+
+#[test]
+fn replay_points_at_the_first_divergent_message() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(100, 64, rng);
+    let choice = random_boolvec_len(100, rng);
+    let (_, receiver) = recorded_run(Protocol::Alsz, group, &messages, &choice);
+
+    // Another choice bit first shows in the corrections u_i.
+    let mut other_choice = choice.clone();
+    other_choice[37] ^= true;
+    let (_, replayed) = replay_receiver(Protocol::Alsz, group, &receiver, &other_choice);
+    assert_eq!(receiver.first_divergence(&replayed), Some(2));
+    let report = receiver.describe_divergence(&replayed).unwrap();
+    assert!(report.contains("u_i"), "{}", report);
+
+    // A recording that ends early makes the replayed party fail on the missing message.
+    let mut truncated = Transcript::new();
+    receiver.messages()[..3].iter().for_each(|x| truncated.push(x.direction, &x.label, &x.bytes));
+    let (output, replayed) = replay_receiver(Protocol::Alsz, group, &truncated, &choice);
+    assert!(matches!(output, Err(OteError::Io(_))));
+    assert_eq!(replayed, truncated);
+    assert!(receiver.describe_divergence(&replayed).unwrap().contains("replay stopped before message 3"));
+}
+
+#[test]
+fn transcripts_round_trip_through_files() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let messages = random_messages(10, 8, rng);
+    let choice = random_boolvec_len(10, rng);
+    let (sender, _) = recorded_run(Protocol::Iknp, group, &messages, &choice);
+    let path = std::env::temp_dir().join(format!("ote_transcript_{}", std::process::id()));
+    sender.save(&path).unwrap();
+    assert_eq!(Transcript::load(&path).unwrap(), sender);
+    std::fs::remove_file(&path).ok();
+
+    let bytes = sender.to_bytes();
+    for bad in [&bytes[..bytes.len() - 1], &bytes[1..], &[bytes.as_slice(), &[0]].concat()] {
+        assert!(matches!(Transcript::from_bytes(bad), Err(OteError::InvalidFile(_))));
+    }
+}
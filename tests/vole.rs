@@ -0,0 +1,158 @@
+mod common;
+
+use rand::Rng;
+
+use ote::arith::Modulus;
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::transcript::{replay_channel, Transcript};
+use ote::vole::{
+    gf128_mul, vole_fp, vole_fp_receiver, vole_fp_sender, vole_gf128, vole_gf128_receiver, vole_gf128_sender, Gf128Receiver, Gf128Sender, VoleMode,
+};
+use ote::{OtExtConfig, OtExtReceiver, OtExtSender, OtExtSession, OteError, Protocol, SafePrimeGroup, SecurityParams};
+
+use common::{channels, runtime, seeded_rng};
+
+fn parties(protocol: Protocol, group: &SafePrimeGroup) -> (OtExtSender, OtExtReceiver) {
+    let config = OtExtConfig::new(protocol, SecurityParams::default());
+    (OtExtSender::new(config, group.clone()), OtExtReceiver::new(config, group.clone()))
+}
+
+// Runs a VOLE over GF(2^128) over a channel and returns both outputs and the sender's transcript.
+fn gf128_over_channel(protocol: Protocol, group: &SafePrimeGroup, u: &[bool], mode: VoleMode) -> (Gf128Sender, Gf128Receiver, Transcript) {
+    let (sender, receiver) = parties(protocol, group);
+    runtime().block_on(async {
+        let (mut chan_0, mut chan_1) = channels();
+        chan_0.record();
+        let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        let (s, r) = tokio::try_join!(
+            vole_gf128_sender(&mut chan_0, &sender, u.len(), mode, rng_0),
+            vole_gf128_receiver(&mut chan_1, &receiver, u, mode, rng_1)
+        )
+        .unwrap();
+        (s, r, chan_0.take_transcript().unwrap())
+    })
+}
+
+// The recorded transcript with one bit of the receiver's x or z flipped, as a cheating receiver would send them.
+fn tampered(recorded: &Transcript, byte: usize) -> Transcript {
+    let mut transcript = Transcript::new();
+    for message in recorded.messages() {
+        let mut bytes = message.bytes.clone();
+        if message.label == "vole x,z" {
+            bytes[byte] ^= 1;
+        }
+        transcript.push(message.direction, &message.label, &bytes);
+    }
+    transcript
+}
+
+#[test]
+fn gf128_multiplication() {
+    let rng = &mut seeded_rng(0);
+    // x^127 * x = x^128 = x^7 + x^2 + x + 1.
+    assert_eq!(gf128_mul(1 << 127, 2), 0x87);
+    for _ in 0..20 {
+        let (a, b, c) = (rng.gen::<u128>(), rng.gen::<u128>(), rng.gen::<u128>());
+        assert_eq!(gf128_mul(a, 1), a);
+        assert_eq!(gf128_mul(a, b), gf128_mul(b, a));
+        assert_eq!(gf128_mul(a, b ^ c), gf128_mul(a, b) ^ gf128_mul(a, c));
+        assert_eq!(gf128_mul(gf128_mul(a, b), c), gf128_mul(a, gf128_mul(b, c)));
+    }
+}
+
+#[test]
+fn gf128_outputs_are_correlated() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    for protocol in [Protocol::Iknp, Protocol::Alsz] {
+        let mut session = OtExtSession::new(protocol, 128, group, rng).unwrap();
+        let u = random_boolvec_len(300, rng);
+        let (local_sender, local_receiver) = vole_gf128(&mut session, &u).unwrap();
+        for mode in [VoleMode::SemiHonest, VoleMode::Malicious] {
+            let (sender, receiver, _) = gf128_over_channel(protocol, group, &u, mode);
+            for (sender, receiver) in [(&local_sender, &local_receiver), (&sender, &receiver)] {
+                // The masks of malicious mode are gone after the check.
+                assert_eq!((sender.b.len(), &receiver.u[..]), (300, &u[..]));
+                for (j, &u_j) in u.iter().enumerate() {
+                    let expected = if u_j { sender.b[j] ^ *sender.delta } else { sender.b[j] };
+                    assert_eq!(receiver.w[j], expected);
+                }
+            }
+        }
+    }
+    let mut session = OtExtSession::new(Protocol::Alsz, 192, group, rng).unwrap();
+    assert!(matches!(vole_gf128(&mut session, &[true]), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn fp_outputs_are_correlated() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let mut session = OtExtSession::new(Protocol::Alsz, 128, group, rng).unwrap();
+    let (sender, receiver) = parties(Protocol::Iknp, group);
+    for p in [2, 101, (1 << 61) - 1] {
+        let modulus = Modulus::prime(p).unwrap();
+        let delta = rng.gen_range(0..p);
+        let u = (0..100).map(|_| rng.gen_range(0..p)).collect::<Vec<_>>();
+        let mut outputs = vec![vole_fp(&mut session, modulus, delta, &u).unwrap()];
+        for mode in [VoleMode::SemiHonest, VoleMode::Malicious] {
+            outputs.push(runtime().block_on(async {
+                let (mut chan_0, mut chan_1) = channels();
+                let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+                tokio::try_join!(
+                    vole_fp_sender(&mut chan_0, &sender, modulus, delta, u.len(), mode, rng_0),
+                    vole_fp_receiver(&mut chan_1, &receiver, modulus, &u, mode, rng_1)
+                )
+                .unwrap()
+            }));
+        }
+        for (sender, receiver) in &outputs {
+            assert_eq!((sender.b.len(), &receiver.u[..]), (100, &u[..]));
+            for (j, &u_j) in u.iter().enumerate() {
+                assert_eq!(receiver.w[j], modulus.add(sender.b[j], modulus.mul(u_j, delta)));
+            }
+        }
+    }
+    let modulus = Modulus::prime(101).unwrap();
+    assert!(matches!(vole_fp(&mut session, modulus, 101, &[1]), Err(OteError::BadParameter(_))));
+    assert!(matches!(vole_fp(&mut session, Modulus::power_of_two(8).unwrap(), 1, &[1]), Err(OteError::BadParameter(_))));
+}
+
+#[test]
+fn the_sender_catches_an_inconsistent_receiver() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let u = random_boolvec_len(50, rng);
+    let (sender, _) = parties(Protocol::Iknp, group);
+    let (_, _, recorded) = gf128_over_channel(Protocol::Iknp, group, &u, VoleMode::Malicious);
+    // The sender replayed with the same randomness against an x or a z that does not fit the receiver's output.
+    for byte in [0, 31] {
+        let result = runtime().block_on(async {
+            let mut chan = replay_channel(&tampered(&recorded, byte));
+            vole_gf128_sender(&mut chan, &sender, u.len(), VoleMode::Malicious, &mut seeded_rng(1)).await
+        });
+        assert!(matches!(result, Err(OteError::ConsistencyCheckFailed(_))));
+    }
+
+    let modulus = Modulus::prime(101).unwrap();
+    let u = (0..50).map(|_| rng.gen_range(0..101)).collect::<Vec<_>>();
+    let (sender, receiver) = parties(Protocol::Alsz, group);
+    let recorded = runtime().block_on(async {
+        let (mut chan_0, mut chan_1) = channels();
+        chan_0.record();
+        let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+        tokio::try_join!(
+            vole_fp_sender(&mut chan_0, &sender, modulus, 42, u.len(), VoleMode::Malicious, rng_0),
+            vole_fp_receiver(&mut chan_1, &receiver, modulus, &u, VoleMode::Malicious, rng_1)
+        )
+        .unwrap();
+        chan_0.take_transcript().unwrap()
+    });
+    // The last byte of x of the first repetition, which stays an element of F_101.
+    let result = runtime().block_on(async {
+        let mut chan = replay_channel(&tampered(&recorded, 7));
+        vole_fp_sender(&mut chan, &sender, modulus, 42, u.len(), VoleMode::Malicious, &mut seeded_rng(1)).await
+    });
+    assert!(matches!(result, Err(OteError::ConsistencyCheckFailed(_))));
+}
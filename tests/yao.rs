@@ -0,0 +1,76 @@
+mod common;
+
+use std::path::Path;
+
+use ote::circuit::Circuit;
+use ote::common::random_boolvec_len;
+use ote::ot_primitive::make_test_group;
+use ote::yao::{decode, evaluate, garble, yao};
+use ote::{yao_evaluator, yao_garbler, OtExtConfig, OteError};
+
+use common::{channels, runtime, seeded_rng};
+
+fn load(name: &str) -> Circuit {
+    Circuit::load(&Path::new("tests/circuits").join(name)).unwrap()
+}
+
+fn bits(x: u64, n: usize) -> Vec<bool> {
+    (0..n).map(|i| x >> i & 1 == 1).collect()
+}
+
+fn value(bits: &[bool]) -> u64 {
+    bits.iter().rev().fold(0, |acc, &b| acc << 1 | b as u64)
+}
+
+#[test]
+fn garbled_circuits_match_plaintext() {
+    let rng = &mut seeded_rng(0);
+    for name in ["adder8.txt", "compare8.txt", "misc.txt"] {
+        let circuit = load(name);
+        let (garbled, encoding) = garble(&circuit, rng).unwrap();
+        // Two rows per AND gate and nothing for the others.
+        assert_eq!(garbled.tables.len(), circuit.and_gates());
+        for _ in 0..100 {
+            let inputs = circuit.inputs.iter().map(|&n| random_boolvec_len(n, rng)).collect::<Vec<_>>();
+            let labels = inputs.concat().iter().enumerate().map(|(w, &x)| encoding.label(w, x)).collect::<Vec<_>>();
+            let output = decode(&garbled, &evaluate(&circuit, &garbled, &labels).unwrap());
+            assert_eq!(circuit.split_outputs(&output), circuit.evaluate(&inputs).unwrap(), "{}", name);
+        }
+        let labels = vec![0; circuit.inputs.iter().sum::<usize>() + 1];
+        assert!(matches!(evaluate(&circuit, &garbled, &labels), Err(OteError::MalformedMessage(_))));
+    }
+}
+
+#[test]
+fn yao_computes_sums_and_comparisons() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let (adder, compare) = (load("adder8.txt"), load("compare8.txt"));
+    for (x, y) in [(0, 0), (255, 1), (100, 155), (17, 200)] {
+        let (x_bits, y_bits) = (bits(x, 8), bits(y, 8));
+        assert_eq!(value(&yao(&adder, [&x_bits, &y_bits], 128, group, rng).unwrap()), (x + y) % 256);
+        assert_eq!(yao(&compare, [&x_bits, &y_bits], 128, group, rng).unwrap(), [x > y]);
+    }
+}
+
+#[test]
+fn yao_over_channel() {
+    let rng = &mut seeded_rng(0);
+    let group = &make_test_group(rng).unwrap();
+    let circuit = load("compare8.txt");
+    let config = OtExtConfig::default();
+    for (x, y) in [(200, 199), (199, 200)] {
+        let (garbler, evaluator) = runtime().block_on(async {
+            let (mut chan_0, mut chan_1) = channels();
+            let (rng_0, rng_1) = (&mut seeded_rng(1), &mut seeded_rng(2));
+            let (input_0, input_1) = (bits(x, 8), bits(y, 8));
+            tokio::try_join!(
+                yao_garbler(&mut chan_0, &circuit, &input_0, config, group, rng_0),
+                yao_evaluator(&mut chan_1, &circuit, &input_1, config, group, rng_1)
+            )
+            .unwrap()
+        });
+        assert_eq!(garbler, [x > y]);
+        assert_eq!(evaluator, garbler);
+    }
+}